@@ -0,0 +1,416 @@
+//! # Audio Processor Module
+//!
+//! Ottimizzazione dei file audio (memo vocali, export WAV, musica) con ffmpeg.
+//!
+//! ## Responsabilità:
+//! - Analizza la sorgente con ffprobe (codec, bitrate, canali, copertina)
+//! - Decide se transcodificare o lasciare il file com'è ([`AudioDecision`])
+//! - Transcodifica in FLAC (lossless), Opus o AAC secondo `config.audio_format`
+//! - Conserva i tag (`-map_metadata 0`) e, in FLAC/M4A, la copertina
+//! - Applica downmix (`audio_channels`) e loudness (`loudness_target`)
+//!
+//! ## Formati:
+//! | Formato | Encoder | Estensione | Qualità                     |
+//! |---------|---------|------------|-----------------------------|
+//! | FLAC    | flac    | `.flac`    | lossless, `-compression_level 12` |
+//! | Opus    | libopus | `.opus`    | `audio_bitrate`             |
+//! | AAC     | aac     | `.m4a`     | `audio_bitrate`             |
+//!
+//! ## Decisione:
+//! - Sorgenti lossless (PCM, FLAC, ALAC...) vengono sempre transcodificate
+//! - Una sorgente lossy non viene mai convertita in FLAC: crescerebbe soltanto
+//! - Tra formati lossy si transcodifica solo se il bitrate sorgente supera il
+//!   bitrate di destinazione oltre la soglia di sostituzione
+//! - Il PCM in virgola mobile non è rappresentabile in FLAC senza perdite
+//!
+//! I file non transcodificati mantengono la loro estensione; la soglia
+//! `size_threshold` decide comunque se l'output sostituisce l'originale.
+
+use crate::config::{AudioFormat, Config};
+use crate::error::OptimizeError;
+use crate::ffmpeg;
+use crate::file_manager::FileManager;
+use crate::optimizer::path_resolver::PathResolver;
+use crate::progress::ProgressCallback;
+use crate::utils::{parse_bitrate, to_string_vec};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+
+/// Estensioni di contenitori che trasportano (quasi) sempre audio lossless
+const LOSSLESS_EXTENSIONS: &[&str] = &["wav", "aif", "aiff", "flac"];
+
+/// Codec ffprobe lossless (oltre a tutti i `pcm_*`)
+const LOSSLESS_CODECS: &[&str] = &["flac", "alac", "wavpack", "ape", "tta", "mlp", "truehd"];
+
+impl AudioFormat {
+    /// Estensione dei file prodotti
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+            Self::Aac => "m4a",
+        }
+    }
+
+    /// Encoder ffmpeg
+    pub fn encoder(self) -> &'static str {
+        match self {
+            Self::Flac => "flac",
+            Self::Opus => "libopus",
+            Self::Aac => "aac",
+        }
+    }
+
+    /// Nome del codec in ffprobe
+    fn codec_name(self) -> &'static str {
+        match self {
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+            Self::Aac => "aac",
+        }
+    }
+
+    pub fn is_lossless(self) -> bool {
+        self == Self::Flac
+    }
+
+    /// Il container accetta una copertina (stream video `attached_pic`)
+    fn supports_cover(self) -> bool {
+        self != Self::Opus
+    }
+}
+
+/// Informazioni sul primo stream audio di un file
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioInfo {
+    /// Durata in secondi
+    pub duration: f64,
+    /// Bitrate complessivo in bit/s (0 se sconosciuto)
+    pub bitrate: u64,
+    /// Codec ffprobe (es. "pcm_s16le", "mp3")
+    pub codec: String,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+    /// Il file contiene una copertina
+    pub has_cover: bool,
+}
+
+impl AudioInfo {
+    /// Estrae le informazioni dal JSON di ffprobe (`-show_format -show_streams`)
+    pub fn from_ffprobe_json(json_str: &str) -> Result<Self> {
+        let info: serde_json::Value = serde_json::from_str(json_str)?;
+        let format = &info["format"];
+        let empty = vec![];
+        let streams = info["streams"].as_array().unwrap_or(&empty);
+
+        let audio = streams.iter()
+            .find(|s| s["codec_type"] == "audio")
+            .ok_or_else(|| OptimizeError::UnsupportedFormat("no audio stream".to_string()))?;
+        let has_cover = streams.iter()
+            .any(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"] == 1);
+
+        let number = |value: &serde_json::Value| value.as_str().and_then(|v| v.parse::<f64>().ok());
+        Ok(Self {
+            duration: number(&format["duration"]).unwrap_or(0.0),
+            bitrate: number(&format["bit_rate"]).or_else(|| number(&audio["bit_rate"])).unwrap_or(0.0) as u64,
+            codec: audio["codec_name"].as_str().unwrap_or("unknown").to_string(),
+            channels: audio["channels"].as_u64().map(|c| c as u32),
+            sample_rate: number(&audio["sample_rate"]).map(|r| r as u32),
+            has_cover,
+        })
+    }
+
+    /// La sorgente è lossless (PCM o codec lossless)
+    pub fn is_lossless(&self) -> bool {
+        self.codec.starts_with("pcm_") || LOSSLESS_CODECS.contains(&self.codec.as_str())
+    }
+
+    /// PCM in virgola mobile (32/64 bit float): FLAC lo convertirebbe in interi
+    pub fn is_float_pcm(&self) -> bool {
+        self.codec.starts_with("pcm_f")
+    }
+}
+
+/// Azione decisa dall'analisi di un file audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioAction {
+    Transcode,
+    Skip,
+}
+
+/// Esito dell'analisi di un file audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioDecision {
+    pub action: AudioAction,
+    pub reason: String,
+}
+
+impl AudioDecision {
+    fn transcode(reason: impl Into<String>) -> Self {
+        Self { action: AudioAction::Transcode, reason: reason.into() }
+    }
+
+    fn skip(reason: impl Into<String>) -> Self {
+        Self { action: AudioAction::Skip, reason: reason.into() }
+    }
+}
+
+/// Ottimizzatore dei file audio
+pub struct AudioProcessor {
+    config: Config,
+    stop_receiver: Option<broadcast::Receiver<()>>,
+    progress: Option<ProgressCallback>,
+}
+
+impl AudioProcessor {
+    pub fn new(config: Config) -> Self {
+        Self { config, stop_receiver: None, progress: None }
+    }
+
+    /// Crea un processore che interrompe l'elaborazione alla ricezione dello stop
+    pub fn new_with_cancellation(config: Config, stop_receiver: broadcast::Receiver<()>) -> Self {
+        Self { config, stop_receiver: Some(stop_receiver), progress: None }
+    }
+
+    /// Imposta la callback per l'avanzamento di ffmpeg
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress = Some(callback);
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if let Some(ref mut receiver) = self.stop_receiver {
+            matches!(receiver.try_recv(), Ok(()) | Err(broadcast::error::TryRecvError::Lagged(_)))
+        } else {
+            false
+        }
+    }
+
+    /// Indica se, in base all'estensione, un file audio cambierà formato.
+    ///
+    /// Senza analisi si assume lossless solo WAV/AIFF/FLAC: le sorgenti lossy
+    /// non vengono convertite in un formato lossless.
+    pub fn converts_extension(input_path: &Path, format: AudioFormat) -> bool {
+        let lossless = input_path.extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .is_some_and(|ext| LOSSLESS_EXTENSIONS.contains(&ext.as_str()));
+        lossless || !format.is_lossless()
+    }
+
+    /// Ottimizza un file audio.
+    ///
+    /// # Returns
+    /// Il path del risultato: in modalità output è il file nella directory di
+    /// output (una copia dell'originale se non transcodificato); in-place è un
+    /// file temporaneo che `TaskOptimizer` sposta al posto dell'originale.
+    pub async fn optimize(&mut self, input_path: &Path, input_base_dir: &Path) -> Result<PathBuf> {
+        if self.should_stop() {
+            return Err(OptimizeError::Cancelled("Audio optimization".to_string()).into());
+        }
+
+        let source_metadata = tokio::fs::metadata(input_path).await?;
+        let info = AudioInfo::from_ffprobe_json(&ffmpeg::probe(input_path).await?)?;
+        let decision = Self::plan(&self.config, &info);
+        info!(
+            "🎵 {}: {:?} ({})",
+            input_path.file_name().unwrap_or_default().to_string_lossy(),
+            decision.action,
+            decision.reason
+        );
+
+        if decision.action == AudioAction::Skip {
+            let output_path = PathResolver::get_passthrough_path(input_path, input_base_dir, &self.config)?;
+            if output_path != input_path {
+                PathResolver::ensure_parent_dirs(&output_path).await?;
+                tokio::fs::copy(input_path, &output_path).await?;
+                FileManager::apply_file_attributes(&output_path, &source_metadata, self.config.preserve_ownership).await?;
+            }
+            return Ok(output_path);
+        }
+
+        let format = self.config.audio_format;
+        let temp_file = NamedTempFile::with_suffix(format!(".{}", format.extension()))?;
+        let mut args = to_string_vec(["-i", &input_path.to_string_lossy()]);
+        args.extend(Self::transcode_args(&self.config, &info));
+        args.extend(to_string_vec(["-y", &temp_file.path().to_string_lossy()]));
+
+        let start_time = std::time::Instant::now();
+        ffmpeg::run(&args, input_path, Some(info.duration), self.progress.as_ref()).await?;
+        if self.should_stop() {
+            return Err(OptimizeError::Cancelled("Audio optimization".to_string()).into());
+        }
+        debug!("Audio transcode of {} completed in {:.1}s", input_path.display(), start_time.elapsed().as_secs_f64());
+
+        if self.config.output_path.is_none() {
+            // In-place: l'originale viene sostituito solo se la soglia è rispettata
+            return Ok(temp_file.into_temp_path().keep()?);
+        }
+
+        let output_path = PathResolver::get_output_path_with_extension(
+            input_path, input_base_dir, &self.config, format.extension()
+        )?;
+        PathResolver::ensure_parent_dirs(&output_path).await?;
+        tokio::fs::copy(temp_file.path(), &output_path).await?;
+        FileManager::apply_file_attributes(&output_path, &source_metadata, self.config.preserve_ownership).await?;
+        Ok(output_path)
+    }
+
+    /// Decide se transcodificare un file audio (vedi la documentazione del modulo)
+    pub fn plan(config: &Config, info: &AudioInfo) -> AudioDecision {
+        let format = config.audio_format;
+
+        if format.is_lossless() && info.is_float_pcm() {
+            return AudioDecision::skip(format!("{} cannot be stored losslessly in FLAC", info.codec));
+        }
+        if info.is_lossless() {
+            return AudioDecision::transcode(format!("lossless {} source", info.codec));
+        }
+        if format.is_lossless() {
+            return AudioDecision::skip(format!("lossy {} source: FLAC would only make it larger", info.codec));
+        }
+        if Self::needs_processing(config, info) {
+            return AudioDecision::transcode("downmix or loudness normalization requested");
+        }
+        if info.bitrate == 0 {
+            return AudioDecision::transcode(format!("{} source with unknown bitrate", info.codec));
+        }
+
+        let target_bitrate = parse_bitrate(&config.audio_bitrate).unwrap_or(128_000);
+        if (info.bitrate as f64) * config.size_threshold > target_bitrate as f64 {
+            AudioDecision::transcode(format!("{} at {} kbps", info.codec, info.bitrate / 1000))
+        } else if info.codec == format.codec_name() {
+            AudioDecision::skip(format!("already {} at {} kbps", info.codec, info.bitrate / 1000))
+        } else {
+            AudioDecision::skip(format!("{} at {} kbps is below the target bitrate", info.codec, info.bitrate / 1000))
+        }
+    }
+
+    /// Downmix o normalizzazione richiesti per questa sorgente
+    fn needs_processing(config: &Config, info: &AudioInfo) -> bool {
+        config.loudness_target.is_some() || Self::downmix_channels(config, info).is_some()
+    }
+
+    /// Canali di destinazione, solo se la sorgente ne ha di più (mai upmix)
+    fn downmix_channels(config: &Config, info: &AudioInfo) -> Option<u32> {
+        config.audio_channels.filter(|max| info.channels.is_none_or(|channels| channels > *max))
+    }
+
+    /// Argomenti ffmpeg tra input e output per la transcodifica
+    pub fn transcode_args(config: &Config, info: &AudioInfo) -> Vec<String> {
+        let format = config.audio_format;
+        let mut args = to_string_vec(["-map", "0:a:0"]);
+        if info.has_cover && format.supports_cover() {
+            args.extend(to_string_vec(["-map", "0:v:0", "-c:v", "copy", "-disposition:v:0", "attached_pic"]));
+        }
+
+        args.extend(to_string_vec(["-c:a", format.encoder()]));
+        if format.is_lossless() {
+            args.extend(to_string_vec(["-compression_level", "12"]));
+        } else {
+            args.extend(to_string_vec(["-b:a", &config.audio_bitrate]));
+        }
+        if let Some(channels) = Self::downmix_channels(config, info) {
+            args.extend(to_string_vec(["-ac", &channels.to_string()]));
+        }
+        if let Some(lufs) = config.loudness_target {
+            // loudnorm lavora a 192 kHz: si torna alla frequenza della sorgente,
+            // tranne per libopus che accetta solo 48/24/16/12/8 kHz
+            let sample_rate = match format {
+                AudioFormat::Opus => 48_000,
+                _ => info.sample_rate.unwrap_or(48_000),
+            }.to_string();
+            args.extend(to_string_vec(["-af", &format!("loudnorm=I={}:TP=-1.5:LRA=11", lufs), "-ar", &sample_rate]));
+        }
+
+        args.extend(to_string_vec(["-map_metadata", "0"]));
+        if format == AudioFormat::Aac {
+            args.extend(to_string_vec(["-movflags", "+faststart"]));
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(codec: &str, bitrate: u64, channels: u32, cover: bool) -> AudioInfo {
+        let mut streams = vec![serde_json::json!({
+            "codec_type": "audio",
+            "codec_name": codec,
+            "channels": channels,
+            "sample_rate": "44100"
+        })];
+        if cover {
+            streams.push(serde_json::json!({
+                "codec_type": "video",
+                "codec_name": "mjpeg",
+                "disposition": { "attached_pic": 1 }
+            }));
+        }
+        let json = serde_json::json!({
+            "format": { "duration": "180.0", "bit_rate": bitrate.to_string() },
+            "streams": streams
+        });
+        AudioInfo::from_ffprobe_json(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_audio_plan() {
+        let flac = Config::default();
+        let opus = Config { audio_format: AudioFormat::Opus, audio_bitrate: "96k".to_string(), ..Default::default() };
+
+        let wav = probe("pcm_s16le", 1_411_200, 2, false);
+        assert_eq!(wav.sample_rate, Some(44_100));
+        assert_eq!(AudioProcessor::plan(&flac, &wav).action, AudioAction::Transcode);
+        assert_eq!(AudioProcessor::plan(&opus, &wav).action, AudioAction::Transcode);
+
+        // Lossy sources never become FLAC; float PCM is not lossless in FLAC
+        let mp3 = probe("mp3", 320_000, 2, true);
+        assert_eq!(AudioProcessor::plan(&flac, &mp3).action, AudioAction::Skip);
+        assert_eq!(AudioProcessor::plan(&flac, &probe("pcm_f32le", 2_822_400, 2, false)).action, AudioAction::Skip);
+
+        // Lossy to lossy only when the source bitrate is well above the target
+        assert_eq!(AudioProcessor::plan(&opus, &mp3).action, AudioAction::Transcode);
+        assert_eq!(AudioProcessor::plan(&opus, &probe("mp3", 96_000, 2, false)).action, AudioAction::Skip);
+        let loud = Config { loudness_target: Some(-16.0), ..opus.clone() };
+        assert_eq!(AudioProcessor::plan(&loud, &probe("opus", 64_000, 1, false)).action, AudioAction::Transcode);
+
+        assert!(AudioProcessor::converts_extension(Path::new("/a/memo.WAV"), AudioFormat::Flac));
+        assert!(!AudioProcessor::converts_extension(Path::new("/a/song.mp3"), AudioFormat::Flac));
+        assert!(AudioProcessor::converts_extension(Path::new("/a/song.mp3"), AudioFormat::Aac));
+    }
+
+    #[test]
+    fn test_transcode_args() {
+        let wav = probe("pcm_s24le", 2_116_800, 6, false);
+        let args = AudioProcessor::transcode_args(&Config::default(), &wav);
+        assert_eq!(args, ["-map", "0:a:0", "-c:a", "flac", "-compression_level", "12", "-map_metadata", "0"]);
+
+        // Cover art kept in M4A, downmix to stereo, loudness back at the source rate
+        let config = Config {
+            audio_format: AudioFormat::Aac,
+            audio_channels: Some(2),
+            loudness_target: Some(-16.0),
+            ..Default::default()
+        };
+        let args = AudioProcessor::transcode_args(&config, &probe("flac", 900_000, 6, true));
+        assert!(args.windows(2).any(|w| w == ["-disposition:v:0", "attached_pic"]));
+        assert!(args.windows(2).any(|w| w == ["-b:a", "128k"]));
+        assert!(args.windows(2).any(|w| w == ["-ac", "2"]));
+        assert!(args.windows(2).any(|w| w == ["-ar", "44100"]));
+
+        // Ogg cannot hold the cover
+        let opus = Config { audio_format: AudioFormat::Opus, ..Default::default() };
+        let args = AudioProcessor::transcode_args(&opus, &probe("flac", 900_000, 2, true));
+        assert!(!args.contains(&"0:v:0".to_string()));
+        assert!(args.windows(2).any(|w| w == ["-c:a", "libopus"]));
+
+        // libopus rejects 44.1 kHz: loudness output goes to 48 kHz
+        let loud_opus = Config { loudness_target: Some(-16.0), ..opus };
+        let args = AudioProcessor::transcode_args(&loud_opus, &probe("flac", 900_000, 2, false));
+        assert!(args.windows(2).any(|w| w == ["-ar", "48000"]));
+    }
+}
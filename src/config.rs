@@ -11,22 +11,54 @@
 //! ## Parametri di configurazione:
 //! - `jpeg_quality`: Qualità JPEG (1-100, default: 80)
 //! - `video_crf`: CRF video (0-51, default: 26, più basso = migliore qualità)
-//! - `audio_bitrate`: Bitrate audio video (default: "128k")
+//! - `video_codec`: Codec video di output (h264, hevc, av1, vp9; default: h264)
+//! - `video_preset`: Velocità dell'encoder con i nomi x264 (default: None = default dell'encoder)
+//! - `video_tune`: Ottimizzazione per film, animation o grain (default: None)
+//! - `video_profile` / `video_level`: Profilo e livello del bitstream (default: None)
+//! - `video_target_bitrate`: Bitrate medio video, encoding a due passaggi al posto del CRF (default: None)
+//! - `video_target_size`: Dimensione del file di output, bitrate ricavato dalla durata (default: None)
+//! - `keyframe_interval`: Distanza massima tra keyframe in frame (default: None)
+//! - `hdr_policy`: Video HDR mantenuti a 10 bit o convertiti in SDR (default: preserve)
+//! - `stream_policy`: Tracce video, audio e sottotitoli da mantenere (default: first-video)
+//! - `video_trim`: Taglia i tratti neri o silenziosi a inizio e fine video (default: None)
+//! - `max_video_dimension`: Lato lungo massimo dei video in pixel, mai upscale (default: None)
+//! - `max_video_fps`: Frame rate massimo dei video (default: None)
+//! - `remux_videos`: Cambia solo il container dei video, senza ricodifica (default: false)
+//! - `streaming`: Packaging HLS o DASH dei video lunghi, con ladder di bitrate (default: None)
+//! - `streaming_ladder`: Gradini `altezza:bitrate` (default: 1080/720/480/360p)
+//! - `streaming_min_duration`: Durata minima in secondi per lo streaming (default: 120)
+//! - `audio_bitrate`: Bitrate audio di video e file audio lossy (default: "128k")
+//! - `audio_codec`: Audio dei video: auto, aac, opus, copy o none (default: auto)
+//! - `audio_channels`: Downmix a questo numero di canali (default: None)
+//! - `loudness_target`: Normalizzazione loudness EBU R128 in LUFS (default: None)
+//! - `audio_format`: Formato dei file audio: flac (lossless), opus o aac (default: flac)
 //! - `size_threshold`: Soglia per sostituire file (0.0-1.0, default: 0.9)
 //! - `dry_run`: Flag per simulazione senza modifiche (default: false)
-//! - `workers`: Numero di worker paralleli (default: 4)
+//! - `workers`: Numero massimo di file elaborati in parallelo (default: 4)
+//! - `max_threads`: Thread CPU a disposizione dello scheduler (default: None = tutti i core)
+//! - `max_memory`: Memoria a disposizione dello scheduler, es. "8G" (default: None = 75% della disponibile)
+//! - `timeout_policy`: Cosa fare di un file che supera il suo timeout: fail, copy-original o retry-faster (default: copy-original)
+//! - `timeout_multiplier`: Fattore dei timeout stimati dal contenuto, per macchine lente (default: 1.0)
 //! - `output_path`: Directory di output per file ottimizzati (default: None = replace in place)
 //! - `convert_to_webp`: Converte tutti i media a formato WebP (default: false)
 //! - `webp_quality`: Qualità WebP (1-100, default: 80)
+//! - `metadata_policy`: Metadati da mantenere nelle immagini (default: keep-all)
+//! - `color_profile`: Gestione profili ICC (default: preserve)
+//! - `storyboards`: Profili degli storyboard video con indice WebVTT (default: nessuno)
+//! - `video_preview_seconds`: Durata delle clip di anteprima dei video nei thumbnails (default: None = solo poster)
+//! - `preserve_ownership`: Copia owner/group dell'originale sugli output (default: false)
 //! 
 //! ## Validazione:
 //! - Controlla che jpeg_quality sia 1-100
 //! - Controlla che video_crf sia 0-51
+//! - Controlla che profilo/livello video siano nomi semplici e keyframe_interval > 0
+//! - Controlla che bitrate e dimensione target siano validi e non usati insieme
 //! - Controlla che size_threshold sia 0.0-1.0
-//! - Controlla che workers sia > 0
+//! - Controlla che workers e max_threads siano > 0 e che max_memory sia valida
+//! - Controlla che timeout_multiplier sia > 0
 //! 
 //! ## Esempio:
-//! ```rust
+//! ```rust,ignore
 //! let config = Config {
 //!     jpeg_quality: 85,
 //!     video_crf: 24,
@@ -40,6 +72,7 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// Configurazione per le dimensioni dei thumbnails
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +87,494 @@ impl ThumbnailSize {
     }
 }
 
+/// Profilo di uno storyboard (sprite di frame per lo scrubbing dei video)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoryboardProfile {
+    /// Numero massimo di frame campionati (al più uno al secondo)
+    pub frames: u32,
+    /// Tile per riga dello sprite
+    pub columns: u32,
+    /// Larghezza di ogni tile in pixel (l'altezza segue l'aspect ratio)
+    pub tile_width: u32,
+}
+
+impl StoryboardProfile {
+    pub fn new(frames: u32, columns: u32, tile_width: u32) -> Self {
+        Self { frames, columns, tile_width }
+    }
+}
+
+/// Politica di gestione dei metadati delle immagini, applicata dopo l'encoding
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetadataPolicy {
+    /// Copia tutti i metadati dall'originale (anche se l'encoder li ha scartati)
+    #[default]
+    KeepAll,
+    /// Rimuove tutti i metadati
+    StripAll,
+    /// Mantiene tutto tranne GPS e campi di localizzazione
+    StripLocation,
+    /// Mantiene solo copyright/autore e date di scatto
+    KeepOnlyCopyrightAndDate,
+    /// Mantiene solo i tag exiftool elencati (es. "Copyright", "EXIF:Make")
+    Custom(Vec<String>),
+}
+
+impl FromStr for MetadataPolicy {
+    type Err = String;
+
+    /// Accetta `keep-all`, `strip-all`, `strip-location`,
+    /// `keep-only-copyright-and-date` oppure `custom:Tag1,Tag2`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep-all" => Ok(Self::KeepAll),
+            "strip-all" => Ok(Self::StripAll),
+            "strip-location" => Ok(Self::StripLocation),
+            "keep-only-copyright-and-date" => Ok(Self::KeepOnlyCopyrightAndDate),
+            _ => {
+                let tags = s.strip_prefix("custom:").ok_or_else(|| format!(
+                    "Invalid metadata policy '{}': expected keep-all, strip-all, strip-location, \
+                     keep-only-copyright-and-date or custom:Tag1,Tag2", s
+                ))?;
+                let tags: Vec<String> = tags.split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+                if tags.is_empty() {
+                    return Err("Custom metadata policy needs at least one tag".to_string());
+                }
+                Ok(Self::Custom(tags))
+            }
+        }
+    }
+}
+
+/// Gestione dei profili colore ICC delle immagini
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorProfilePolicy {
+    /// Mantiene il profilo incorporato (Display P3, Adobe RGB...) nell'output
+    #[default]
+    Preserve,
+    /// Converte i pixel in sRGB e incorpora il profilo sRGB
+    ConvertToSrgb,
+}
+
+impl FromStr for ColorProfilePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "preserve" => Ok(Self::Preserve),
+            "convert-to-srgb" | "srgb" => Ok(Self::ConvertToSrgb),
+            _ => Err(format!("Invalid color profile policy '{}': expected preserve or convert-to-srgb", s)),
+        }
+    }
+}
+
+/// Gestione dei video HDR (PQ/HLG) in ricodifica (dettagli in `video_color`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HdrPolicy {
+    /// Mantiene HDR: 10 bit, tag BT.2020 e metadati HDR10
+    #[default]
+    Preserve,
+    /// Converte in SDR BT.709 a 8 bit (tone mapping)
+    ToneMap,
+}
+
+impl FromStr for HdrPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "preserve" => Ok(Self::Preserve),
+            "tone-map" | "tonemap" | "sdr" => Ok(Self::ToneMap),
+            _ => Err(format!("Invalid HDR policy '{}': expected preserve or tone-map", s)),
+        }
+    }
+}
+
+/// Stream del sorgente portati nell'output video (dettagli in `video_streams`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StreamPolicy {
+    /// Tutti gli stream video, audio e sottotitoli
+    All,
+    /// Il primo video, tutto l'audio e i sottotitoli
+    #[default]
+    FirstVideo,
+    /// Il primo video e tutto l'audio, senza sottotitoli
+    NoSubtitles,
+}
+
+impl FromStr for StreamPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "all" | "keep-all" => Ok(Self::All),
+            "first-video" => Ok(Self::FirstVideo),
+            "no-subtitles" | "drop-subtitles" => Ok(Self::NoSubtitles),
+            _ => Err(format!("Invalid stream policy '{}': expected all, first-video or no-subtitles", s)),
+        }
+    }
+}
+
+/// Segmenti morti tagliati all'inizio e alla fine dei video (dettagli in `video_trim`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TrimMode {
+    /// Solo i tratti neri (telefono in tasca, copriobiettivo)
+    Black,
+    /// Solo i tratti silenziosi
+    Silence,
+    /// Tratti neri o silenziosi
+    Any,
+}
+
+impl FromStr for TrimMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "black" => Ok(Self::Black),
+            "silence" => Ok(Self::Silence),
+            "any" | "black-or-silence" => Ok(Self::Any),
+            _ => Err(format!("Invalid trim mode '{}': expected black, silence or any", s)),
+        }
+    }
+}
+
+/// Cosa succede a un file che supera il suo timeout (stima in `optimizer::timeout`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeoutPolicy {
+    /// Il file fallisce senza output
+    Fail,
+    /// Con directory di output viene copiato l'originale; in-place resta com'è
+    #[default]
+    CopyOriginal,
+    /// I video vengono ricodificati una volta con il preset più veloce e un
+    /// nuovo timeout; se scade anche questo (o non è un video) si copia l'originale
+    RetryFaster,
+}
+
+impl TimeoutPolicy {
+    /// Nome usato da CLI e log
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fail => "fail",
+            Self::CopyOriginal => "copy-original",
+            Self::RetryFaster => "retry-faster",
+        }
+    }
+}
+
+impl FromStr for TimeoutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fail" => Ok(Self::Fail),
+            "copy-original" | "copy" => Ok(Self::CopyOriginal),
+            "retry-faster" | "retry" => Ok(Self::RetryFaster),
+            _ => Err(format!("Invalid timeout policy '{}': expected fail, copy-original or retry-faster", s)),
+        }
+    }
+}
+
+/// Codec video di output (gli encoder ffmpeg sono in `video_codec`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VideoCodec {
+    /// H.264 (libx264) in MP4: massima compatibilità
+    #[default]
+    H264,
+    /// H.265/HEVC (libx265) in MP4, taggato `hvc1` per la riproduzione Apple
+    Hevc,
+    /// AV1 (libsvtav1, in alternativa libaom-av1) in MP4
+    Av1,
+    /// VP9 (libvpx-vp9) in WebM con audio Opus
+    Vp9,
+}
+
+impl FromStr for VideoCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "h264" | "avc" | "x264" => Ok(Self::H264),
+            "hevc" | "h265" | "x265" => Ok(Self::Hevc),
+            "av1" => Ok(Self::Av1),
+            "vp9" => Ok(Self::Vp9),
+            _ => Err(format!("Invalid video codec '{}': expected h264, hevc, av1 or vp9", s)),
+        }
+    }
+}
+
+/// Trattamento della traccia audio dei video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioCodec {
+    /// Codec del container: AAC in MP4, Opus in WebM
+    #[default]
+    Auto,
+    /// AAC (non ammesso in WebM)
+    Aac,
+    /// Opus, anche in MP4 (tipico con AV1)
+    Opus,
+    /// Copia la traccia senza ricodifica, se il container la accetta
+    Copy,
+    /// Rimuove l'audio (B-roll muto)
+    #[serde(rename = "none")]
+    Disabled,
+}
+
+impl AudioCodec {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Aac => "aac",
+            Self::Opus => "opus",
+            Self::Copy => "copy",
+            Self::Disabled => "none",
+        }
+    }
+}
+
+impl FromStr for AudioCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "aac" => Ok(Self::Aac),
+            "opus" => Ok(Self::Opus),
+            "copy" => Ok(Self::Copy),
+            "none" | "drop" => Ok(Self::Disabled),
+            _ => Err(format!("Invalid audio codec '{}': expected auto, aac, opus, copy or none", s)),
+        }
+    }
+}
+
+/// Formato di output dei file audio (gli argomenti ffmpeg sono in `audio_processor`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioFormat {
+    /// FLAC: compressione lossless di WAV/AIFF
+    #[default]
+    Flac,
+    /// Opus in Ogg (`.opus`): ideale per voce e memo vocali
+    Opus,
+    /// AAC in MP4 (`.m4a`): massima compatibilità
+    Aac,
+}
+
+impl AudioFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+            Self::Aac => "aac",
+        }
+    }
+}
+
+impl FromStr for AudioFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "flac" => Ok(Self::Flac),
+            "opus" => Ok(Self::Opus),
+            "aac" | "m4a" => Ok(Self::Aac),
+            _ => Err(format!("Invalid audio format '{}': expected flac, opus or aac", s)),
+        }
+    }
+}
+
+/// Formato di packaging per lo streaming adattivo (gli argomenti ffmpeg sono in `streaming`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StreamingFormat {
+    /// HLS con segmenti fMP4
+    Hls,
+    /// DASH, con playlist HLS sugli stessi segmenti
+    Dash,
+}
+
+impl StreamingFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hls => "hls",
+            Self::Dash => "dash",
+        }
+    }
+}
+
+impl FromStr for StreamingFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hls" => Ok(Self::Hls),
+            "dash" => Ok(Self::Dash),
+            _ => Err(format!("Invalid streaming format '{}': expected hls or dash", s)),
+        }
+    }
+}
+
+/// Gradino della ladder di streaming: lato corto in pixel e bitrate video
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamingRendition {
+    /// Lato corto (1080 = 1080p, anche per i video verticali)
+    pub height: u32,
+    /// Bitrate video in formato ffmpeg (es. "5000k")
+    pub bitrate: String,
+}
+
+impl StreamingRendition {
+    pub fn new(height: u32, bitrate: &str) -> Self {
+        Self { height, bitrate: bitrate.to_string() }
+    }
+
+    /// Ladder predefinita, dal gradino più alto
+    pub fn default_ladder() -> Vec<Self> {
+        vec![
+            Self::new(1080, "5000k"),
+            Self::new(720, "2800k"),
+            Self::new(480, "1400k"),
+            Self::new(360, "800k"),
+        ]
+    }
+}
+
+impl FromStr for StreamingRendition {
+    type Err = String;
+
+    /// Formato `altezza:bitrate`, es. `720:2800k`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (height, bitrate) = s.split_once(':')
+            .ok_or_else(|| format!("Invalid rendition '{}': expected height:bitrate (e.g. 720:2800k)", s))?;
+        let height = height.trim().parse::<u32>()
+            .map_err(|_| format!("Invalid rendition height '{}'", height))?;
+        Ok(Self::new(height, bitrate.trim()))
+    }
+}
+
+fn default_streaming_ladder() -> Vec<StreamingRendition> {
+    StreamingRendition::default_ladder()
+}
+
+fn default_streaming_min_duration() -> f64 {
+    120.0
+}
+
+fn default_timeout_multiplier() -> f64 {
+    1.0
+}
+
+/// Velocità dell'encoder video, con i nomi dei preset x264.
+///
+/// Per gli altri encoder viene convertita nella scala equivalente
+/// (`-preset` di SVT-AV1, `-cpu-used` di libaom e libvpx), vedi `video_codec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoPreset {
+    Ultrafast,
+    Superfast,
+    Veryfast,
+    Faster,
+    Fast,
+    Medium,
+    Slow,
+    Slower,
+    Veryslow,
+}
+
+impl VideoPreset {
+    pub const ALL: [VideoPreset; 9] = [
+        Self::Ultrafast,
+        Self::Superfast,
+        Self::Veryfast,
+        Self::Faster,
+        Self::Fast,
+        Self::Medium,
+        Self::Slow,
+        Self::Slower,
+        Self::Veryslow,
+    ];
+
+    /// Nome del preset x264/x265
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ultrafast => "ultrafast",
+            Self::Superfast => "superfast",
+            Self::Veryfast => "veryfast",
+            Self::Faster => "faster",
+            Self::Fast => "fast",
+            Self::Medium => "medium",
+            Self::Slow => "slow",
+            Self::Slower => "slower",
+            Self::Veryslow => "veryslow",
+        }
+    }
+
+    /// Posizione nella scala, da 0 (ultrafast) a 8 (veryslow)
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl FromStr for VideoPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.as_str() == s)
+            .ok_or_else(|| format!("Invalid video preset '{}': expected ultrafast, superfast, veryfast, faster, fast, medium, slow, slower or veryslow", s))
+    }
+}
+
+/// Tipo di contenuto per cui ottimizzare l'encoder video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoTune {
+    /// Riprese live action
+    Film,
+    /// Animazione e cartoni: superfici piatte, bordi netti
+    Animation,
+    /// Pellicola o footage rumoroso: conserva la grana
+    Grain,
+}
+
+impl VideoTune {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Film => "film",
+            Self::Animation => "animation",
+            Self::Grain => "grain",
+        }
+    }
+}
+
+impl FromStr for VideoTune {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "film" => Ok(Self::Film),
+            "animation" => Ok(Self::Animation),
+            "grain" => Ok(Self::Grain),
+            _ => Err(format!("Invalid video tune '{}': expected film, animation or grain", s)),
+        }
+    }
+}
+
 /// Configuration for media optimization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -61,14 +582,81 @@ pub struct Config {
     pub jpeg_quality: u8,
     /// Video CRF value (0-51, lower = better quality)
     pub video_crf: u8,
-    /// Video audio bitrate
+    /// Output video codec
+    #[serde(default)]
+    pub video_codec: VideoCodec,
+    /// Encoder speed/size trade-off (None = the encoder's own default)
+    #[serde(default)]
+    pub video_preset: Option<VideoPreset>,
+    /// Encoder tuning for the kind of content
+    #[serde(default)]
+    pub video_tune: Option<VideoTune>,
+    /// Bitstream profile passed to the encoder (e.g. "high", "main10")
+    #[serde(default)]
+    pub video_profile: Option<String>,
+    /// Bitstream level (e.g. "4.1")
+    #[serde(default)]
+    pub video_level: Option<String>,
+    /// Average video bitrate (e.g. "2500k"): two-pass encoding instead of CRF
+    #[serde(default)]
+    pub video_target_bitrate: Option<String>,
+    /// Output file size (e.g. "700M"): the bitrate is derived from the duration
+    #[serde(default)]
+    pub video_target_size: Option<String>,
+    /// Maximum distance between keyframes, in frames
+    #[serde(default)]
+    pub keyframe_interval: Option<u32>,
+    /// How HDR videos are encoded: kept HDR or tone-mapped to SDR
+    #[serde(default)]
+    pub hdr_policy: HdrPolicy,
+    /// Which source streams (video, audio, subtitles) are kept in video outputs
+    #[serde(default)]
+    pub stream_policy: StreamPolicy,
+    /// Trim leading/trailing black or silent segments (None = no analysis)
+    #[serde(default)]
+    pub video_trim: Option<TrimMode>,
+    /// Downscale videos whose long edge exceeds this many pixels
+    #[serde(default)]
+    pub max_video_dimension: Option<u32>,
+    /// Cap the video frame rate (frames per second)
+    #[serde(default)]
+    pub max_video_fps: Option<u32>,
+    /// Stream-copy videos into the output container instead of re-encoding
+    /// (videos whose codec the container cannot hold are still encoded)
+    #[serde(default)]
+    pub remux_videos: bool,
+    /// Audio bitrate (video soundtracks and lossy audio files)
     pub audio_bitrate: String,
+    /// Audio codec for videos (copy keeps the source track, none drops it)
+    #[serde(default)]
+    pub audio_codec: AudioCodec,
+    /// Downmix audio with more channels than this (2 = stereo)
+    #[serde(default)]
+    pub audio_channels: Option<u32>,
+    /// Normalize audio loudness to this integrated level in LUFS (ffmpeg loudnorm)
+    #[serde(default)]
+    pub loudness_target: Option<f64>,
+    /// Output format for standalone audio files (lossy formats use `audio_bitrate`)
+    #[serde(default)]
+    pub audio_format: AudioFormat,
     /// Size threshold (keep if new size < original * threshold)
     pub size_threshold: f64,
     /// Dry run - don't actually replace files
     pub dry_run: bool,
     /// Number of parallel workers
     pub workers: usize,
+    /// CPU threads the scheduler may fill (None = every core)
+    #[serde(default)]
+    pub max_threads: Option<usize>,
+    /// Memory the scheduler may reserve, e.g. "8G" (None = measured from the system)
+    #[serde(default)]
+    pub max_memory: Option<String>,
+    /// What happens to a file that exceeds its content-derived timeout
+    #[serde(default)]
+    pub timeout_policy: TimeoutPolicy,
+    /// Scale factor for the estimated timeouts (e.g. 3.0 on slow machines)
+    #[serde(default = "default_timeout_multiplier")]
+    pub timeout_multiplier: f64,
     /// Output directory for optimized files (None = replace in place)
     pub output_path: Option<PathBuf>,
     /// Convert all media to WebP format
@@ -83,6 +671,33 @@ pub struct Config {
     pub json_output: bool,
     /// Thumbnail configurations: name -> (width, height)
     pub thumbnails: HashMap<String, ThumbnailSize>,
+    /// Package long videos for adaptive streaming instead of a single file
+    #[serde(default)]
+    pub streaming: Option<StreamingFormat>,
+    /// Streaming renditions (short edge + video bitrate), highest first
+    #[serde(default = "default_streaming_ladder")]
+    pub streaming_ladder: Vec<StreamingRendition>,
+    /// Videos shorter than this (seconds) are optimized as a single file
+    #[serde(default = "default_streaming_min_duration")]
+    pub streaming_min_duration: f64,
+    /// Storyboard configurations: name -> (frames, columns, tile width)
+    #[serde(default)]
+    pub storyboards: HashMap<String, StoryboardProfile>,
+    /// Length of the muted preview clip created next to each video poster (None = posters only)
+    #[serde(default)]
+    pub video_preview_seconds: Option<f64>,
+    /// Which image metadata survives optimization
+    #[serde(default)]
+    pub metadata_policy: MetadataPolicy,
+    /// How embedded ICC colour profiles are handled
+    #[serde(default)]
+    pub color_profile: ColorProfilePolicy,
+    /// Also copy owner/group to outputs (timestamps and permissions are always kept)
+    #[serde(default)]
+    pub preserve_ownership: bool,
+    /// Ignore the journal of an interrupted run: files it completed are considered again
+    #[serde(default)]
+    pub rescan: bool,
 }
 
 impl Default for Config {
@@ -90,10 +705,32 @@ impl Default for Config {
         Self {
             jpeg_quality: 80,
             video_crf: 26,
+            video_codec: VideoCodec::default(),
+            video_preset: None,
+            video_tune: None,
+            video_profile: None,
+            video_level: None,
+            video_target_bitrate: None,
+            video_target_size: None,
+            keyframe_interval: None,
+            hdr_policy: HdrPolicy::default(),
+            stream_policy: StreamPolicy::default(),
+            video_trim: None,
+            max_video_dimension: None,
+            max_video_fps: None,
+            remux_videos: false,
             audio_bitrate: "128k".to_string(),
+            audio_codec: AudioCodec::default(),
+            audio_channels: None,
+            loudness_target: None,
+            audio_format: AudioFormat::default(),
             size_threshold: 0.9,
             dry_run: false,
             workers: 4,
+            max_threads: None,
+            max_memory: None,
+            timeout_policy: TimeoutPolicy::default(),
+            timeout_multiplier: default_timeout_multiplier(),
             output_path: None,
             convert_to_webp: false,
             webp_quality: 80,
@@ -101,6 +738,15 @@ impl Default for Config {
             skip_video_compression: false,
             json_output: false,
             thumbnails: HashMap::new(),
+            streaming: None,
+            streaming_ladder: StreamingRendition::default_ladder(),
+            streaming_min_duration: default_streaming_min_duration(),
+            storyboards: HashMap::new(),
+            video_preview_seconds: None,
+            metadata_policy: MetadataPolicy::default(),
+            color_profile: ColorProfilePolicy::default(),
+            preserve_ownership: false,
+            rescan: false,
         }
     }
 }
@@ -120,6 +766,119 @@ impl Config {
             return Err(anyhow::anyhow!("Video CRF must be between 0 and 51"));
         }
         
+        for value in [&self.video_profile, &self.video_level].into_iter().flatten() {
+            if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_') {
+                return Err(anyhow::anyhow!("Invalid video profile/level '{}'", value));
+            }
+        }
+        
+        if self.video_target_bitrate.is_some() && self.video_target_size.is_some() {
+            return Err(anyhow::anyhow!("Video target bitrate and target size are mutually exclusive"));
+        }
+        
+        if let Some(ref bitrate) = self.video_target_bitrate {
+            if crate::utils::parse_bitrate(bitrate).is_none_or(|bits| bits == 0) {
+                return Err(anyhow::anyhow!("Invalid video target bitrate '{}'", bitrate));
+            }
+        }
+        
+        if let Some(ref size) = self.video_target_size {
+            if crate::utils::parse_file_size(size).is_none_or(|bytes| bytes == 0) {
+                return Err(anyhow::anyhow!("Invalid video target size '{}': expected e.g. 700M or 1.5G", size));
+            }
+        }
+        
+        let targeted = self.video_target_bitrate.is_some() || self.video_target_size.is_some();
+        if targeted && (self.remux_videos || self.skip_video_compression) {
+            return Err(anyhow::anyhow!("A video target bitrate or size needs re-encoding: incompatible with remux and skip video compression"));
+        }
+        
+        if self.video_trim.is_some() && (self.remux_videos || self.skip_video_compression) {
+            return Err(anyhow::anyhow!("Trimming dead segments needs re-encoding: incompatible with remux and skip video compression"));
+        }
+        
+        if self.keyframe_interval == Some(0) {
+            return Err(anyhow::anyhow!("Keyframe interval must be greater than 0"));
+        }
+        
+        if matches!(self.max_video_dimension, Some(d) if d < 16) {
+            return Err(anyhow::anyhow!("Maximum video dimension must be at least 16 pixels"));
+        }
+        
+        if self.max_video_fps == Some(0) {
+            return Err(anyhow::anyhow!("Maximum video frame rate must be greater than 0"));
+        }
+        
+        if self.remux_videos && (self.max_video_dimension.is_some() || self.max_video_fps.is_some()) {
+            return Err(anyhow::anyhow!("Remux mode copies the video stream and cannot downscale or change the frame rate"));
+        }
+        
+        if self.remux_videos && self.skip_video_compression {
+            return Err(anyhow::anyhow!("Remux mode and skip video compression are mutually exclusive"));
+        }
+        
+        if matches!(self.audio_channels, Some(channels) if channels == 0 || channels > 8) {
+            return Err(anyhow::anyhow!("Audio channels must be between 1 and 8"));
+        }
+        
+        if matches!(self.loudness_target, Some(lufs) if !(-70.0..=-5.0).contains(&lufs)) {
+            return Err(anyhow::anyhow!("Loudness target must be between -70 and -5 LUFS"));
+        }
+        
+        let audio_processing = self.audio_channels.is_some() || self.loudness_target.is_some();
+        if audio_processing && matches!(self.audio_codec, AudioCodec::Copy | AudioCodec::Disabled) {
+            return Err(anyhow::anyhow!(
+                "Audio downmix and loudness normalization need re-encoding: incompatible with audio codec '{}'",
+                self.audio_codec.as_str()
+            ));
+        }
+        
+        if self.audio_codec == AudioCodec::Aac && self.video_codec == VideoCodec::Vp9 {
+            return Err(anyhow::anyhow!("AAC audio cannot be stored in WebM: use opus or auto"));
+        }
+        
+        if self.streaming.is_some() {
+            if self.output_path.is_none() {
+                return Err(anyhow::anyhow!("Streaming output requires an output directory"));
+            }
+            if self.video_codec == VideoCodec::Vp9 {
+                return Err(anyhow::anyhow!("Streaming output uses fMP4 segments: VP9 is not supported"));
+            }
+            if self.remux_videos || self.skip_video_compression {
+                return Err(anyhow::anyhow!("Streaming output re-encodes videos: incompatible with remux and skip video compression"));
+            }
+            if self.streaming_ladder.is_empty() {
+                return Err(anyhow::anyhow!("Streaming ladder must contain at least one rendition"));
+            }
+            for rendition in &self.streaming_ladder {
+                if !(144..=4320).contains(&rendition.height) || rendition.height % 2 != 0 {
+                    return Err(anyhow::anyhow!("Invalid rendition height {}: must be even and 144-4320", rendition.height));
+                }
+                if crate::utils::parse_bitrate(&rendition.bitrate).is_none_or(|bits| bits == 0) {
+                    return Err(anyhow::anyhow!("Invalid rendition bitrate '{}'", rendition.bitrate));
+                }
+            }
+            if self.streaming_min_duration < 0.0 {
+                return Err(anyhow::anyhow!("Streaming minimum duration cannot be negative"));
+            }
+        }
+        
+        for (name, profile) in &self.storyboards {
+            if !(1..=1000).contains(&profile.frames)
+                || !(1..=50).contains(&profile.columns)
+                || !(16..=1024).contains(&profile.tile_width)
+            {
+                return Err(anyhow::anyhow!(
+                    "Invalid storyboard '{}': frames must be 1-1000, columns 1-50, tile width 16-1024",
+                    name
+                ));
+            }
+        }
+        
+        if matches!(self.video_preview_seconds, Some(seconds) if !(1.0..=30.0).contains(&seconds)) {
+            return Err(anyhow::anyhow!("Video preview length must be between 1 and 30 seconds"));
+        }
+        
         if self.size_threshold <= 0.0 || self.size_threshold > 1.0 {
             return Err(anyhow::anyhow!("Size threshold must be between 0.0 and 1.0"));
         }
@@ -128,6 +887,26 @@ impl Config {
             return Err(anyhow::anyhow!("Number of workers must be greater than 0"));
         }
         
+        if self.max_threads == Some(0) {
+            return Err(anyhow::anyhow!("Maximum CPU threads must be greater than 0"));
+        }
+        
+        if !(self.timeout_multiplier > 0.0 && self.timeout_multiplier.is_finite()) {
+            return Err(anyhow::anyhow!("Timeout multiplier must be greater than 0"));
+        }
+        
+        if let Some(ref memory) = self.max_memory {
+            if crate::utils::parse_file_size(memory).is_none_or(|bytes| bytes == 0) {
+                return Err(anyhow::anyhow!("Invalid maximum memory '{}': expected e.g. 8G or 512M", memory));
+            }
+        }
+        
+        if let MetadataPolicy::Custom(ref tags) = self.metadata_policy {
+            if tags.iter().any(|t| t.is_empty() || t.starts_with('-')) {
+                return Err(anyhow::anyhow!("Custom metadata tags must be plain exiftool tag names"));
+            }
+        }
+        
         // Validate output path if specified
         if let Some(ref output_path) = self.output_path {
             if !output_path.exists() {
@@ -206,6 +985,7 @@ mod tests {
             size_threshold: 0.85,
             dry_run: true,
             workers: 8,
+            ..Default::default()
         };
 
         // Save config
@@ -221,4 +1001,105 @@ mod tests {
         assert!(loaded_config.dry_run);
         assert_eq!(loaded_config.workers, 8);
     }
+
+    #[test]
+    fn test_metadata_policy_parsing() {
+        assert_eq!("keep-all".parse::<MetadataPolicy>().unwrap(), MetadataPolicy::KeepAll);
+        assert_eq!("strip-location".parse::<MetadataPolicy>().unwrap(), MetadataPolicy::StripLocation);
+        assert_eq!(
+            "custom:Copyright, EXIF:Make".parse::<MetadataPolicy>().unwrap(),
+            MetadataPolicy::Custom(vec!["Copyright".to_string(), "EXIF:Make".to_string()])
+        );
+        assert!("custom:".parse::<MetadataPolicy>().is_err());
+        assert!("everything".parse::<MetadataPolicy>().is_err());
+
+        // I file di configurazione precedenti (senza il campo) restano validi
+        let mut value = serde_json::to_value(Config::default()).unwrap();
+        value.as_object_mut().unwrap().remove("metadata_policy");
+        let config: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(config.metadata_policy, MetadataPolicy::KeepAll);
+
+        let config = Config {
+            metadata_policy: MetadataPolicy::Custom(vec!["-all=".to_string()]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_video_encoder_options_parsing() {
+        assert_eq!("veryslow".parse::<VideoPreset>().unwrap(), VideoPreset::Veryslow);
+        assert_eq!("Fast".parse::<VideoPreset>().unwrap(), VideoPreset::Fast);
+        assert!("placebo".parse::<VideoPreset>().is_err());
+        assert_eq!(VideoPreset::Ultrafast.index(), 0);
+        assert_eq!(VideoPreset::Veryslow.index(), 8);
+        assert_eq!("grain".parse::<VideoTune>().unwrap(), VideoTune::Grain);
+        assert!("zerolatency".parse::<VideoTune>().is_err());
+
+        let config = Config { video_level: Some("4.1".to_string()), ..Default::default() };
+        assert!(config.validate().is_ok());
+        let config = Config { video_profile: Some("high -x".to_string()), ..Default::default() };
+        assert!(config.validate().is_err());
+        let config = Config { keyframe_interval: Some(0), ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_video_target_validation() {
+        let config = Config { video_target_size: Some("700M".to_string()), ..Default::default() };
+        assert!(config.validate().is_ok());
+        let config = Config { video_target_size: Some("big".to_string()), ..Default::default() };
+        assert!(config.validate().is_err());
+        // Bitrate and size are alternative targets
+        let config = Config {
+            video_target_bitrate: Some("2500k".to_string()),
+            video_target_size: Some("700M".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        // A remux does not encode, so it cannot hit a target
+        let config = Config { video_target_bitrate: Some("2500k".to_string()), remux_videos: true, ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_trim_options_parsing() {
+        assert_eq!("black".parse::<TrimMode>().unwrap(), TrimMode::Black);
+        assert_eq!("black-or-silence".parse::<TrimMode>().unwrap(), TrimMode::Any);
+        assert!("everything".parse::<TrimMode>().is_err());
+
+        // Trimming needs an encode: a remux cannot cut precisely
+        let config = Config { video_trim: Some(TrimMode::Black), remux_videos: true, ..Default::default() };
+        assert!(config.validate().is_err());
+        let config = Config { video_trim: Some(TrimMode::Any), ..Default::default() };
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_audio_options_validation() {
+        assert_eq!("none".parse::<AudioCodec>().unwrap(), AudioCodec::Disabled);
+        assert_eq!("Opus".parse::<AudioCodec>().unwrap(), AudioCodec::Opus);
+        assert!("mp3".parse::<AudioCodec>().is_err());
+
+        let config = Config { audio_channels: Some(2), loudness_target: Some(-16.0), ..Default::default() };
+        assert!(config.validate().is_ok());
+        // Copy and drop cannot apply filters
+        let config = Config { audio_codec: AudioCodec::Copy, loudness_target: Some(-16.0), ..Default::default() };
+        assert!(config.validate().is_err());
+        let config = Config { audio_codec: AudioCodec::Disabled, audio_channels: Some(2), ..Default::default() };
+        assert!(config.validate().is_err());
+        let config = Config { audio_codec: AudioCodec::Aac, video_codec: VideoCodec::Vp9, ..Default::default() };
+        assert!(config.validate().is_err());
+        let config = Config { loudness_target: Some(3.0), ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_timeout_policy_parsing() {
+        assert_eq!("retry".parse::<TimeoutPolicy>().unwrap(), TimeoutPolicy::RetryFaster);
+        assert!("skip".parse::<TimeoutPolicy>().is_err());
+
+        let config = Config { timeout_multiplier: 0.0, ..Default::default() };
+        assert!(config.validate().is_err());
+    }
 }
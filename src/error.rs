@@ -17,6 +17,7 @@
 //! - `UnsupportedFormat`: Formato file non supportato
 //! - `MissingDependency`: Tool esterno mancante (ffmpeg, exiftool)
 //! - `Validation`: Errori di validazione input
+//! - `Cancelled`: Operazione interrotta da un segnale di stop
 //! 
 //! ## Vantaggi:
 //! - Errori tipizzati per handling specifico
@@ -25,7 +26,7 @@
 //! - Integration con `anyhow` per error propagation
 //! 
 //! ## Esempio:
-//! ```rust
+//! ```rust,ignore
 //! if !tool_exists {
 //!     return Err(OptimizeError::MissingDependency("ffmpeg".to_string()));
 //! }
@@ -57,4 +58,14 @@ pub enum OptimizeError {
     
     #[error("File validation error: {0}")]
     Validation(String),
+    
+    #[error("{0} cancelled by user")]
+    Cancelled(String),
+}
+
+impl OptimizeError {
+    /// Indica se l'errore (anche dentro un `anyhow::Error`) è una cancellazione
+    pub fn is_cancelled(error: &anyhow::Error) -> bool {
+        matches!(error.downcast_ref::<OptimizeError>(), Some(OptimizeError::Cancelled(_)))
+    }
 }
@@ -0,0 +1,271 @@
+//! # FFmpeg Runner Module
+//!
+//! Esecuzione di ffmpeg e ffprobe condivisa dai processori media (video e
+//! audio), con avanzamento in tempo reale.
+//!
+//! ## Responsabilità:
+//! - Analisi dei file con ffprobe (`probe`)
+//! - Lettura di dati grezzi da stdout, es. un frame in scala di grigi (`capture`)
+//! - Passaggi di analisi con filtri che scrivono su stderr, es. blackdetect (`analyze`)
+//! - Avvia ffmpeg con le opzioni globali comuni (`-nostdin`, log level, `-progress`)
+//! - Legge in streaming il report di `-progress pipe:1` (stdout)
+//! - Converte `out_time_us`/`speed` in una percentuale rispetto alla durata
+//! - Raccoglie stderr per messaggi d'errore dettagliati
+//!
+//! ## Formato del report:
+//! ffmpeg scrive blocchi `chiave=valore` chiusi da `progress=continue` (o
+//! `progress=end`), circa due volte al secondo:
+//! ```text
+//! out_time_us=12345678
+//! speed=2.51x
+//! progress=continue
+//! ```
+//! `out_time_ms` è, nonostante il nome, anch'esso in microsecondi.
+
+use crate::error::OptimizeError;
+use crate::platform::PlatformCommands;
+use crate::progress::{FileProgress, ProgressCallback};
+use crate::utils::to_string_vec;
+use anyhow::Result;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::Command;
+use tracing::{debug, error};
+
+/// Stato del parser del report `-progress`
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    out_time: Option<f64>,
+    speed: Option<f64>,
+}
+
+/// Un blocco completo del report `-progress`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressUpdate {
+    /// Posizione raggiunta nell'output, in secondi
+    pub out_time: f64,
+    /// Velocità rispetto al tempo reale (2.0 = il doppio del tempo reale)
+    pub speed: Option<f64>,
+    /// ffmpeg ha terminato (`progress=end`)
+    pub finished: bool,
+}
+
+impl ProgressParser {
+    /// Elabora una riga del report; restituisce un aggiornamento a fine blocco
+    pub fn feed(&mut self, line: &str) -> Option<ProgressUpdate> {
+        let (key, value) = line.trim().split_once('=')?;
+        match key {
+            "out_time_us" | "out_time_ms" => {
+                // "N/A" all'inizio dello stream
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time = Some(us.max(0) as f64 / 1_000_000.0);
+                }
+            }
+            "speed" => {
+                self.speed = value.trim_end_matches('x').trim().parse::<f64>().ok().filter(|s| *s > 0.0);
+            }
+            "progress" => {
+                return Some(ProgressUpdate {
+                    out_time: self.out_time.unwrap_or(0.0),
+                    speed: self.speed,
+                    finished: value == "end",
+                });
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+impl ProgressUpdate {
+    /// Converte l'aggiornamento in [`FileProgress`] per un file di durata `duration`
+    pub fn to_file_progress(self, path: &Path, duration: Option<f64>) -> FileProgress {
+        let duration = duration.filter(|d| *d > 0.0);
+        let percent = if self.finished {
+            Some(100.0)
+        } else {
+            duration.map(|d| (self.out_time / d * 100.0).clamp(0.0, 100.0))
+        };
+        let eta_seconds = match (duration, self.speed) {
+            (Some(d), Some(speed)) if !self.finished => Some(((d - self.out_time).max(0.0)) / speed),
+            _ => None,
+        };
+
+        FileProgress {
+            path: path.to_path_buf(),
+            percent,
+            processed_seconds: self.out_time,
+            duration_seconds: duration,
+            speed: self.speed,
+            eta_seconds,
+        }
+    }
+}
+
+/// Esegue ffmpeg riportando l'avanzamento.
+///
+/// # Arguments
+/// * `args` - Argomenti specifici (input, codec, output), senza opzioni globali
+/// * `input_path` - File di riferimento per gli eventi di avanzamento
+/// * `duration` - Durata attesa dell'output in secondi, per la percentuale
+/// * `progress` - Callback invocata a ogni blocco del report
+///
+/// # Errors
+/// `OptimizeError::FFmpeg` con lo stderr di ffmpeg se il processo fallisce.
+pub async fn run(
+    args: &[String],
+    input_path: &Path,
+    duration: Option<f64>,
+    progress: Option<&ProgressCallback>,
+) -> Result<()> {
+    let log_level = if tracing::enabled!(tracing::Level::DEBUG) { "info" } else { "warning" };
+    execute(args, input_path, duration, progress, log_level).await.map(|_| ())
+}
+
+/// Esegue un passaggio di analisi e restituisce lo stderr di ffmpeg.
+///
+/// Filtri come `blackdetect` e `silencedetect` scrivono i risultati nel log
+/// a livello `info`, che qui è sempre attivo. Argomenti e avanzamento come [`run`].
+pub async fn analyze(
+    args: &[String],
+    input_path: &Path,
+    duration: Option<f64>,
+    progress: Option<&ProgressCallback>,
+) -> Result<String> {
+    execute(args, input_path, duration, progress, "info").await
+}
+
+/// Avvia ffmpeg, inoltra l'avanzamento e restituisce lo stderr
+async fn execute(
+    args: &[String],
+    input_path: &Path,
+    duration: Option<f64>,
+    progress: Option<&ProgressCallback>,
+    log_level: &str,
+) -> Result<String> {
+    let ffmpeg_cmd = PlatformCommands::instance().get_command("ffmpeg");
+
+    let mut cmd = Command::new(ffmpeg_cmd);
+    cmd.args(global_args(log_level))
+        .args(args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    debug!("Running {} {:?}", ffmpeg_cmd, args);
+    let mut child = cmd.spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to execute {}: {}", ffmpeg_cmd, e))?;
+
+    // stderr letto in parallelo: se il buffer si riempie ffmpeg si blocca
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_task = tokio::spawn(async move {
+        let mut buffer = String::new();
+        let _ = stderr.read_to_string(&mut buffer).await;
+        buffer
+    });
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut lines = BufReader::new(stdout).lines();
+    let mut parser = ProgressParser::default();
+    while let Some(line) = lines.next_line().await? {
+        if let (Some(update), Some(callback)) = (parser.feed(&line), progress) {
+            callback(&update.to_file_progress(input_path, duration));
+        }
+    }
+
+    let status = child.wait().await?;
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    if !status.success() {
+        error!("❌ FFmpeg failed on {}: {}", input_path.display(), stderr.trim());
+        return Err(OptimizeError::FFmpeg(stderr.trim().to_string()).into());
+    }
+    if !stderr.trim().is_empty() {
+        debug!("FFmpeg output for {}: {}", input_path.display(), stderr.trim());
+    }
+
+    Ok(stderr)
+}
+
+/// Analizza `input_path` con ffprobe.
+///
+/// # Returns
+/// Il JSON di ffprobe con formato, stream e capitoli (`-show_format -show_streams -show_chapters`).
+pub async fn probe(input_path: &Path) -> Result<String> {
+    let ffprobe_cmd = PlatformCommands::instance().get_command("ffprobe");
+    let output = Command::new(ffprobe_cmd)
+        .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams", "-show_chapters"])
+        .arg(input_path)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output().await
+        .map_err(|e| anyhow::anyhow!("Failed to execute {}: {}", ffprobe_cmd, e))?;
+
+    if !output.status.success() {
+        return Err(OptimizeError::FFmpeg(String::from_utf8_lossy(&output.stderr).trim().to_string()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Esegue ffmpeg e restituisce ciò che scrive su stdout (output `pipe:1`).
+///
+/// Per operazioni brevi senza avanzamento, come l'estrazione di un frame.
+pub async fn capture(args: &[String]) -> Result<Vec<u8>> {
+    let ffmpeg_cmd = PlatformCommands::instance().get_command("ffmpeg");
+    debug!("Running {} {:?}", ffmpeg_cmd, args);
+    let output = Command::new(ffmpeg_cmd)
+        .args(["-hide_banner", "-nostdin", "-loglevel", "error"])
+        .args(args)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output().await
+        .map_err(|e| anyhow::anyhow!("Failed to execute {}: {}", ffmpeg_cmd, e))?;
+
+    if !output.status.success() {
+        return Err(OptimizeError::FFmpeg(String::from_utf8_lossy(&output.stderr).trim().to_string()).into());
+    }
+    Ok(output.stdout)
+}
+
+/// Opzioni globali comuni, da anteporre a input e output
+fn global_args(log_level: &str) -> Vec<String> {
+    to_string_vec([
+        "-hide_banner",
+        "-nostdin",
+        "-loglevel", log_level,
+        "-nostats",
+        "-progress", "pipe:1",
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_parsing() {
+        let mut parser = ProgressParser::default();
+        assert_eq!(parser.feed("out_time_us=N/A"), None);
+        assert_eq!(parser.feed("out_time_us=15000000"), None);
+        assert_eq!(parser.feed("speed=2.5x"), None);
+        let update = parser.feed("progress=continue").unwrap();
+        assert_eq!(update, ProgressUpdate { out_time: 15.0, speed: Some(2.5), finished: false });
+
+        let progress = update.to_file_progress(Path::new("/v/clip.mov"), Some(60.0));
+        assert_eq!(progress.percent, Some(25.0));
+        assert_eq!(progress.eta_seconds, Some(18.0));
+
+        // Durata sconosciuta: nessuna percentuale, ma il tempo elaborato resta
+        let progress = update.to_file_progress(Path::new("/v/clip.mov"), None);
+        assert_eq!(progress.percent, None);
+        assert_eq!(progress.processed_seconds, 15.0);
+
+        parser.feed("speed=N/A");
+        let end = parser.feed("progress=end").unwrap();
+        assert!(end.finished);
+        assert_eq!(end.speed, None);
+        assert_eq!(end.to_file_progress(Path::new("/v/clip.mov"), Some(60.0)).percent, Some(100.0));
+    }
+}
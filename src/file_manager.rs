@@ -4,7 +4,7 @@
 //! 
 //! ## Responsabilità:
 //! - Discovery ricorsiva di file media in directory
-//! - Determinazione formato file (immagine vs video)
+//! - Determinazione del tipo di media ([`MediaKind`]: immagine, video, audio)
 //! - Operazioni sicure sui file con backup automatici
 //! - Utilità per calcoli dimensioni e percentuali
 //! - Formattazione human-readable delle dimensioni
@@ -12,24 +12,30 @@
 //! ## Formati supportati:
 //! - **Immagini**: JPG, JPEG, PNG, WebP
 //! - **Video**: MP4, MOV, AVI, MKV, WebM
+//! - **Audio**: MP3, WAV, FLAC, M4A, AIFF, Ogg, Opus
 //! 
 //! ## Operazioni sui file:
 //! - `find_media_files()`: Trova tutti i file media in una directory
-//! - `is_image()` / `is_video()`: Determina tipo di file
+//! - `walk_media_files()`: Come sopra, ma un file alla volta (scansione in streaming)
+//! - `media_kind()`: Determina il tipo di media (`is_image()` / `is_video()` / `is_audio()`)
 //! - `get_file_info()`: Ottiene dimensione e modification time
 //! - `replace_file()`: Sostituzione sicura con backup
+//! - `copy_file_attributes()`: Copia atime/mtime, permessi e (opzionale) owner
+//! - `TempFileGuard`: Rimuove i file temporanei anche se il task viene cancellato
 //! 
 //! ## Sicurezza operazioni:
 //! - Backup automatico prima della sostituzione
 //! - Rollback in caso di errore durante la sostituzione
 //! - Validazione esistenza file prima delle operazioni
+//! - Ogni output mantiene date e permessi dell'originale (le app foto
+//!   ordinano per data del file)
 //! 
 //! ## Utilità:
 //! - `format_size()`: Converte bytes in formato leggibile (KB, MB, GB)
 //! - `calculate_reduction()`: Calcola percentuale di riduzione
 //! 
 //! ## Esempio:
-//! ```rust
+//! ```rust,ignore
 //! let files = FileManager::find_media_files("/path/to/media")?;
 //! for file in files {
 //!     if FileManager::is_image(&file) {
@@ -39,11 +45,32 @@
 //! ```
 
 use anyhow::Result;
+use std::fs::{FileTimes, Metadata};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use tokio::fs;
+use tracing::warn;
 use walkdir::WalkDir;
 
+/// Tipo di media, determinato dall'estensione
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+}
+
+impl MediaKind {
+    /// Nome per log e statistiche
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Video => "video",
+            Self::Audio => "audio",
+        }
+    }
+}
+
 /// Manages file operations and discovery
 pub struct FileManager;
 
@@ -61,57 +88,57 @@ impl FileManager {
     
     /// Find all supported media files in a directory
     pub fn find_media_files(media_dir: &Path) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
-        
-        for entry in WalkDir::new(media_dir)
+        Ok(Self::walk_media_files(media_dir).collect())
+    }
+    
+    /// Iterate over the supported media files in a directory, one directory
+    /// entry at a time (bloccante: nei contesti async va in `spawn_blocking`)
+    pub fn walk_media_files(media_dir: &Path) -> impl Iterator<Item = PathBuf> {
+        WalkDir::new(media_dir)
             .into_iter()
             .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-            if Self::is_supported_format(path) {
-                files.push(path.to_path_buf());
-            }
-        }
-        
-        Ok(files)
+            .filter(|e| e.file_type().is_file() && Self::is_supported_format(e.path()))
+            .map(walkdir::DirEntry::into_path)
     }
     
     /// Check if a file format is supported
     pub fn is_supported_format(path: &Path) -> bool {
-        if let Some(ext) = path.extension() {
-            let ext_str = ext.to_string_lossy();
-            matches!(
-                ext_str.to_lowercase().as_str(),
-                "jpg" | "jpeg" | "png" | "webp" | "mp4" | "mov" | "avi" | "mkv" | "webm"
-            )
-        } else {
-            false
+        Self::media_kind(path).is_some()
+    }
+    
+    /// Determine the media kind from the file extension
+    pub fn media_kind(path: &Path) -> Option<MediaKind> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "jpg" | "jpeg" | "png" | "webp" => Some(MediaKind::Image),
+            "mp4" | "mov" | "avi" | "mkv" | "webm" => Some(MediaKind::Video),
+            "mp3" | "wav" | "flac" | "m4a" | "aif" | "aiff" | "ogg" | "opus" => Some(MediaKind::Audio),
+            _ => None,
         }
     }
     
     /// Check if a file is an image
     pub fn is_image(path: &Path) -> bool {
-        if let Some(ext) = path.extension() {
-            let ext_str = ext.to_string_lossy();
-            matches!(ext_str.to_lowercase().as_str(), "jpg" | "jpeg" | "png" | "webp")
-        } else {
-            false
-        }
+        Self::media_kind(path) == Some(MediaKind::Image)
     }
     
     /// Check if a file is a video
     pub fn is_video(path: &Path) -> bool {
-        if let Some(ext) = path.extension() {
-            let ext_str = ext.to_string_lossy();
-            matches!(ext_str.to_lowercase().as_str(), "mp4" | "mov" | "avi" | "mkv" | "webm")
-        } else {
-            false
-        }
+        Self::media_kind(path) == Some(MediaKind::Video)
+    }
+    
+    /// Check if a file is an audio file
+    pub fn is_audio(path: &Path) -> bool {
+        Self::media_kind(path) == Some(MediaKind::Audio)
     }
     
-    /// Safely replace a file with its optimized version
-    pub async fn replace_file(original: &Path, optimized: &Path) -> Result<()> {
+    /// Safely replace a file with its optimized version.
+    ///
+    /// The replaced file keeps the original timestamps, permissions and,
+    /// if `preserve_ownership` is set, owner/group.
+    pub async fn replace_file(original: &Path, optimized: &Path, preserve_ownership: bool) -> Result<()> {
+        let original_metadata = fs::metadata(original).await?;
+
         // Create backup first
         let backup_path = original.with_extension(
             format!("{}.backup", original.extension().unwrap_or_default().to_string_lossy())
@@ -125,7 +152,7 @@ impl FileManager {
             Ok(_) => {
                 // Success - remove backup
                 let _ = fs::remove_file(&backup_path).await;
-                Ok(())
+                Self::apply_file_attributes(original, &original_metadata, preserve_ownership).await
             }
             Err(e) => {
                 // Failure - restore from backup
@@ -136,6 +163,50 @@ impl FileManager {
         }
     }
     
+    /// Copy access/modification times, permission bits and optionally
+    /// ownership from `source` to `target`
+    pub async fn copy_file_attributes(source: &Path, target: &Path, preserve_ownership: bool) -> Result<()> {
+        let metadata = fs::metadata(source).await?;
+        Self::apply_file_attributes(target, &metadata, preserve_ownership).await
+    }
+
+    /// Apply attributes captured from a source file, for outputs that
+    /// overwrite their own source (in-place mode)
+    pub async fn apply_file_attributes(target: &Path, source: &Metadata, preserve_ownership: bool) -> Result<()> {
+        let target = target.to_path_buf();
+        let source = source.clone();
+        tokio::task::spawn_blocking(move || Self::apply_file_attributes_sync(&target, &source, preserve_ownership)).await?
+    }
+
+    /// Synchronous version of [`FileManager::apply_file_attributes`].
+    ///
+    /// Times are set first (the file must still be writable), then ownership
+    /// (chown clears setuid/setgid bits), then permissions. A failed chown,
+    /// e.g. when not running as root, is only logged.
+    pub fn apply_file_attributes_sync(target: &Path, source: &Metadata, preserve_ownership: bool) -> Result<()> {
+        let mut times = FileTimes::new();
+        if let Ok(accessed) = source.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        if let Ok(modified) = source.modified() {
+            times = times.set_modified(modified);
+        }
+        std::fs::File::options().write(true).open(target)?.set_times(times)?;
+
+        #[cfg(unix)]
+        if preserve_ownership {
+            use std::os::unix::fs::MetadataExt;
+            if let Err(e) = std::os::unix::fs::chown(target, Some(source.uid()), Some(source.gid())) {
+                warn!("Failed to preserve ownership of {}: {}", target.display(), e);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = preserve_ownership;
+
+        std::fs::set_permissions(target, source.permissions())?;
+        Ok(())
+    }
+    
     /// Get human-readable file size
     pub fn format_size(size: u64) -> String {
         const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
@@ -163,3 +234,117 @@ impl FileManager {
         }
     }
 }
+
+/// Rimuove i file temporanei registrati quando esce di scope.
+///
+/// Un task cancellato viene droppato a metà di un `.await`: il codice di
+/// cleanup dopo quel punto non viene mai eseguito, il `Drop` sì.
+#[derive(Debug, Default)]
+pub struct TempFileGuard {
+    paths: Vec<PathBuf>,
+}
+
+impl TempFileGuard {
+    /// Registra un path da rimuovere e lo restituisce
+    pub fn track(&mut self, path: PathBuf) -> PathBuf {
+        self.paths.push(path.clone());
+        path
+    }
+
+    /// Rimuove subito un file registrato (es. un intermedio non più necessario)
+    pub async fn remove(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+        if let Err(e) = fs::remove_file(path).await {
+            warn!("Failed to cleanup temporary file {}: {}", path.display(), e);
+        }
+    }
+
+    /// Smette di tracciare un file completato: diventa un output definitivo
+    pub fn keep(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+    }
+}
+
+impl Drop for TempFileGuard {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            match std::fs::remove_file(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to cleanup temporary file {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_media_kind() {
+        assert_eq!(FileManager::media_kind(Path::new("/m/IMG_1.JPG")), Some(MediaKind::Image));
+        assert_eq!(FileManager::media_kind(Path::new("/m/clip.mov")), Some(MediaKind::Video));
+        assert_eq!(FileManager::media_kind(Path::new("/m/memo.m4a")), Some(MediaKind::Audio));
+        assert_eq!(FileManager::media_kind(Path::new("/m/take.AIFF")), Some(MediaKind::Audio));
+        assert_eq!(FileManager::media_kind(Path::new("/m/notes.txt")), None);
+        assert!(FileManager::is_supported_format(Path::new("/m/song.flac")));
+    }
+
+    #[tokio::test]
+    async fn test_outputs_keep_source_times_and_permissions() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("IMG_0001.jpg");
+        let output = temp_dir.path().join("IMG_0001.webp");
+        std::fs::write(&source, b"original").unwrap();
+        std::fs::write(&output, b"optimized").unwrap();
+
+        let taken = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        std::fs::File::options().write(true).open(&source).unwrap()
+            .set_times(FileTimes::new().set_accessed(taken).set_modified(taken))
+            .unwrap();
+        let mut permissions = std::fs::metadata(&source).unwrap().permissions();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(0o640);
+        }
+        #[cfg(not(unix))]
+        permissions.set_readonly(false);
+        std::fs::set_permissions(&source, permissions).unwrap();
+        let permissions = std::fs::metadata(&source).unwrap().permissions();
+
+        FileManager::copy_file_attributes(&source, &output, false).await.unwrap();
+        let metadata = std::fs::metadata(&output).unwrap();
+        assert_eq!(metadata.modified().unwrap(), taken);
+        assert_eq!(metadata.permissions(), permissions);
+
+        // Sostituzione in-place: il file sostituito mantiene la data originale
+        FileManager::replace_file(&source, &output, false).await.unwrap();
+        assert_eq!(std::fs::read(&source).unwrap(), b"optimized");
+        assert_eq!(std::fs::metadata(&source).unwrap().modified().unwrap(), taken);
+    }
+
+    #[tokio::test]
+    async fn test_temp_file_guard_cleans_up_on_cancel() {
+        let temp_dir = TempDir::new().unwrap();
+        let intermediate = temp_dir.path().join("photo_srgb_temp.jpg");
+
+        // Task cancellato mentre è fermo su un .await: il guard viene droppato
+        let path = intermediate.clone();
+        let task = tokio::spawn(async move {
+            let mut temp_files = TempFileGuard::default();
+            let path = temp_files.track(path);
+            std::fs::write(&path, b"partial").unwrap();
+            std::future::pending::<()>().await;
+        });
+        while !intermediate.exists() {
+            tokio::task::yield_now().await;
+        }
+        task.abort();
+        let _ = task.await;
+        assert!(!intermediate.exists());
+    }
+}
@@ -0,0 +1,983 @@
+//! # Image Header Probing Module
+//!
+//! Questo modulo legge le proprietà di base di un'immagine analizzando solo
+//! l'header del file, senza decodificare i pixel e senza lanciare processi esterni.
+//!
+//! ## Responsabilità:
+//! - Rileva il formato dal contenuto (magic bytes), non dall'estensione
+//! - Estrae dimensioni, orientamento EXIF, bit depth e presenza di canale alpha
+//! - Rileva il profilo colore ICC incorporato e ne legge il nome
+//! - Sostituisce `magick identify` nel percorso critico di ottimizzazione e resize
+//!
+//! ## Formati supportati:
+//! | Formato | Sorgente dimensioni | Orientamento | Profilo ICC |
+//! |---------|---------------------|--------------|-------------|
+//! | JPEG    | Marker SOFn         | APP1 Exif    | APP2 ICC_PROFILE |
+//! | PNG     | Chunk IHDR          | Chunk eXIf   | Chunk iCCP  |
+//! | WebP    | VP8 / VP8L / VP8X   | Chunk EXIF   | Chunk ICCP  |
+//! | GIF     | Logical screen      | -            | -           |
+//! | HEIF/AVIF | Box `ispe`        | Box `irot`   | Box `colr`  |
+//!
+//! ## Strategia di lettura:
+//! Il file viene letto a segmenti/chunk usando `Seek` per saltare i dati
+//! compressi: anche per file da centinaia di MB vengono letti pochi KB.
+//!
+//! ## Esempio:
+//! ```rust,ignore
+//! let header = ImageProbe::probe(Path::new("photo.jpg")).await?;
+//! let (width, height) = header.display_dimensions();
+//! ```
+
+use crate::error::OptimizeError;
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Dimensione massima di un blocco metadata letto in memoria (EXIF, box `meta`)
+const MAX_METADATA_BLOCK: u64 = 1024 * 1024;
+
+/// Image container format detected from magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Gif,
+    Heif,
+    Avif,
+}
+
+/// Properties read from an image header without decoding pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageHeader {
+    /// Detected container format
+    pub format: ImageFormat,
+    /// Stored width in pixels (before applying orientation)
+    pub width: u32,
+    /// Stored height in pixels (before applying orientation)
+    pub height: u32,
+    /// EXIF orientation (1-8, 1 = normal / tag absent)
+    pub orientation: u8,
+    /// Bits per channel (8 for most images, 10/12/16 for high bit depth)
+    pub bit_depth: u8,
+    /// Whether the image carries an alpha channel or transparency
+    pub has_alpha: bool,
+    /// Description of the embedded ICC profile (empty if it has no readable name)
+    pub icc_profile: Option<String>,
+}
+
+impl ImageHeader {
+    fn new(format: ImageFormat, width: u32, height: u32) -> Self {
+        Self {
+            format,
+            width,
+            height,
+            orientation: 1,
+            bit_depth: 8,
+            has_alpha: false,
+            icc_profile: None,
+        }
+    }
+
+    /// Returns the dimensions as displayed, swapping width/height for
+    /// orientations that rotate the image by 90° (EXIF 5-8).
+    pub fn display_dimensions(&self) -> (u32, u32) {
+        if (5..=8).contains(&self.orientation) {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// Whether the EXIF orientation asks viewers to rotate or flip the pixels.
+    ///
+    /// When true, any step that drops metadata must physically rotate the
+    /// image first, otherwise the output is displayed sideways.
+    pub fn needs_auto_orient(&self) -> bool {
+        self.orientation > 1
+    }
+
+    /// Whether the embedded ICC profile describes something other than sRGB
+    /// (Display P3, Adobe RGB, ProPhoto...), so that dropping it shifts colours.
+    ///
+    /// Untagged images are assumed to be sRGB already.
+    pub fn has_non_srgb_profile(&self) -> bool {
+        self.icc_profile
+            .as_deref()
+            .is_some_and(|name| !name.to_ascii_lowercase().contains("srgb"))
+    }
+
+    /// Total number of pixels (useful for cost estimation)
+    pub fn pixel_count(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+/// Header-only image prober
+pub struct ImageProbe;
+
+impl ImageProbe {
+    /// Probes an image file reading only its header.
+    ///
+    /// The blocking file I/O runs on tokio's blocking pool.
+    ///
+    /// # Errors
+    /// Returns `OptimizeError::UnsupportedFormat` if the magic bytes are not
+    /// recognised, or `OptimizeError::Validation` if the header is truncated.
+    pub async fn probe(path: &Path) -> Result<ImageHeader> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::probe_sync(&path)).await?
+    }
+
+    /// Synchronous version of [`ImageProbe::probe`]
+    pub fn probe_sync(path: &Path) -> Result<ImageHeader> {
+        let file = File::open(path)?;
+        Self::from_reader(&mut BufReader::new(file))
+            .map_err(|e| anyhow::anyhow!("Failed to probe {}: {}", path.display(), e))
+    }
+
+    /// Parses an image header from any seekable reader
+    pub fn from_reader<R: Read + Seek>(reader: &mut R) -> Result<ImageHeader> {
+        let mut magic = [0u8; 12];
+        let read = read_up_to(reader, &mut magic)?;
+        let magic = &magic[..read];
+        reader.seek(SeekFrom::Start(0))?;
+
+        if magic.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            parse_jpeg(reader)
+        } else if magic.starts_with(b"\x89PNG\r\n\x1a\n") {
+            parse_png(reader)
+        } else if magic.len() >= 12 && &magic[0..4] == b"RIFF" && &magic[8..12] == b"WEBP" {
+            parse_webp(reader)
+        } else if magic.starts_with(b"GIF87a") || magic.starts_with(b"GIF89a") {
+            parse_gif(reader)
+        } else if magic.len() >= 8 && &magic[4..8] == b"ftyp" {
+            parse_heif(reader)
+        } else {
+            Err(OptimizeError::UnsupportedFormat("unrecognised image signature".to_string()).into())
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers di lettura
+// ---------------------------------------------------------------------------
+
+fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| OptimizeError::Validation("truncated image header".to_string()))?;
+    Ok(buf)
+}
+
+fn read_vec<R: Read>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+    if len > MAX_METADATA_BLOCK {
+        return Err(OptimizeError::Validation(format!("metadata block too large ({} bytes)", len)).into());
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| OptimizeError::Validation("truncated image header".to_string()))?;
+    Ok(buf)
+}
+
+fn be_u16(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+fn be_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn le_u32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn le_u24(b: &[u8]) -> u32 {
+    b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16
+}
+
+/// Legge il tag Orientation (0x0112) dall'IFD0 di un blocco TIFF/EXIF.
+///
+/// `tiff` deve iniziare con l'header TIFF (`II*\0` o `MM\0*`).
+pub(crate) fn parse_exif_orientation(tiff: &[u8]) -> Option<u8> {
+    if tiff.len() < 8 {
+        return None;
+    }
+    let little_endian = match &tiff[0..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |off: usize| -> Option<u16> {
+        let b = tiff.get(off..off + 2)?;
+        Some(if little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) })
+    };
+    let u32_at = |off: usize| -> Option<u32> {
+        let b = tiff.get(off..off + 4)?;
+        Some(if little_endian { le_u32(b) } else { be_u32(b) })
+    };
+
+    let ifd0 = u32_at(4)? as usize;
+    let entries = u16_at(ifd0)? as usize;
+    for i in 0..entries {
+        let entry = ifd0 + 2 + i * 12;
+        if u16_at(entry)? == 0x0112 {
+            let value = u16_at(entry + 8)?;
+            return (1..=8).contains(&value).then_some(value as u8);
+        }
+    }
+    None
+}
+
+/// Legge la descrizione di un profilo ICC (tag `desc`, tipo v2 `desc` o v4 `mluc`).
+pub(crate) fn parse_icc_description(profile: &[u8]) -> Option<String> {
+    let tag_count = be_u32(profile.get(128..132)?) as usize;
+    let (offset, size) = (0..tag_count.min(256)).find_map(|i| {
+        let entry = profile.get(132 + i * 12..144 + i * 12)?;
+        (&entry[0..4] == b"desc").then(|| (be_u32(&entry[4..8]) as usize, be_u32(&entry[8..12]) as usize))
+    })?;
+    let tag = profile.get(offset..offset.checked_add(size)?)?;
+
+    let text = match tag.get(0..4)? {
+        b"desc" => {
+            let len = be_u32(tag.get(8..12)?) as usize;
+            String::from_utf8_lossy(tag.get(12..12 + len)?).into_owned()
+        }
+        b"mluc" => {
+            // Primo record: usa la prima lingua disponibile
+            let len = be_u32(tag.get(20..24)?) as usize;
+            let start = be_u32(tag.get(24..28)?) as usize;
+            let units: Vec<u16> = tag.get(start..start + len)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+    Some(text.trim_end_matches('\0').trim().to_string())
+}
+
+// ---------------------------------------------------------------------------
+// JPEG
+// ---------------------------------------------------------------------------
+
+fn parse_jpeg<R: Read + Seek>(reader: &mut R) -> Result<ImageHeader> {
+    reader.seek(SeekFrom::Start(2))?;
+    let mut orientation = 1;
+    // Il profilo ICC può essere diviso su più segmenti APP2 consecutivi
+    let mut icc: Option<Vec<u8>> = None;
+
+    loop {
+        // Salta eventuali byte di fill 0xFF prima del marker
+        let mut marker = read_array::<R, 1>(reader)?[0];
+        if marker != 0xFF {
+            return Err(OptimizeError::Validation("invalid JPEG marker".to_string()).into());
+        }
+        while marker == 0xFF {
+            marker = read_array::<R, 1>(reader)?[0];
+        }
+
+        // Marker senza payload (RSTn, TEM)
+        if (0xD0..=0xD7).contains(&marker) || marker == 0x01 {
+            continue;
+        }
+        if marker == 0xD9 || marker == 0xDA {
+            return Err(OptimizeError::Validation("no SOF marker before image data".to_string()).into());
+        }
+
+        let length = be_u16(&read_array::<R, 2>(reader)?) as u64;
+        if length < 2 {
+            return Err(OptimizeError::Validation("invalid JPEG segment length".to_string()).into());
+        }
+        let payload_len = length - 2;
+
+        match marker {
+            // SOF0-SOF15, esclusi DHT (C4), JPG (C8) e DAC (CC)
+            0xC0..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                let sof = read_array::<R, 6>(reader)?;
+                let bit_depth = sof[0];
+                let height = be_u16(&sof[1..3]) as u32;
+                let width = be_u16(&sof[3..5]) as u32;
+                let mut header = ImageHeader::new(ImageFormat::Jpeg, width, height);
+                header.bit_depth = bit_depth;
+                header.orientation = orientation;
+                header.icc_profile = icc.map(|data| parse_icc_description(&data).unwrap_or_default());
+                return Ok(header);
+            }
+            // APP1: potenziale blocco Exif
+            0xE1 => {
+                let data = read_vec(reader, payload_len)?;
+                if data.starts_with(b"Exif\0\0") {
+                    if let Some(o) = parse_exif_orientation(&data[6..]) {
+                        orientation = o;
+                    }
+                }
+            }
+            // APP2: potenziale chunk ICC_PROFILE (14 byte di intestazione)
+            0xE2 => {
+                let data = read_vec(reader, payload_len)?;
+                if let Some(chunk) = data.strip_prefix(b"ICC_PROFILE\0").and_then(|d| d.get(2..)) {
+                    let profile = icc.get_or_insert_with(Vec::new);
+                    if (profile.len() + chunk.len()) as u64 <= MAX_METADATA_BLOCK {
+                        profile.extend_from_slice(chunk);
+                    }
+                }
+            }
+            _ => {
+                reader.seek(SeekFrom::Current(payload_len as i64))?;
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PNG
+// ---------------------------------------------------------------------------
+
+fn parse_png<R: Read + Seek>(reader: &mut R) -> Result<ImageHeader> {
+    reader.seek(SeekFrom::Start(8))?;
+
+    let ihdr_head = read_array::<R, 8>(reader)?;
+    if &ihdr_head[4..8] != b"IHDR" {
+        return Err(OptimizeError::Validation("PNG without IHDR chunk".to_string()).into());
+    }
+    let ihdr = read_array::<R, 13>(reader)?;
+    let width = be_u32(&ihdr[0..4]);
+    let height = be_u32(&ihdr[4..8]);
+    let bit_depth = ihdr[8];
+    let color_type = ihdr[9];
+
+    let mut header = ImageHeader::new(ImageFormat::Png, width, height);
+    header.bit_depth = bit_depth;
+    // Color type 4 = grayscale+alpha, 6 = RGBA
+    header.has_alpha = color_type == 4 || color_type == 6;
+
+    // Salta CRC di IHDR e scorre i chunk ancillari fino a IDAT
+    reader.seek(SeekFrom::Current(4))?;
+    loop {
+        let chunk = match read_array::<R, 8>(reader) {
+            Ok(chunk) => chunk,
+            Err(_) => break,
+        };
+        let length = be_u32(&chunk[0..4]) as u64;
+        match &chunk[4..8] {
+            b"IDAT" | b"IEND" => break,
+            b"tRNS" => {
+                header.has_alpha = true;
+                reader.seek(SeekFrom::Current(length as i64 + 4))?;
+            }
+            b"eXIf" => {
+                let data = read_vec(reader, length)?;
+                if let Some(o) = parse_exif_orientation(&data) {
+                    header.orientation = o;
+                }
+                reader.seek(SeekFrom::Current(4))?;
+            }
+            // iCCP: nome del profilo, NUL, metodo di compressione, profilo zlib
+            b"iCCP" => {
+                let data = read_vec(reader, length)?;
+                let name = data.split(|b| *b == 0).next().unwrap_or(&[]);
+                header.icc_profile = Some(String::from_utf8_lossy(name).into_owned());
+                reader.seek(SeekFrom::Current(4))?;
+            }
+            _ => {
+                reader.seek(SeekFrom::Current(length as i64 + 4))?;
+            }
+        }
+    }
+
+    Ok(header)
+}
+
+// ---------------------------------------------------------------------------
+// WebP
+// ---------------------------------------------------------------------------
+
+fn parse_webp<R: Read + Seek>(reader: &mut R) -> Result<ImageHeader> {
+    reader.seek(SeekFrom::Start(12))?;
+    let mut header: Option<ImageHeader> = None;
+    let mut vp8x_flags = 0u8;
+
+    loop {
+        let chunk = match read_array::<R, 8>(reader) {
+            Ok(chunk) => chunk,
+            Err(_) => break,
+        };
+        let fourcc = [chunk[0], chunk[1], chunk[2], chunk[3]];
+        let length = le_u32(&chunk[4..8]) as u64;
+        // I chunk RIFF sono allineati a 2 byte
+        let padded = length + (length & 1);
+
+        match &fourcc {
+            b"VP8X" => {
+                let data = read_array::<R, 10>(reader)?;
+                vp8x_flags = data[0];
+                let mut h = ImageHeader::new(ImageFormat::WebP, le_u24(&data[4..7]) + 1, le_u24(&data[7..10]) + 1);
+                h.has_alpha = vp8x_flags & 0x10 != 0;
+                header = Some(h);
+                // Senza flag EXIF (0x08) o ICC (0x20) non serve scorrere il resto del file
+                if vp8x_flags & 0x28 == 0 {
+                    break;
+                }
+                reader.seek(SeekFrom::Current(padded as i64 - 10))?;
+            }
+            b"VP8 " => {
+                let data = read_array::<R, 10>(reader)?;
+                if data[3..6] != [0x9D, 0x01, 0x2A] {
+                    return Err(OptimizeError::Validation("invalid VP8 start code".to_string()).into());
+                }
+                let width = (u16::from_le_bytes([data[6], data[7]]) & 0x3FFF) as u32;
+                let height = (u16::from_le_bytes([data[8], data[9]]) & 0x3FFF) as u32;
+                if header.is_none() {
+                    return Ok(ImageHeader::new(ImageFormat::WebP, width, height));
+                }
+                reader.seek(SeekFrom::Current(padded as i64 - 10))?;
+            }
+            b"VP8L" => {
+                let data = read_array::<R, 5>(reader)?;
+                if data[0] != 0x2F {
+                    return Err(OptimizeError::Validation("invalid VP8L signature".to_string()).into());
+                }
+                let bits = le_u32(&data[1..5]);
+                let width = (bits & 0x3FFF) + 1;
+                let height = ((bits >> 14) & 0x3FFF) + 1;
+                if header.is_none() {
+                    let mut h = ImageHeader::new(ImageFormat::WebP, width, height);
+                    h.has_alpha = (bits >> 28) & 1 == 1;
+                    return Ok(h);
+                }
+                reader.seek(SeekFrom::Current(padded as i64 - 5))?;
+            }
+            b"ICCP" => {
+                let data = read_vec(reader, length)?;
+                if let Some(h) = header.as_mut() {
+                    h.icc_profile = Some(parse_icc_description(&data).unwrap_or_default());
+                }
+                if vp8x_flags & 0x08 == 0 {
+                    break;
+                }
+                reader.seek(SeekFrom::Current(padded as i64 - length as i64))?;
+            }
+            b"EXIF" => {
+                let data = read_vec(reader, length)?;
+                // Alcuni encoder includono il prefisso "Exif\0\0" anche qui
+                let tiff = data.strip_prefix(b"Exif\0\0").unwrap_or(&data);
+                if let (Some(h), Some(o)) = (header.as_mut(), parse_exif_orientation(tiff)) {
+                    h.orientation = o;
+                }
+                break;
+            }
+            _ => {
+                reader.seek(SeekFrom::Current(padded as i64))?;
+            }
+        }
+    }
+
+    header.ok_or_else(|| OptimizeError::Validation("WebP without image chunk".to_string()).into())
+}
+
+// ---------------------------------------------------------------------------
+// GIF
+// ---------------------------------------------------------------------------
+
+fn parse_gif<R: Read + Seek>(reader: &mut R) -> Result<ImageHeader> {
+    reader.seek(SeekFrom::Start(6))?;
+    let screen = read_array::<R, 7>(reader)?;
+    let width = u16::from_le_bytes([screen[0], screen[1]]) as u32;
+    let height = u16::from_le_bytes([screen[2], screen[3]]) as u32;
+    let packed = screen[4];
+
+    let mut header = ImageHeader::new(ImageFormat::Gif, width, height);
+    // Color resolution: bit per canale primario della palette originale
+    header.bit_depth = ((packed >> 4) & 0x07) + 1;
+
+    if packed & 0x80 != 0 {
+        let table_size = 3 * (1u64 << ((packed & 0x07) + 1));
+        reader.seek(SeekFrom::Current(table_size as i64))?;
+    }
+
+    // Cerca la trasparenza nella Graphic Control Extension del primo frame
+    loop {
+        let introducer = match read_array::<R, 1>(reader) {
+            Ok(b) => b[0],
+            Err(_) => break,
+        };
+        match introducer {
+            0x21 => {
+                let label = read_array::<R, 1>(reader)?[0];
+                if label == 0xF9 {
+                    let gce = read_array::<R, 6>(reader)?;
+                    header.has_alpha = gce[1] & 0x01 != 0;
+                    break;
+                }
+                skip_gif_sub_blocks(reader)?;
+            }
+            // Image descriptor o trailer: nessuna GCE prima del primo frame
+            _ => break,
+        }
+    }
+
+    Ok(header)
+}
+
+fn skip_gif_sub_blocks<R: Read + Seek>(reader: &mut R) -> Result<()> {
+    loop {
+        let size = read_array::<R, 1>(reader)?[0];
+        if size == 0 {
+            return Ok(());
+        }
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// HEIF / AVIF (ISO base media file format)
+// ---------------------------------------------------------------------------
+
+/// Legge l'header di un box ISOBMFF, ritornando (tipo, dimensione payload)
+fn read_box_header<R: Read + Seek>(reader: &mut R) -> Result<([u8; 4], u64)> {
+    let head = read_array::<R, 8>(reader)?;
+    let size = be_u32(&head[0..4]) as u64;
+    let box_type = [head[4], head[5], head[6], head[7]];
+    let payload = match size {
+        1 => {
+            let large = u64::from_be_bytes(read_array::<R, 8>(reader)?);
+            large.checked_sub(16)
+        }
+        // size 0 = fino a fine file: non supportato per i box che ci interessano
+        0 => None,
+        _ => size.checked_sub(8),
+    }
+    .ok_or_else(|| OptimizeError::Validation("invalid ISOBMFF box size".to_string()))?;
+    Ok((box_type, payload))
+}
+
+/// Itera sui box contenuti in un buffer già letto in memoria
+fn iter_boxes(mut data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < 8 {
+            return None;
+        }
+        let size = be_u32(&data[0..4]) as usize;
+        let box_type = [data[4], data[5], data[6], data[7]];
+        if size < 8 || size > data.len() {
+            return None;
+        }
+        let payload = &data[8..size];
+        data = &data[size..];
+        Some((box_type, payload))
+    })
+}
+
+fn parse_heif<R: Read + Seek>(reader: &mut R) -> Result<ImageHeader> {
+    let (box_type, ftyp_len) = read_box_header(reader)?;
+    if &box_type != b"ftyp" {
+        return Err(OptimizeError::UnsupportedFormat("missing ftyp box".to_string()).into());
+    }
+    let ftyp = read_vec(reader, ftyp_len)?;
+    let brands: Vec<&[u8]> = std::iter::once(&ftyp[0..4.min(ftyp.len())])
+        .chain(ftyp.get(8..).unwrap_or(&[]).chunks_exact(4))
+        .collect();
+    let format = if brands.iter().any(|b| *b == b"avif" || *b == b"avis") {
+        ImageFormat::Avif
+    } else if brands.iter().any(|b| matches!(*b, b"heic" | b"heix" | b"heim" | b"heis" | b"mif1" | b"msf1")) {
+        ImageFormat::Heif
+    } else {
+        return Err(OptimizeError::UnsupportedFormat("ISOBMFF file is not HEIF/AVIF".to_string()).into());
+    };
+
+    // Cerca il box `meta` tra i box di primo livello
+    let meta = loop {
+        let (box_type, len) = read_box_header(reader)?;
+        if &box_type == b"meta" {
+            break read_vec(reader, len)?;
+        }
+        reader.seek(SeekFrom::Current(len as i64))?;
+    };
+
+    // `meta` è un FullBox: 4 byte di version/flags prima dei figli
+    let iprp = iter_boxes(meta.get(4..).unwrap_or(&[]))
+        .find(|(t, _)| t == b"iprp")
+        .map(|(_, p)| p)
+        .ok_or_else(|| OptimizeError::Validation("HEIF without iprp box".to_string()))?;
+    let ipco = iter_boxes(iprp)
+        .find(|(t, _)| t == b"ipco")
+        .map(|(_, p)| p)
+        .ok_or_else(|| OptimizeError::Validation("HEIF without ipco box".to_string()))?;
+
+    let mut dims: Option<(u32, u32)> = None;
+    let mut header_bits = None;
+    let mut orientation = 1;
+    let mut has_alpha = false;
+    let mut icc_profile = None;
+
+    for (box_type, payload) in iter_boxes(ipco) {
+        match &box_type {
+            // Più `ispe` possono esistere (tile, thumbnail): la più grande è l'immagine principale
+            b"ispe" if payload.len() >= 12 => {
+                let w = be_u32(&payload[4..8]);
+                let h = be_u32(&payload[8..12]);
+                if dims.is_none_or(|(dw, dh)| (w as u64 * h as u64) > (dw as u64 * dh as u64)) {
+                    dims = Some((w, h));
+                }
+            }
+            b"pixi" if payload.len() >= 6 => {
+                header_bits.get_or_insert(payload[5]);
+            }
+            // irot: rotazione antioraria in step di 90°
+            b"irot" if !payload.is_empty() => {
+                orientation = match payload[0] & 0x03 {
+                    1 => 8,
+                    2 => 3,
+                    3 => 6,
+                    _ => 1,
+                };
+            }
+            // colr: `prof`/`rICC` incorporano un profilo ICC, `nclx` solo parametri CICP
+            b"colr" if payload.len() > 4 && matches!(&payload[0..4], b"prof" | b"rICC") => {
+                icc_profile = Some(parse_icc_description(&payload[4..]).unwrap_or_default());
+            }
+            b"auxC" if payload.len() > 4 => {
+                let aux_type = String::from_utf8_lossy(&payload[4..]);
+                if aux_type.contains("alpha") {
+                    has_alpha = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let (width, height) = dims.ok_or_else(|| OptimizeError::Validation("HEIF without ispe box".to_string()))?;
+    let mut header = ImageHeader::new(format, width, height);
+    header.orientation = orientation;
+    header.bit_depth = header_bits.unwrap_or(8);
+    header.has_alpha = has_alpha;
+    header.icc_profile = icc_profile;
+    Ok(header)
+}
+
+/// Fixture condivise dai test dei moduli immagine
+#[cfg(test)]
+pub(crate) mod fixtures {
+    /// Costruisce un blocco TIFF big-endian con il solo tag Orientation
+    pub(crate) fn tiff_with_orientation(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        tiff.extend_from_slice(&1u16.to_be_bytes());
+        tiff.extend_from_slice(&0x0112u16.to_be_bytes());
+        tiff.extend_from_slice(&3u16.to_be_bytes()); // SHORT
+        tiff.extend_from_slice(&1u32.to_be_bytes());
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0]);
+        tiff.extend_from_slice(&0u32.to_be_bytes());
+        tiff
+    }
+
+    /// Costruisce un profilo ICC v2 minimale con il solo tag `desc`
+    pub(crate) fn icc_profile(description: &str) -> Vec<u8> {
+        let mut desc = b"desc\0\0\0\0".to_vec();
+        desc.extend_from_slice(&(description.len() as u32 + 1).to_be_bytes());
+        desc.extend_from_slice(description.as_bytes());
+        desc.push(0);
+
+        let mut profile = vec![0u8; 128];
+        profile[36..40].copy_from_slice(b"acsp");
+        profile.extend_from_slice(&1u32.to_be_bytes());
+        profile.extend_from_slice(b"desc");
+        profile.extend_from_slice(&144u32.to_be_bytes());
+        profile.extend_from_slice(&(desc.len() as u32).to_be_bytes());
+        profile.extend(desc);
+        let size = profile.len() as u32;
+        profile[0..4].copy_from_slice(&size.to_be_bytes());
+        profile
+    }
+
+    /// JPEG wide-gamut: come [`jpeg_with_orientation`] più un APP2 ICC_PROFILE
+    pub(crate) fn jpeg_with_icc(width: u32, height: u32, description: &str) -> Vec<u8> {
+        let data = jpeg_with_orientation(width, height, 1);
+        let mut app2 = b"ICC_PROFILE\0\x01\x01".to_vec();
+        app2.extend(icc_profile(description));
+        let mut out = data[..2].to_vec();
+        out.extend_from_slice(&[0xFF, 0xE2]);
+        out.extend_from_slice(&((app2.len() + 2) as u16).to_be_bytes());
+        out.extend(app2);
+        out.extend_from_slice(&data[2..]);
+        out
+    }
+
+    /// Codifica un vero JPEG `width`x`height` e inserisce un APP1 Exif con
+    /// l'orientamento richiesto subito dopo SOI.
+    pub(crate) fn jpeg_with_orientation(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+        let pixels: Vec<u8> = (0..width * height)
+            .flat_map(|i| [(i % 256) as u8, 128, 64])
+            .collect();
+        let mut encoded = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new(&mut encoded)
+            .encode(&pixels, width, height, image::ColorType::Rgb8)
+            .expect("JPEG fixture encoding");
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff_with_orientation(orientation));
+        let mut data = encoded[..2].to_vec();
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+        data.extend(app1);
+        data.extend_from_slice(&encoded[2..]);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{icc_profile, jpeg_with_icc, jpeg_with_orientation, tiff_with_orientation};
+    use super::*;
+    use std::io::Cursor;
+
+    fn probe(bytes: Vec<u8>) -> Result<ImageHeader> {
+        ImageProbe::from_reader(&mut Cursor::new(bytes))
+    }
+
+    fn jpeg(width: u16, height: u16, orientation: Option<u16>) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        // APP0 JFIF
+        data.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        data.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        if let Some(o) = orientation {
+            let mut app1 = b"Exif\0\0".to_vec();
+            app1.extend(tiff_with_orientation(o));
+            data.extend_from_slice(&[0xFF, 0xE1]);
+            data.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+            data.extend(app1);
+        }
+        // SOF0
+        data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&[0x03, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        data.extend_from_slice(&[0xFF, 0xDA]);
+        data
+    }
+
+    fn png_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunk = (payload.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(payload);
+        chunk.extend_from_slice(&[0, 0, 0, 0]); // CRC non verificato
+        chunk
+    }
+
+    fn png(width: u32, height: u32, bit_depth: u8, color_type: u8, extra: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+        data.extend(png_chunk(b"IHDR", &ihdr));
+        for chunk in extra {
+            data.extend_from_slice(chunk);
+        }
+        data.extend(png_chunk(b"IDAT", &[0; 4]));
+        data
+    }
+
+    fn riff(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = b"WEBP".to_vec();
+        for (kind, payload) in chunks {
+            body.extend_from_slice(*kind);
+            body.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            body.extend_from_slice(payload);
+            if payload.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut data = b"RIFF".to_vec();
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend(body);
+        data
+    }
+
+    fn iso_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_jpeg_dimensions_and_orientation() {
+        let header = probe(jpeg(4032, 3024, Some(6))).unwrap();
+        assert_eq!(header.format, ImageFormat::Jpeg);
+        assert_eq!((header.width, header.height), (4032, 3024));
+        assert_eq!(header.orientation, 6);
+        assert_eq!(header.bit_depth, 8);
+        assert!(!header.has_alpha);
+        assert_eq!(header.display_dimensions(), (3024, 4032));
+
+        let header = probe(jpeg(640, 480, None)).unwrap();
+        assert_eq!(header.orientation, 1);
+        assert_eq!(header.display_dimensions(), (640, 480));
+    }
+
+    #[test]
+    fn test_all_exif_orientations() {
+        for orientation in 1..=8u16 {
+            let header = probe(jpeg_with_orientation(40, 20, orientation)).unwrap();
+            assert_eq!(header.orientation, orientation as u8);
+            assert_eq!((header.width, header.height), (40, 20));
+            assert_eq!(header.needs_auto_orient(), orientation > 1);
+
+            let expected = if orientation >= 5 { (20, 40) } else { (40, 20) };
+            assert_eq!(header.display_dimensions(), expected, "orientation {}", orientation);
+        }
+    }
+
+    #[test]
+    fn test_png_alpha_and_exif() {
+        let header = probe(png(800, 600, 16, 6, &[])).unwrap();
+        assert_eq!(header.format, ImageFormat::Png);
+        assert_eq!((header.width, header.height), (800, 600));
+        assert_eq!(header.bit_depth, 16);
+        assert!(header.has_alpha);
+
+        let exif = png_chunk(b"eXIf", &tiff_with_orientation(8));
+        let trns = png_chunk(b"tRNS", &[0, 0]);
+        let header = probe(png(100, 50, 8, 2, &[exif, trns])).unwrap();
+        assert!(header.has_alpha);
+        assert_eq!(header.orientation, 8);
+    }
+
+    #[test]
+    fn test_webp_variants() {
+        // VP8 lossy
+        let mut vp8 = vec![0x10, 0x02, 0x00, 0x9D, 0x01, 0x2A];
+        vp8.extend_from_slice(&1920u16.to_le_bytes());
+        vp8.extend_from_slice(&1080u16.to_le_bytes());
+        let header = probe(riff(&[(b"VP8 ", vp8)])).unwrap();
+        assert_eq!((header.width, header.height), (1920, 1080));
+        assert!(!header.has_alpha);
+
+        // VP8L lossless con alpha
+        let bits: u32 = (300 - 1) | ((200 - 1) << 14) | (1 << 28);
+        let mut vp8l = vec![0x2F];
+        vp8l.extend_from_slice(&bits.to_le_bytes());
+        let header = probe(riff(&[(b"VP8L", vp8l)])).unwrap();
+        assert_eq!((header.width, header.height), (300, 200));
+        assert!(header.has_alpha);
+
+        // VP8X esteso con EXIF in coda
+        let mut vp8x = vec![0x10 | 0x08, 0, 0, 0];
+        vp8x.extend_from_slice(&(5000u32 - 1).to_le_bytes()[..3]);
+        vp8x.extend_from_slice(&(3000u32 - 1).to_le_bytes()[..3]);
+        let header = probe(riff(&[
+            (b"VP8X", vp8x),
+            (b"ALPH", vec![0; 3]),
+            (b"EXIF", tiff_with_orientation(3)),
+        ]))
+        .unwrap();
+        assert_eq!((header.width, header.height), (5000, 3000));
+        assert!(header.has_alpha);
+        assert_eq!(header.orientation, 3);
+    }
+
+    #[test]
+    fn test_icc_profile_detection() {
+        let header = probe(jpeg_with_icc(32, 16, "Display P3")).unwrap();
+        assert_eq!(header.icc_profile.as_deref(), Some("Display P3"));
+        assert!(header.has_non_srgb_profile());
+
+        let header = probe(jpeg_with_icc(32, 16, "sRGB IEC61966-2.1")).unwrap();
+        assert!(!header.has_non_srgb_profile());
+
+        let header = probe(jpeg_with_orientation(32, 16, 1)).unwrap();
+        assert_eq!(header.icc_profile, None);
+        assert!(!header.has_non_srgb_profile());
+
+        // PNG: il nome è la keyword del chunk iCCP
+        let iccp = png_chunk(b"iCCP", b"Adobe RGB (1998)\0\0\x78\x9c");
+        let header = probe(png(10, 10, 8, 2, &[iccp])).unwrap();
+        assert_eq!(header.icc_profile.as_deref(), Some("Adobe RGB (1998)"));
+
+        // WebP: flag ICC nel VP8X e chunk ICCP subito dopo
+        let mut vp8x = vec![0x20, 0, 0, 0];
+        vp8x.extend_from_slice(&[9, 0, 0, 9, 0, 0]);
+        let header = probe(riff(&[(b"VP8X", vp8x), (b"ICCP", icc_profile("ProPhoto RGB"))])).unwrap();
+        assert_eq!(header.icc_profile.as_deref(), Some("ProPhoto RGB"));
+    }
+
+    #[test]
+    fn test_gif_transparency() {
+        let mut data = b"GIF89a".to_vec();
+        data.extend_from_slice(&320u16.to_le_bytes());
+        data.extend_from_slice(&240u16.to_le_bytes());
+        data.extend_from_slice(&[0xF1, 0, 0]); // global table 4 colori, color res 8 bit
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[0x21, 0xF9, 0x04, 0x01, 0, 0, 0, 0]);
+        data.push(0x2C);
+        let header = probe(data).unwrap();
+        assert_eq!(header.format, ImageFormat::Gif);
+        assert_eq!((header.width, header.height), (320, 240));
+        assert_eq!(header.bit_depth, 8);
+        assert!(header.has_alpha);
+    }
+
+    #[test]
+    fn test_heif_and_avif_boxes() {
+        let mut ispe = vec![0, 0, 0, 0];
+        ispe.extend_from_slice(&4032u32.to_be_bytes());
+        ispe.extend_from_slice(&3024u32.to_be_bytes());
+        let mut thumb = vec![0, 0, 0, 0];
+        thumb.extend_from_slice(&320u32.to_be_bytes());
+        thumb.extend_from_slice(&240u32.to_be_bytes());
+        let mut ipco = iso_box(b"ispe", &thumb);
+        ipco.extend(iso_box(b"ispe", &ispe));
+        ipco.extend(iso_box(b"pixi", &[0, 0, 0, 0, 3, 10, 10, 10]));
+        ipco.extend(iso_box(b"irot", &[3]));
+        let mut aux = vec![0, 0, 0, 0];
+        aux.extend_from_slice(b"urn:mpeg:mpegB:cicp:systems:auxiliary:alpha\0");
+        ipco.extend(iso_box(b"auxC", &aux));
+        let iprp = iso_box(b"iprp", &iso_box(b"ipco", &ipco));
+        let mut meta = vec![0, 0, 0, 0];
+        meta.extend(iso_box(b"hdlr", &[0; 24]));
+        meta.extend(iprp);
+
+        let build = |major: &[u8; 4]| {
+            let mut ftyp = major.to_vec();
+            ftyp.extend_from_slice(&[0, 0, 0, 0]);
+            ftyp.extend_from_slice(b"mif1");
+            let mut data = iso_box(b"ftyp", &ftyp);
+            data.extend(iso_box(b"meta", &meta));
+            data
+        };
+
+        let header = probe(build(b"heic")).unwrap();
+        assert_eq!(header.format, ImageFormat::Heif);
+        assert_eq!((header.width, header.height), (4032, 3024));
+        assert_eq!(header.bit_depth, 10);
+        assert_eq!(header.orientation, 6);
+        assert!(header.has_alpha);
+
+        let header = probe(build(b"avif")).unwrap();
+        assert_eq!(header.format, ImageFormat::Avif);
+    }
+
+    #[test]
+    fn test_unsupported_and_truncated() {
+        assert!(probe(b"not an image at all".to_vec()).is_err());
+        let mut truncated = jpeg(10, 10, None);
+        truncated.truncate(20);
+        assert!(probe(truncated).is_err());
+    }
+}
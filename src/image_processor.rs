@@ -19,6 +19,9 @@
 //!   - Richiede tool esterni installati nel sistema
 //!   - Overhead di process spawning per ogni immagine
 //! 
+//! Le sole informazioni lette in-process sono quelle dell'header (dimensioni,
+//! orientamento) tramite `image_probe`, per evitare un `magick identify` per file.
+//! 
 //! ## Formati Supportati
 //! 
 //! | Formato | Input | Output | Tool Utilizzati |
@@ -31,10 +34,21 @@
 //! 
 //! 1. **Rilevamento formato**: Analizza estensione file (case-insensitive)
 //! 2. **Decisione conversione**: WebP se `convert_to_webp = true`
-//! 3. **Calcolo path output**: Preserva struttura directory o in-place
-//! 4. **Creazione directory**: Async creation delle directory parent
-//! 5. **Tool selection**: Priorità decrescente per ogni formato
-//! 6. **Strict error handling**: Errore se nessun tool disponibile
+//! 3. **Orientamento EXIF**: Se il passaggio scarta i metadata, i pixel vengono
+//!    ruotati fisicamente (`-auto-orient`) prima dell'encoding
+//! 4. **Calcolo path output**: Preserva struttura directory o in-place
+//! 5. **Creazione directory**: Async creation delle directory parent
+//! 6. **Tool selection**: Priorità decrescente per ogni formato
+//! 7. **Strict error handling**: Errore se nessun tool disponibile
+//! 8. **Metadata policy**: `config.metadata_policy` applicata all'output via exiftool
+//! 9. **Attributi file**: date, permessi (e owner se richiesto) copiati dall'originale
+//! 
+//! ## Profili colore ICC
+//! 
+//! Il profilo incorporato (Display P3, Adobe RGB...) viene sempre mantenuto:
+//! cwebp con `-metadata icc`, oxipng con `--strip safe`. Con
+//! `color_profile = ConvertToSrgb` i pixel vengono prima convertiti in sRGB
+//! (`vips icc_transform` o `magick -profile`) e l'output riceve il profilo sRGB.
 //! 
 //! ## Strategia Tool Selection
 //! 
@@ -96,7 +110,7 @@
 //! 
 //! ## Esempi d'Uso
 //! 
-//! ```rust
+//! ```rust,ignore
 //! use image_processor::ImageProcessor;
 //! 
 //! // Creazione processore con configurazione
@@ -117,11 +131,18 @@
 //! processor.// print_available_tools().await;
 //! ```
 
-use crate::config::Config;
+use crate::config::{ColorProfilePolicy, Config, MetadataPolicy};
+use crate::error::OptimizeError;
+use crate::file_manager::FileManager;
+use crate::image_probe::ImageProbe;
+use crate::metadata::MetadataWriter;
 use crate::platform::PlatformCommands;
 use crate::utils::to_string_vec;
 use anyhow::Result;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tempfile::{NamedTempFile, TempPath};
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use tokio::sync::broadcast;
 use tracing::{debug, info, warn, error};
@@ -169,7 +190,7 @@ impl ImageProcessor {
     /// * `Result<Self>` - A new ImageProcessor instance
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let config = Config::default();
     /// let processor = ImageProcessor::new(config).await?;
     /// ```
@@ -190,7 +211,7 @@ impl ImageProcessor {
     /// * `Result<Self>` - A new ImageProcessor instance with cancellation support
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let (stop_sender, stop_receiver) = broadcast::channel(1);
     /// let config = Config::default();
     /// let processor = ImageProcessor::new_with_cancellation(config, stop_receiver).await?;
@@ -268,7 +289,7 @@ impl ImageProcessor {
     /// - **Other**: Returns error (no optimization possible)
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let processor = ImageProcessor::new(config).await?;
     /// let output_path = processor.optimize(
     ///     Path::new("/input/photos/image.jpg"), 
@@ -278,19 +299,44 @@ impl ImageProcessor {
     pub async fn optimize(&mut self, input_path: &Path, input_base_dir: &Path) -> Result<PathBuf> {
         // Check for cancellation before starting
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Image optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("Image optimization".to_string()).into());
         }
 
-        // Pre-resize large images to 2.5K if needed
-        let actual_input_path = if self.is_larger_than_4k(input_path).await.unwrap_or(false) {
-            let temp_resized_path = self.create_temp_resized_path(input_path)?;
+        // Captured now: in-place mode overwrites the original
+        let source_metadata = tokio::fs::metadata(input_path).await?;
+        // Intermediates are unique temp files, removed on drop on every exit
+        // path (including cancellation)
+
+        // Pre-resize large images to 2.5K if needed (also bakes the EXIF orientation)
+        let mut oriented_temp = if self.is_larger_than_4k(input_path).await.unwrap_or(false) {
+            let temp_resized_path = Self::create_temp_file(input_path)?;
             self.pre_resize_to_4k(input_path, &temp_resized_path).await?;
             info!("Pre-resized large image {} to 2.5K at {}", 
                   input_path.display(), temp_resized_path.display());
-            temp_resized_path
+            Some(temp_resized_path)
+        } else if self.needs_orientation_bake(input_path).await {
+            let temp_oriented_path = Self::create_temp_file(input_path)?;
+            self.auto_orient(input_path, &temp_oriented_path).await?;
+            info!("Rotated {} according to EXIF orientation before metadata-stripping encode",
+                  input_path.display());
+            Some(temp_oriented_path)
         } else {
-            input_path.to_path_buf()
+            None
         };
+        let orientation_baked = oriented_temp.is_some();
+
+        // Convert wide-gamut pixels to sRGB if requested (the profile is read from the original)
+        let srgb_temp = if self.needs_srgb_conversion(input_path).await {
+            let temp_srgb_path = Self::create_temp_file(input_path)?;
+            self.convert_to_srgb(oriented_temp.as_deref().unwrap_or(input_path), &temp_srgb_path).await?;
+            // The oriented copy is no longer needed
+            oriented_temp = None;
+            info!("Converted {} to sRGB", input_path.display());
+            Some(temp_srgb_path)
+        } else {
+            None
+        };
+        let actual_input_path = srgb_temp.as_deref().or(oriented_temp.as_deref()).unwrap_or(input_path);
 
         // Convert input path to string for tool commands
         let input_str = actual_input_path.to_str()
@@ -308,7 +354,7 @@ impl ImageProcessor {
 
         // Check for cancellation after directory creation
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Image optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("Image optimization".to_string()).into());
         }
 
         // Extract and normalize file extension for format detection
@@ -316,6 +362,25 @@ impl ImageProcessor {
             .and_then(|s| s.to_str())
             .map(|s| s.to_lowercase());
 
+        // jpegoptim keeps every tag: with keep-all there is nothing left to do
+        let encoder_keeps_metadata = matches!(ext.as_deref(), Some("jpg") | Some("jpeg"))
+            && !self.config.convert_to_webp
+            && actual_input_path == input_path;
+        let apply_policy = !(encoder_keeps_metadata && self.config.metadata_policy == MetadataPolicy::KeepAll);
+
+        // In-place mode overwrites the original, so keep a copy to read tags from
+        let metadata_copy = if apply_policy
+            && output_path == input_path
+            && self.config.metadata_policy.copies_from_source()
+        {
+            let temp_metadata_path = Self::create_temp_file(input_path)?;
+            tokio::fs::copy(input_path, &temp_metadata_path).await?;
+            Some(temp_metadata_path)
+        } else {
+            None
+        };
+        let metadata_source = metadata_copy.as_deref().unwrap_or(input_path);
+
         // Route to appropriate optimization method based on format
         let result = match ext.as_deref() {
             Some("jpg") | Some("jpeg") => {
@@ -342,15 +407,24 @@ impl ImageProcessor {
             }
         };
 
-        // Clean up temporary pre-resized file if we created one
-        if actual_input_path != input_path {
-            if let Err(e) = tokio::fs::remove_file(&actual_input_path).await {
-                warn!("Failed to cleanup temporary resized file {}: {}", actual_input_path.display(), e);
-            } else {
-                debug!("Cleaned up temporary pre-resized file: {}", actual_input_path.display());
+        let result = match result {
+            Ok(path) if apply_policy => {
+                MetadataWriter::apply(&self.config.metadata_policy, metadata_source, &path, orientation_baked)
+                    .await
+                    .map(|_| path)
             }
-        }
+            other => other,
+        };
 
+        // exiftool and the encoders leave the current time: restore the original's
+        let result = match result {
+            Ok(path) => FileManager::apply_file_attributes(&path, &source_metadata, self.config.preserve_ownership)
+                .await
+                .map(|_| path),
+            Err(e) => Err(e),
+        };
+
+        // The metadata copy and the pre-resized/oriented/sRGB files are removed on drop
         result
     }
 
@@ -361,7 +435,7 @@ impl ImageProcessor {
     /// **Returns error if jpegoptim is not available**
     async fn optimize_jpeg(&mut self, input: &str, output: &str) -> Result<PathBuf> {
         if self.should_stop() {
-            return Err(anyhow::anyhow!("JPEG optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("JPEG optimization".to_string()).into());
         }
 
         let platform = PlatformCommands::instance();
@@ -378,20 +452,21 @@ impl ImageProcessor {
 
         if self.run_tool_with_stdout_output("jpegoptim", &args, output).await? {
             debug!("JPEG optimized successfully with jpegoptim");
-            return Ok(PathBuf::from(output));
+            Ok(PathBuf::from(output))
         } else {
-            return Err(anyhow::anyhow!("jpegoptim failed to optimize: {}", input));
+            Err(anyhow::anyhow!("jpegoptim failed to optimize: {}", input))
         }
     }
 
     /// Optimizes PNG images using oxipng.
     /// 
     /// **Tool Used:** oxipng with level 6 optimization and metadata stripping
-    /// **Features:** Lossless compression, metadata stripping for privacy
+    /// **Features:** Lossless compression, metadata stripping for privacy.
+    /// `--strip safe` keeps colour chunks (iCCP, sRGB, cICP) so colours do not shift
     /// **Returns error if oxipng is not available**
     async fn optimize_png(&mut self, input: &str, output: &str) -> Result<PathBuf> {
         if self.should_stop() {
-            return Err(anyhow::anyhow!("PNG optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("PNG optimization".to_string()).into());
         }
 
         let platform = PlatformCommands::instance();
@@ -402,7 +477,7 @@ impl ImageProcessor {
         debug!("Optimizing PNG with oxipng");
         let args = to_string_vec([
             "-o", "6",
-            "--strip", "all",
+            "--strip", "safe",
             "--out", output,
             input,
         ]);
@@ -413,6 +488,7 @@ impl ImageProcessor {
         let start_time = std::time::Instant::now();
         let success = Command::new(&tool_path)
             .args(&args)
+            .kill_on_drop(true)
             .status()
             .await?
             .success();
@@ -430,10 +506,11 @@ impl ImageProcessor {
     /// 
     /// **Tool Used:** cwebp with quality control and multi-threading
     /// **Quality Setting:** Uses `config.webp_quality` (1-100)
+    /// **Colour:** `-metadata icc` keeps the embedded ICC profile
     /// **Returns error if cwebp is not available**
     async fn optimize_webp(&mut self, input: &str, output: &str) -> Result<PathBuf> {
         if self.should_stop() {
-            return Err(anyhow::anyhow!("WebP optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("WebP optimization".to_string()).into());
         }
 
         let platform = PlatformCommands::instance();
@@ -446,6 +523,7 @@ impl ImageProcessor {
             "-q", &self.config.webp_quality.to_string(),
             "-m", "4",
             "-mt",
+            "-metadata", "icc",
             input,
             "-o", output,
         ]);
@@ -456,6 +534,7 @@ impl ImageProcessor {
         let start_time = std::time::Instant::now();
         let success = Command::new(&tool_path)
             .args(&args)
+            .kill_on_drop(true)
             .status()
             .await?
             .success();
@@ -473,10 +552,11 @@ impl ImageProcessor {
     /// 
     /// **Tool Used:** cwebp with quality control and multi-threading
     /// **Quality Setting:** Uses `config.webp_quality` (1-100)
+    /// **Colour:** `-metadata icc` keeps the embedded ICC profile
     /// **Returns error if cwebp is not available**
     async fn convert_to_webp(&mut self, input: &str, output: &str) -> Result<PathBuf> {
         if self.should_stop() {
-            return Err(anyhow::anyhow!("WebP conversion cancelled by user"));
+            return Err(OptimizeError::Cancelled("WebP conversion".to_string()).into());
         }
 
         let platform = PlatformCommands::instance();
@@ -489,6 +569,7 @@ impl ImageProcessor {
             "-q", &self.config.webp_quality.to_string(),
             "-m", "4",
             "-mt",
+            "-metadata", "icc",
             input,
             "-o", output,
         ]);
@@ -499,6 +580,7 @@ impl ImageProcessor {
         let start_time = std::time::Instant::now();
         let success = Command::new(&tool_path)
             .args(&args)
+            .kill_on_drop(true)
             .status()
             .await?
             .success();
@@ -539,7 +621,7 @@ impl ImageProcessor {
     /// * `PathBuf` - Calculated output path for the optimized image
     /// 
     /// # Examples
-    /// ```rust
+    /// ```rust,ignore
     /// // Output directory mode with WebP conversion
     /// let input = Path::new("/src/photos/vacation/IMG_001.jpg");
     /// let base = Path::new("/src/photos");
@@ -671,7 +753,7 @@ impl ImageProcessor {
     /// * `bool` - `true` if cwebp is available, `false` otherwise
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// if ImageProcessor::check_webp_support().await {
     ///     // println!("WebP conversion is supported");
     /// } else {
@@ -695,7 +777,7 @@ impl ImageProcessor {
     /// * `(broadcast::Sender<()>, broadcast::Receiver<()>)` - Sender and receiver for cancellation signals
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let (stop_sender, stop_receiver) = ImageProcessor::create_cancellation_channel(1);
     /// let processor = ImageProcessor::new_with_cancellation(config, stop_receiver).await?;
     /// 
@@ -721,15 +803,27 @@ impl ImageProcessor {
         args: &[String],
         output_path: &str,
     ) -> Result<bool> {
+        let tool_path = PlatformCommands::instance().get_tool_path(tool_name)
+            .unwrap_or_else(|| PathBuf::from(tool_name));
+
         let start_time = std::time::Instant::now();
-        let output_data = Command::new(tool_name)
+        let mut child = Command::new(&tool_path)
             .args(args)
-            .output()
-            .await?;
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // L'immagine viene scritta man mano invece di essere tenuta in memoria
+        let mut stdout = child.stdout.take()
+            .ok_or_else(|| anyhow::anyhow!("{} stdout not captured", tool_name))?;
+        let mut file = tokio::fs::File::create(output_path).await?;
+        tokio::io::copy(&mut stdout, &mut file).await?;
+        file.flush().await?;
+        let status = child.wait().await?;
         let elapsed = start_time.elapsed();
 
-        if output_data.status.success() {
-            tokio::fs::write(output_path, output_data.stdout).await?;
+        if status.success() {
             debug!("{} completed successfully in {:?}", tool_name, elapsed);
             Ok(true)
         } else {
@@ -738,52 +832,51 @@ impl ImageProcessor {
         }
     }
 
-    /// Gets image dimensions using ImageMagick identify command.
+    /// Gets image dimensions by parsing the file header in-process.
+    /// 
+    /// Uses [`ImageProbe`] so no external process is spawned for the formats it
+    /// understands (JPEG, PNG, WebP, GIF, HEIF/AVIF). ImageMagick `identify` is
+    /// kept only as a fallback for anything the header parser rejects.
     /// 
     /// # Arguments
     /// * `image_path` - Path to the image file
     /// 
     /// # Returns
-    /// * `Result<(u32, u32)>` - Width and height in pixels, or error if detection failed
+    /// * `Result<(u32, u32)>` - Stored width and height in pixels (orientation not applied)
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let (width, height) = processor.get_image_dimensions(&image_path).await?;
     /// println!("Image is {}x{} pixels", width, height);
     /// ```
     pub async fn get_image_dimensions(&self, image_path: &Path) -> Result<(u32, u32)> {
-        let platform = PlatformCommands::instance();
-        
-        // Try ImageMagick 7.x first (magick identify)
-        if let Some(magick_path) = platform.get_tool_path("magick") {
-            if let Ok(output) = Command::new(magick_path)
-                .args(&["identify", "-format", "%w %h", &image_path.to_string_lossy()])
-                .output()
-                .await
-            {
-                if output.status.success() {
-                    let dimensions_str = String::from_utf8_lossy(&output.stdout);
-                    let parts: Vec<&str> = dimensions_str.trim().split_whitespace().collect();
-                    if parts.len() == 2 {
-                        if let (Ok(width), Ok(height)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
-                            debug!("Got dimensions {}x{} for {}", width, height, image_path.display());
-                            return Ok((width, height));
-                        }
-                    }
-                }
+        match ImageProbe::probe(image_path).await {
+            Ok(header) => {
+                debug!("Got dimensions {}x{} for {} (header probe)", header.width, header.height, image_path.display());
+                return Ok((header.width, header.height));
+            }
+            Err(e) => {
+                debug!("Header probe failed for {}: {}, falling back to ImageMagick", image_path.display(), e);
             }
         }
+
+        let platform = PlatformCommands::instance();
+        let image_str = image_path.to_string_lossy();
         
-        // Try ImageMagick 6.x (identify)
-        if let Some(identify_path) = platform.get_tool_path("identify") {
-            if let Ok(output) = Command::new(identify_path)
-                .args(&["-format", "%w %h", &image_path.to_string_lossy()])
-                .output()
-                .await
-            {
+        // Try ImageMagick 7.x first (magick identify), then 6.x (identify)
+        let candidates = [
+            ("magick", to_string_vec(["identify", "-format", "%w %h", &image_str])),
+            ("identify", to_string_vec(["-format", "%w %h", &image_str])),
+        ];
+
+        for (tool, args) in &candidates {
+            let Some(tool_path) = platform.get_tool_path(tool) else {
+                continue;
+            };
+            if let Ok(output) = Command::new(tool_path).args(args).kill_on_drop(true).output().await {
                 if output.status.success() {
                     let dimensions_str = String::from_utf8_lossy(&output.stdout);
-                    let parts: Vec<&str> = dimensions_str.trim().split_whitespace().collect();
+                    let parts: Vec<&str> = dimensions_str.split_whitespace().collect();
                     if parts.len() == 2 {
                         if let (Ok(width), Ok(height)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
                             debug!("Got dimensions {}x{} for {}", width, height, image_path.display());
@@ -795,7 +888,7 @@ impl ImageProcessor {
         }
         
         Err(anyhow::anyhow!(
-            "Unable to detect image dimensions for {}. ImageMagick tools (magick/identify) not available or failed.",
+            "Unable to detect image dimensions for {}. Header probe failed and ImageMagick tools (magick/identify) not available or failed.",
             image_path.display()
         ))
     }
@@ -863,11 +956,12 @@ impl ImageProcessor {
                         let resize_arg = format!("{}x{}>", MAX_2_5K_WIDTH, MAX_2_5K_HEIGHT); // > only shrinks, never enlarges
                         let args = to_string_vec([
                             &input_str,
+                            "-auto-orient", // Apply EXIF orientation before -strip drops the tag
                             "-resize", &resize_arg,
                             "-filter", "Lanczos", // Good quality and reasonable speed
                             "-colorspace", "sRGB", // Ensure consistent color space
                             "-quality", "95", // High quality for temp file (will be optimized later)
-                            "-strip", // Remove metadata to avoid issues
+                            "+profile", "!icc,*", // Remove metadata but keep the ICC colour profile
                             "-limit", "memory", "512MB", // Reduced memory
                             "-limit", "disk", "2GB", // Reduced disk
                             &output_str
@@ -882,6 +976,7 @@ impl ImageProcessor {
                         // Spawn process with timeout
                         let mut child = Command::new(&tool_path)
                             .args(&args)
+                            .kill_on_drop(true)
                             .spawn()?;
                         
                         info!("Process spawned, waiting for completion...");
@@ -911,6 +1006,7 @@ impl ImageProcessor {
                         debug!("Running pre-resize with vips: {:?}", args);
                         let status = Command::new(&tool_path)
                             .args(&args)
+                            .kill_on_drop(true)
                             .status()
                             .await?;
                         status.success()
@@ -933,28 +1029,243 @@ impl ImageProcessor {
         ))
     }
 
-    /// Creates a temporary path for storing pre-resized images.
+    /// Checks whether the encode step for this file will drop the EXIF
+    /// orientation tag while the pixels still rely on it.
+    /// 
+    /// jpegoptim keeps metadata, so the tag stays valid. oxipng
+    /// (`--strip safe`) and cwebp (`-metadata icc`) keep only the colour data
+    /// (ICC profile, sRGB/cICP chunks) and drop EXIF with the tag, so PNG
+    /// inputs and conversions to WebP are rotated first; WebP inputs get the
+    /// tag back from the original in the metadata policy step. Every metadata
+    /// policy that does not keep `Orientation` needs the rotation as well.
+    async fn needs_orientation_bake(&self, input_path: &Path) -> bool {
+        let ext = input_path.extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase());
+        let strips_metadata = !self.config.metadata_policy.keeps_orientation() || match ext.as_deref() {
+            Some("jpg") | Some("jpeg") => self.config.convert_to_webp,
+            Some("png") => true,
+            _ => false,
+        };
+        if !strips_metadata {
+            return false;
+        }
+
+        match ImageProbe::probe(input_path).await {
+            Ok(header) => header.needs_auto_orient(),
+            Err(e) => {
+                debug!("Could not read orientation for {}: {}", input_path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// Checks whether `config.color_profile` asks for sRGB and the image
+    /// carries a wide-gamut (non-sRGB) ICC profile.
+    async fn needs_srgb_conversion(&self, input_path: &Path) -> bool {
+        if self.config.color_profile != ColorProfilePolicy::ConvertToSrgb {
+            return false;
+        }
+
+        match ImageProbe::probe(input_path).await {
+            Ok(header) => header.has_non_srgb_profile(),
+            Err(e) => {
+                debug!("Could not read colour profile for {}: {}", input_path.display(), e);
+                false
+            }
+        }
+    }
+
+    /// Builds the arguments to convert an image from its embedded ICC profile
+    /// to sRGB with `tool_name`, tagging the result with the sRGB profile.
+    /// 
+    /// libvips ships a built-in `srgb` profile; ImageMagick needs a profile
+    /// file on disk (`srgb_profile`), so it is skipped when none is found.
+    fn srgb_conversion_args(tool_name: &str, input: &str, output: &str, srgb_profile: Option<&Path>) -> Option<Vec<String>> {
+        let is_jpeg = output.ends_with(".jpg") || output.ends_with(".jpeg");
+        if tool_name == "vips" {
+            let output = if is_jpeg { format!("{}[Q=95]", output) } else { output.to_string() };
+            Some(to_string_vec([
+                "icc_transform", input, &output, "srgb",
+                "--embedded",
+                "--intent", "perceptual",
+            ]))
+        } else {
+            let profile = srgb_profile?.to_string_lossy().to_string();
+            let mut args = to_string_vec([input, "-intent", "Perceptual", "-profile", &profile]);
+            if is_jpeg {
+                args.extend(to_string_vec(["-quality", "95"]));
+            }
+            args.push(output.to_string());
+            Some(args)
+        }
+    }
+
+    /// Converts an image with a wide-gamut ICC profile to sRGB, writing the
+    /// result (tagged with the sRGB profile) to `output_path`.
+    pub async fn convert_to_srgb(&self, input_path: &Path, output_path: &Path) -> Result<()> {
+        let platform = PlatformCommands::instance();
+        let input_str = input_path.to_string_lossy();
+        let output_str = output_path.to_string_lossy();
+        let srgb_profile = PlatformCommands::srgb_icc_profile();
+
+        for tool_name in ["vips", "magick", "convert"] {
+            if !platform.is_command_available(tool_name).await {
+                continue;
+            }
+            let Some(args) = Self::srgb_conversion_args(tool_name, &input_str, &output_str, srgb_profile.as_deref()) else {
+                continue;
+            };
+            let tool_path = platform.get_tool_path(tool_name)
+                .unwrap_or_else(|| PathBuf::from(tool_name));
+
+            debug!("Running sRGB conversion with {}: {:?}", tool_name, args);
+            if Command::new(&tool_path).args(&args).kill_on_drop(true).status().await?.success() {
+                return Ok(());
+            }
+            warn!("{} failed to convert {} to sRGB", tool_name, input_path.display());
+        }
+
+        Err(anyhow::anyhow!(
+            "Unable to convert {} to sRGB. Install libvips, or ImageMagick with an sRGB profile (SRGB_ICC_PROFILE).",
+            input_path.display()
+        ))
+    }
+
+    /// Physically rotates/flips an image according to its EXIF orientation
+    /// and resets the tag, writing the result to `output_path`.
+    /// 
+    /// Uses `magick`/`convert -auto-orient` or `vips autorot`. JPEG output is
+    /// written at quality 95 because it is re-encoded by the optimizer afterwards.
+    pub async fn auto_orient(&self, input_path: &Path, output_path: &Path) -> Result<()> {
+        let platform = PlatformCommands::instance();
+        let input_str = input_path.to_string_lossy();
+        let output_str = output_path.to_string_lossy();
+
+        for tool_name in ["magick", "convert", "vips"] {
+            if !platform.is_command_available(tool_name).await {
+                continue;
+            }
+            let tool_path = platform.get_tool_path(tool_name)
+                .unwrap_or_else(|| PathBuf::from(tool_name));
+
+            let args = if tool_name == "vips" {
+                to_string_vec(["autorot", &input_str, &output_str])
+            } else {
+                to_string_vec([&input_str, "-auto-orient", "-quality", "95", &output_str])
+            };
+
+            debug!("Running auto-orient with {}: {:?}", tool_name, args);
+            if Command::new(&tool_path).args(&args).kill_on_drop(true).status().await?.success() {
+                return Ok(());
+            }
+            warn!("{} auto-orient failed, trying next tool", tool_name);
+        }
+
+        Err(anyhow::anyhow!(
+            "Unable to apply EXIF orientation to {}. No working tools (magick/convert/vips) found.",
+            input_path.display()
+        ))
+    }
+
+    /// Creates a unique temporary file for an intermediate copy of an image
+    /// (pre-resized, auto-oriented, sRGB or the in-place metadata source).
+    /// 
+    /// The name is random, so concurrent workers handling same-named files
+    /// from different folders never share an intermediate. The original
+    /// extension is kept because the tools pick the format from it.
     /// 
     /// # Arguments
     /// * `original_path` - Path to the original image
     /// 
     /// # Returns
-    /// * `Result<PathBuf>` - Path for the temporary resized image
-    fn create_temp_resized_path(&self, original_path: &Path) -> Result<PathBuf> {
-        let file_stem = original_path.file_stem()
-            .ok_or_else(|| anyhow::anyhow!("Invalid file stem: {}", original_path.display()))?;
+    /// * `Result<TempPath>` - Closed temporary file, removed when dropped
+    fn create_temp_file(original_path: &Path) -> Result<TempPath> {
         let extension = original_path.extension()
             .and_then(|s| s.to_str())
             .unwrap_or("tmp");
-        
-        // Create temp path in system temp directory
-        let temp_dir = std::env::temp_dir();
-        let temp_filename = format!("{}_2_5k_temp.{}", file_stem.to_string_lossy(), extension);
-        let temp_path = temp_dir.join(temp_filename);
-        
-        debug!("Created temp path for pre-resize: {} -> {}", 
-               original_path.display(), temp_path.display());
-        
+
+        let temp_path = NamedTempFile::with_suffix(format!(".{}", extension))?.into_temp_path();
+        debug!("Created temp file for {}: {}", original_path.display(), temp_path.display());
         Ok(temp_path)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_probe::fixtures::{jpeg_with_icc, jpeg_with_orientation};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_orientation_bake_only_when_metadata_is_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+
+        for orientation in 1..=8u16 {
+            let path = temp_dir.path().join(format!("orientation_{}.jpg", orientation));
+            std::fs::write(&path, jpeg_with_orientation(48, 24, orientation)).unwrap();
+
+            // jpegoptim preserva l'EXIF: il tag resta valido
+            let processor = ImageProcessor::new(Config::default()).await.unwrap();
+            assert!(!processor.needs_orientation_bake(&path).await);
+
+            // cwebp scarta i metadata: servono pixel già ruotati
+            let config = Config { convert_to_webp: true, ..Default::default() };
+            let processor = ImageProcessor::new(config).await.unwrap();
+            assert_eq!(processor.needs_orientation_bake(&path).await, orientation > 1);
+
+            // Le policy che non mantengono Orientation richiedono la rotazione anche con jpegoptim
+            let config = Config { metadata_policy: MetadataPolicy::StripAll, ..Default::default() };
+            let processor = ImageProcessor::new(config).await.unwrap();
+            assert_eq!(processor.needs_orientation_bake(&path).await, orientation > 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_srgb_conversion_only_for_wide_gamut() {
+        let temp_dir = TempDir::new().unwrap();
+        let p3 = temp_dir.path().join("display_p3.jpg");
+        let srgb = temp_dir.path().join("srgb.jpg");
+        let untagged = temp_dir.path().join("untagged.jpg");
+        std::fs::write(&p3, jpeg_with_icc(32, 32, "Display P3")).unwrap();
+        std::fs::write(&srgb, jpeg_with_icc(32, 32, "sRGB IEC61966-2.1")).unwrap();
+        std::fs::write(&untagged, jpeg_with_orientation(32, 32, 1)).unwrap();
+
+        // Default: il profilo viene preservato, nessuna conversione
+        let processor = ImageProcessor::new(Config::default()).await.unwrap();
+        assert!(!processor.needs_srgb_conversion(&p3).await);
+
+        let config = Config { color_profile: ColorProfilePolicy::ConvertToSrgb, ..Default::default() };
+        let processor = ImageProcessor::new(config).await.unwrap();
+        assert!(processor.needs_srgb_conversion(&p3).await);
+        assert!(!processor.needs_srgb_conversion(&srgb).await);
+        assert!(!processor.needs_srgb_conversion(&untagged).await);
+    }
+
+    #[test]
+    fn test_srgb_conversion_args() {
+        let vips = ImageProcessor::srgb_conversion_args("vips", "in.jpg", "out.jpg", None).unwrap();
+        assert_eq!(&vips[..4], ["icc_transform", "in.jpg", "out.jpg[Q=95]", "srgb"]);
+        assert!(vips.contains(&"--embedded".to_string()));
+
+        // ImageMagick non ha un profilo sRGB integrato
+        assert!(ImageProcessor::srgb_conversion_args("magick", "in.png", "out.png", None).is_none());
+        let profile = PathBuf::from("/usr/share/color/icc/sRGB.icc");
+        let magick = ImageProcessor::srgb_conversion_args("magick", "in.png", "out.png", Some(&profile)).unwrap();
+        assert_eq!(magick, ["in.png", "-intent", "Perceptual", "-profile", "/usr/share/color/icc/sRGB.icc", "out.png"]);
+    }
+
+    #[test]
+    fn test_temp_files_are_unique_per_worker() {
+        // Same-named files from different folders must not share intermediates
+        let first = ImageProcessor::create_temp_file(Path::new("/a/IMG_0001.jpg")).unwrap();
+        let second = ImageProcessor::create_temp_file(Path::new("/b/IMG_0001.jpg")).unwrap();
+        assert_ne!(first.to_path_buf(), second.to_path_buf());
+        assert_eq!(first.extension().and_then(|e| e.to_str()), Some("jpg"));
+
+        let path = first.to_path_buf();
+        assert!(path.exists());
+        drop(first);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,221 @@
+//! # Run Journal Module
+//!
+//! Journal dei file completati, per riprendere una run interrotta.
+//!
+//! ## Responsabilità:
+//! - Registra l'avvio di una run (directory di output)
+//! - Aggiunge una riga per ogni file completato (append, O(1) per file)
+//! - Alla run successiva restituisce i file già completati, che la
+//!   scansione salta
+//! - Viene rimosso quando una run termina senza file rimasti
+//!
+//! ## Formato (JSON lines):
+//! La prima riga è l'intestazione, le successive sono i path completati. Una
+//! riga troncata da un kill a metà scrittura viene ignorata.
+//! ```text
+//! {"version":2,"output_dir":"/out"}
+//! "/media/a.jpg"
+//! ```
+//!
+//! Il journal non contiene la lista dei file da elaborare: la scansione
+//! procede in streaming insieme all'elaborazione (anche alla ripresa), quindi
+//! la sua dimensione cresce solo con i file completati.
+//!
+//! ## Ripresa e memoria:
+//! Riprendere significa rifare la scansione saltando i file completati. Per
+//! farlo l'insieme dei path completati resta in memoria per tutta la run
+//! (circa 100-150 byte per path, quindi ~150 MB per un milione di file già
+//! completati); la memoria cresce solo alla ripresa, mai durante una run
+//! nuova.
+//!
+//! ## Robustezza:
+//! L'intestazione è scritta in modo atomico (file temporaneo + rename) e i
+//! completamenti sono righe singole: anche dopo un SIGKILL il journal resta
+//! leggibile e la ripresa salta i file già completati.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::state::{state_file_path, write_atomic};
+
+const JOURNAL_VERSION: u32 = 2;
+
+/// Intestazione del journal
+#[derive(Debug, Serialize, Deserialize)]
+struct JournalHeader {
+    version: u32,
+    output_dir: Option<PathBuf>,
+}
+
+/// Journal dei file completati per una directory media
+pub struct RunJournal {
+    path: PathBuf,
+    output_dir: Option<PathBuf>,
+    writer: Mutex<Option<fs::File>>,
+}
+
+impl RunJournal {
+    /// Journal per `media_dir`, accanto allo state file in `~/.media-optimizer`.
+    ///
+    /// `output_dir` fa parte dell'identità della run: un journal scritto per
+    /// un'altra directory di output non viene ripreso.
+    pub async fn new(media_dir: &Path, output_dir: Option<&Path>) -> Result<Self> {
+        Ok(Self::at(state_file_path(media_dir, "journal").await?, output_dir))
+    }
+
+    /// Journal in un path esplicito
+    pub fn at(path: PathBuf, output_dir: Option<&Path>) -> Self {
+        Self {
+            path,
+            output_dir: output_dir.map(Path::to_path_buf),
+            writer: Mutex::new(None),
+        }
+    }
+
+    /// File completati da una run interrotta, se il journal esiste ed è compatibile
+    pub async fn completed_files(&self) -> Result<Option<HashSet<PathBuf>>> {
+        let content = match fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self::parse(&content, self.output_dir.as_deref()))
+    }
+
+    /// Estrae i file completati dal contenuto del journal
+    fn parse(content: &str, output_dir: Option<&Path>) -> Option<HashSet<PathBuf>> {
+        let mut lines = content.lines();
+        let header: JournalHeader = serde_json::from_str(lines.next()?).ok()?;
+        if header.version != JOURNAL_VERSION || header.output_dir.as_deref() != output_dir {
+            return None;
+        }
+
+        Some(lines.filter_map(|line| serde_json::from_str::<PathBuf>(line).ok()).collect())
+    }
+
+    /// Inizia una run e apre il journal in append.
+    ///
+    /// Con `resume` i completamenti della run interrotta restano, altrimenti
+    /// il journal riparte da un'intestazione nuova.
+    pub async fn start(&self, resume: bool) -> Result<()> {
+        if !resume {
+            let header = JournalHeader {
+                version: JOURNAL_VERSION,
+                output_dir: self.output_dir.clone(),
+            };
+            write_atomic(&self.path, format!("{}\n", serde_json::to_string(&header)?).as_bytes()).await?;
+        }
+
+        let mut file = fs::OpenOptions::new().read(true).append(true).open(&self.path).await?;
+        // Una riga troncata da un kill va chiusa, o assorbirebbe la successiva:
+        // basta leggere l'ultimo byte, non tutto il journal
+        let mut last = [b'\n'];
+        if file.metadata().await?.len() > 0 {
+            file.seek(SeekFrom::End(-1)).await?;
+            file.read_exact(&mut last).await?;
+        }
+        if last[0] != b'\n' {
+            file.write_all(b"\n").await?;
+            file.flush().await?;
+        }
+        *self.writer.lock().await = Some(file);
+        Ok(())
+    }
+
+    /// Registra un file come completato (ottimizzato, saltato o fallito)
+    pub async fn mark_done(&self, file_path: &Path) {
+        let mut writer = self.writer.lock().await;
+        let Some(file) = writer.as_mut() else {
+            return;
+        };
+        let line = match serde_json::to_string(file_path) {
+            Ok(line) => line + "\n",
+            Err(e) => {
+                warn!("Failed to journal {}: {}", file_path.display(), e);
+                return;
+            }
+        };
+        // tokio::fs::File scrive in background: senza flush la riga può andare persa
+        let written = match file.write_all(line.as_bytes()).await {
+            Ok(()) => file.flush().await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            warn!("Failed to journal {}: {}", file_path.display(), e);
+        }
+    }
+
+    /// Chiude la run: rimuove il journal se non restano file, altrimenti lo
+    /// lascia per la ripresa
+    pub async fn finish(&self, complete: bool) -> Result<()> {
+        if let Some(mut file) = self.writer.lock().await.take() {
+            file.flush().await?;
+        }
+        if complete {
+            match fs::remove_file(&self.path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_journal_resume() {
+        let temp_dir = TempDir::new().unwrap();
+        let files: Vec<PathBuf> = ["a.jpg", "b.mp4", "c.png"]
+            .iter()
+            .map(|name| temp_dir.path().join(name))
+            .collect();
+        let journal_path = temp_dir.path().join("journal.json");
+        let output = temp_dir.path().join("out");
+
+        let journal = RunJournal::at(journal_path.clone(), Some(&output));
+        assert_eq!(journal.completed_files().await.unwrap(), None);
+
+        // Run interrotta dopo il primo file, senza finish (es. SIGKILL)
+        journal.start(false).await.unwrap();
+        journal.mark_done(&files[0]).await;
+        drop(journal);
+        // Riga troncata a metà scrittura
+        let mut content = std::fs::read_to_string(&journal_path).unwrap();
+        content.push_str("\"/media/tru");
+        std::fs::write(&journal_path, content).unwrap();
+
+        let journal = RunJournal::at(journal_path.clone(), Some(&output));
+        let completed = journal.completed_files().await.unwrap().unwrap();
+        assert_eq!(completed, HashSet::from([files[0].clone()]));
+
+        // Un'altra directory di output non riprende questo journal
+        let other = RunJournal::at(journal_path.clone(), None);
+        assert_eq!(other.completed_files().await.unwrap(), None);
+
+        // La ripresa aggiunge ai completamenti esistenti, dopo la riga troncata
+        journal.start(true).await.unwrap();
+        journal.mark_done(&files[1]).await;
+        journal.finish(false).await.unwrap();
+        let completed = journal.completed_files().await.unwrap().unwrap();
+        assert_eq!(completed, HashSet::from([files[0].clone(), files[1].clone()]));
+
+        // Una run nuova riparte da zero
+        journal.start(false).await.unwrap();
+        assert_eq!(journal.completed_files().await.unwrap(), Some(HashSet::new()));
+
+        journal.finish(true).await.unwrap();
+        assert!(!journal_path.exists());
+    }
+}
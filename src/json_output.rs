@@ -12,12 +12,22 @@
 //! - `progress`: Progresso corrente (file processato, stats)
 //! - `file_start`: Inizio elaborazione di un file
 //! - `file_complete`: Fine elaborazione di un file
+//! - `cancelled`: Elaborazione di un file interrotta da uno stop
+//! - `file_progress`: Avanzamento di un file in elaborazione (encoding video)
+//! - `video_analysis`: Decisione della pre-analisi video (encode, remux, skip)
+//! - `video_trim`: Tratti neri/silenziosi tagliati da un video, con i punti di taglio
+//! - `timeout`: File oltre il suo timeout, con la policy applicata
 //! - `complete`: Fine processo completo con statistiche finali
 //! - `error`: Errore durante elaborazione
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
+use crate::config::TimeoutPolicy;
+use crate::progress::{FileProgress, MediaKindStats, OptimizationStats};
 use crate::state::ProcessedFile;
+use crate::video_processor::{VideoAction, VideoDecision, VideoInfo};
+use crate::video_trim::TrimPoints;
 
 /// Tipo di messaggio JSON
 #[derive(Debug, Serialize, Deserialize)]
@@ -28,7 +38,11 @@ pub enum JsonMessage {
     Start {
         input_dir: PathBuf,
         output_dir: Option<PathBuf>,
+        /// File noti all'avvio: la scansione procede insieme all'elaborazione,
+        /// il totale cresce nei messaggi `progress` (vedi `scan_complete`)
         total_files: usize,
+        /// Ripresa di una run interrotta: i file già completati vengono saltati
+        resumed: bool,
         config: JsonConfig,
     },
     
@@ -36,8 +50,11 @@ pub enum JsonMessage {
     #[serde(rename = "progress")]
     Progress {
         current: usize,
+        /// File scoperti finora
         total: usize,
         percentage: f64,
+        /// La scansione è terminata: `total` è definitivo
+        scan_complete: bool,
         files_optimized: usize,
         files_skipped: usize,
         errors: usize,
@@ -64,6 +81,59 @@ pub enum JsonMessage {
         error: Option<String>,
     },
     
+    /// Elaborazione di un file interrotta da uno stop (processi terminati,
+    /// temporanei e output parziali rimossi)
+    #[serde(rename = "cancelled")]
+    Cancelled {
+        path: PathBuf,
+    },
+    
+    /// Avanzamento di un file in elaborazione
+    #[serde(rename = "file_progress")]
+    FileProgress {
+        path: PathBuf,
+        percent: Option<f64>,
+        processed_seconds: f64,
+        duration_seconds: Option<f64>,
+        speed: Option<f64>,
+        eta_seconds: Option<f64>,
+    },
+    
+    /// Decisione della pre-analisi di un video
+    #[serde(rename = "video_analysis")]
+    VideoAnalysis {
+        path: PathBuf,
+        action: VideoAction,
+        reason: String,
+        codec: Option<String>,
+        width: u32,
+        height: u32,
+        bitrate: u64,
+        bits_per_pixel: f64,
+        estimated_size: u64,
+    },
+    
+    /// Tratti morti tagliati da un video (`video_trim`): parte mantenuta,
+    /// in secondi dalla sorgente
+    #[serde(rename = "video_trim")]
+    VideoTrim {
+        path: PathBuf,
+        start: f64,
+        end: f64,
+        source_duration: f64,
+        removed_seconds: f64,
+    },
+    
+    /// File oltre il timeout stimato dal contenuto. Con `retry-faster`
+    /// arriva un secondo messaggio se scade anche il nuovo tentativo
+    #[serde(rename = "timeout")]
+    Timeout {
+        path: PathBuf,
+        timeout_seconds: f64,
+        /// Policy applicata (`retry-faster` ripiega su `copy-original`)
+        policy: TimeoutPolicy,
+    },
+    
     /// Processo completato
     #[serde(rename = "complete")]
     Complete {
@@ -71,7 +141,19 @@ pub enum JsonMessage {
         files_optimized: usize,
         files_skipped: usize,
         errors: usize,
+        /// File interrotti da uno stop
+        files_cancelled: usize,
+        /// File scoperti ma non avviati per uno stop, ripresi alla run successiva
+        files_remaining: usize,
+        /// Lo stop ha fermato la scansione: restano anche file non ancora scoperti
+        scan_interrupted: bool,
+        /// File che hanno superato il timeout (anche se ritentati con successo)
+        timeouts: usize,
         total_bytes_saved: u64,
+        /// Risultati per tipo di media
+        images: MediaKindStats,
+        videos: MediaKindStats,
+        audio: MediaKindStats,
         average_reduction: f64,
         duration_seconds: f64,
         historical_stats: HistoricalStats,
@@ -90,7 +172,24 @@ pub enum JsonMessage {
 pub struct JsonConfig {
     pub jpeg_quality: u8,
     pub video_crf: u8,
+    pub video_codec: crate::config::VideoCodec,
+    pub video_preset: Option<crate::config::VideoPreset>,
+    pub video_target_bitrate: Option<String>,
+    pub video_target_size: Option<String>,
+    pub hdr_policy: crate::config::HdrPolicy,
+    pub stream_policy: crate::config::StreamPolicy,
+    pub video_trim: Option<crate::config::TrimMode>,
+    pub audio_codec: crate::config::AudioCodec,
+    pub audio_bitrate: String,
+    pub audio_channels: Option<u32>,
+    pub loudness_target: Option<f64>,
+    pub audio_format: crate::config::AudioFormat,
+    pub streaming: Option<crate::config::StreamingFormat>,
     pub workers: usize,
+    pub max_threads: Option<usize>,
+    pub max_memory: Option<String>,
+    pub timeout_policy: TimeoutPolicy,
+    pub timeout_multiplier: f64,
     pub convert_to_webp: bool,
     pub webp_quality: u8,
     pub dry_run: bool,
@@ -117,12 +216,14 @@ impl JsonMessage {
         input_dir: PathBuf,
         output_dir: Option<PathBuf>,
         total_files: usize,
+        resumed: bool,
         config: JsonConfig,
     ) -> Self {
         Self::Start {
             input_dir,
             output_dir,
             total_files,
+            resumed,
             config,
         }
     }
@@ -131,6 +232,7 @@ impl JsonMessage {
     pub fn progress(
         current: usize,
         total: usize,
+        scan_complete: bool,
         files_optimized: usize,
         files_skipped: usize,
         errors: usize,
@@ -146,6 +248,7 @@ impl JsonMessage {
             current,
             total,
             percentage,
+            scan_complete,
             files_optimized,
             files_skipped,
             errors,
@@ -175,24 +278,78 @@ impl JsonMessage {
         }
     }
     
-    /// Crea un messaggio di completamento generale
+    /// Crea un messaggio di file cancellato
+    pub fn cancelled(path: PathBuf) -> Self {
+        Self::Cancelled { path }
+    }
+    
+    /// Crea un messaggio di avanzamento file
+    pub fn file_progress(progress: &FileProgress) -> Self {
+        Self::FileProgress {
+            path: progress.path.clone(),
+            percent: progress.percent,
+            processed_seconds: progress.processed_seconds,
+            duration_seconds: progress.duration_seconds,
+            speed: progress.speed,
+            eta_seconds: progress.eta_seconds,
+        }
+    }
+    
+    /// Crea un messaggio con la decisione della pre-analisi video
+    pub fn video_analysis(path: PathBuf, info: Option<&VideoInfo>, decision: &VideoDecision) -> Self {
+        Self::VideoAnalysis {
+            path,
+            action: decision.action,
+            reason: decision.reason.clone(),
+            codec: info.map(|i| i.codec.clone()),
+            width: info.map_or(0, |i| i.width),
+            height: info.map_or(0, |i| i.height),
+            bitrate: info.map_or(0, |i| i.bitrate),
+            bits_per_pixel: info.map_or(0.0, |i| i.bits_per_pixel()),
+            estimated_size: decision.estimated_size,
+        }
+    }
+    
+    /// Crea un messaggio con i punti di taglio di un video
+    pub fn video_trim(path: PathBuf, trim: &TrimPoints) -> Self {
+        Self::VideoTrim {
+            path,
+            start: trim.start,
+            end: trim.end,
+            source_duration: trim.source_duration,
+            removed_seconds: trim.removed(),
+        }
+    }
+    
+    /// Crea un messaggio di timeout con la policy applicata
+    pub fn timeout(path: PathBuf, timeout: Duration, policy: TimeoutPolicy) -> Self {
+        Self::Timeout {
+            path,
+            timeout_seconds: timeout.as_secs_f64(),
+            policy,
+        }
+    }
+    
+    /// Crea un messaggio di completamento generale dalle statistiche della run
     pub fn complete(
-        files_processed: usize,
-        files_optimized: usize,
-        files_skipped: usize,
-        errors: usize,
-        total_bytes_saved: u64,
-        average_reduction: f64,
+        stats: &OptimizationStats,
         duration_seconds: f64,
         historical_stats: HistoricalStats,
     ) -> Self {
         Self::Complete {
-            files_processed,
-            files_optimized,
-            files_skipped,
-            errors,
-            total_bytes_saved,
-            average_reduction,
+            files_processed: stats.files_processed,
+            files_optimized: stats.files_optimized,
+            files_skipped: stats.files_skipped,
+            errors: stats.errors,
+            files_cancelled: stats.files_cancelled,
+            files_remaining: stats.files_remaining,
+            scan_interrupted: stats.scan_interrupted,
+            timeouts: stats.timeouts,
+            total_bytes_saved: stats.total_bytes_saved,
+            images: stats.images,
+            videos: stats.videos,
+            audio: stats.audio,
+            average_reduction: stats.overall_reduction_percent(),
             duration_seconds,
             historical_stats,
         }
@@ -210,7 +367,24 @@ impl From<&crate::Config> for JsonConfig {
         Self {
             jpeg_quality: config.jpeg_quality,
             video_crf: config.video_crf,
+            video_codec: config.video_codec,
+            video_preset: config.video_preset,
+            video_target_bitrate: config.video_target_bitrate.clone(),
+            video_target_size: config.video_target_size.clone(),
+            hdr_policy: config.hdr_policy,
+            stream_policy: config.stream_policy,
+            video_trim: config.video_trim,
+            audio_codec: config.audio_codec,
+            audio_bitrate: config.audio_bitrate.clone(),
+            audio_channels: config.audio_channels,
+            loudness_target: config.loudness_target,
+            audio_format: config.audio_format,
+            streaming: config.streaming,
             workers: config.workers,
+            max_threads: config.max_threads,
+            max_memory: config.max_memory.clone(),
+            timeout_policy: config.timeout_policy,
+            timeout_multiplier: config.timeout_multiplier,
             convert_to_webp: config.convert_to_webp,
             webp_quality: config.webp_quality,
             dry_run: config.dry_run,
@@ -11,14 +11,26 @@
 //! - `config`: Gestione configurazione e validazione parametri
 //! - `error`: Tipi di errore custom per diverse operazioni
 //! - `state`: Tracking file processati e persistenza stato
+//! - `journal`: File completati di una run interrotta, per la ripresa
 //! - `file_manager`: Operazioni sui file e discovery media
 //! - `image_processor`: Ottimizzazione immagini (JPEG/PNG/WebP)
+//! - `image_probe`: Lettura header immagini (dimensioni, orientamento) senza decodifica
+//! - `metadata`: Applicazione della policy sui metadati delle immagini (exiftool)
 //! - `video_processor`: Ottimizzazione video (MP4/MOV/AVI)
+//! - `audio_processor`: Ottimizzazione file audio (WAV/AIFF -> FLAC, Opus/AAC)
+//! - `streaming`: Ladder adattiva HLS/DASH per i video lunghi
+//! - `storyboard`: Sprite di frame con indice WebVTT per lo scrubbing dei video
+//! - `video_thumbnail`: Poster e clip di anteprima dei video nei thumbnails
+//! - `video_color`: Spazio colore, HDR e bit depth dei video (preserve o tone mapping)
+//! - `video_streams`: Tracce audio, sottotitoli e capitoli portati nei video ottimizzati
+//! - `video_trim`: Rilevamento e taglio dei tratti neri o silenziosi a inizio e fine video
+//! - `video_codec`: Mappatura codec -> encoder ffmpeg, container e argomenti
+//! - `ffmpeg`: Esecuzione di ffmpeg con avanzamento in streaming
 //! - `optimizer`: Orchestratore principale del processo
 //! - `progress`: Progress tracking e statistiche
 //! 
 //! ## Utilizzo:
-//! ```rust
+//! ```rust,ignore
 //! use space_media_optimizer::{Config, MediaOptimizer};
 //! 
 //! let config = Config::default();
@@ -29,10 +41,22 @@
 pub mod config;
 pub mod error;
 pub mod state;
+pub mod journal;
 pub mod optimizer;
 pub mod image_processor;
+pub mod image_probe;
+pub mod metadata;
 pub mod video_processor;
+pub mod video_codec;
+pub mod video_color;
+pub mod video_streams;
+pub mod video_trim;
+pub mod audio_processor;
+pub mod ffmpeg;
 pub mod resize;
+pub mod video_thumbnail;
+pub mod storyboard;
+pub mod streaming;
 pub mod file_manager;
 pub mod platform;
 pub mod progress;
@@ -40,10 +64,12 @@ pub mod json_output;
 pub mod utils;
 pub mod tool_resolver;
 
-pub use config::{Config, ThumbnailSize};
+pub use config::{AudioCodec, AudioFormat, ColorProfilePolicy, Config, HdrPolicy, MetadataPolicy, StoryboardProfile, StreamPolicy, StreamingFormat, StreamingRendition, ThumbnailSize, TimeoutPolicy, TrimMode, VideoCodec, VideoPreset, VideoTune};
 pub use error::OptimizeError;
+pub use file_manager::MediaKind;
 pub use state::{StateFile, ProcessedFile};
 pub use optimizer::MediaOptimizer;
 pub use json_output::{JsonMessage, JsonConfig, HistoricalStats};
+pub use image_probe::{ImageFormat, ImageHeader, ImageProbe};
 pub use resize::{ImageResizer, ResizeAlgorithm, ResizeMode};
 pub use tool_resolver::ToolPathResolver;
@@ -15,6 +15,14 @@
 //! 4. Crea un oggetto Config con tutti i parametri
 //! 5. Istanzia MediaOptimizer e avvia il processo di ottimizzazione
 //! 
+//! ## Segnali:
+//! - Primo Ctrl-C (SIGINT): nessun nuovo file, quelli in corso terminano
+//! - Secondo Ctrl-C o SIGTERM: anche i file in corso vengono cancellati
+//! - Terzo Ctrl-C: uscita immediata
+//! 
+//! In tutti i casi lo stato resta consistente e i file non completati
+//! vengono ripresi dalla run successiva. Una run interrotta esce con codice 130.
+//! 
 //! ## Esempio di utilizzo:
 //! ```bash
 //! media-optimizer /path/to/media --quality 85 --workers 8 --verbose
@@ -24,13 +32,16 @@ use anyhow::Result;
 use clap::Parser;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use tracing::info;
+use tracing::{info, warn};
 
 use space_media_optimizer::{
-    config::{Config, ThumbnailSize},
-    optimizer::media_optimizer::MediaOptimizer,
+    config::{AudioCodec, AudioFormat, ColorProfilePolicy, Config, HdrPolicy, MetadataPolicy, StoryboardProfile, StreamPolicy, StreamingFormat, StreamingRendition, ThumbnailSize, TimeoutPolicy, TrimMode, VideoCodec, VideoPreset, VideoTune},
+    optimizer::media_optimizer::{MediaOptimizer, StopHandle},
 };
 
+/// Exit code of a run interrupted by a signal (128 + SIGINT)
+const EXIT_INTERRUPTED: i32 = 130;
+
 /// Parser for thumbnail configuration from JSON string
 fn parse_thumbnails(s: &str) -> Result<HashMap<String, ThumbnailSize>, String> {
     let parsed: Result<HashMap<String, [u32; 2]>, _> = serde_json::from_str(s);
@@ -49,6 +60,16 @@ fn parse_thumbnails(s: &str) -> Result<HashMap<String, ThumbnailSize>, String> {
     }
 }
 
+/// Parser for storyboard configuration from JSON string
+fn parse_storyboards(s: &str) -> Result<HashMap<String, StoryboardProfile>, String> {
+    let parsed: HashMap<String, [u32; 3]> = serde_json::from_str(s)
+        .map_err(|e| format!("Invalid JSON format (expected {{\"name\": [frames, columns, tile_width]}}): {}", e))?;
+    Ok(parsed
+        .into_iter()
+        .map(|(name, [frames, columns, tile_width])| (name, StoryboardProfile::new(frames, columns, tile_width)))
+        .collect())
+}
+
 #[derive(Parser)]
 #[command(name = "media-optimizer")]
 #[command(about = "Optimize images and videos with smart deduplication")]
@@ -64,10 +85,94 @@ struct Args {
     #[arg(short, long, default_value = "26")]
     crf: u8,
     
-    /// Video audio bitrate
+    /// Video codec: h264, hevc, av1 or vp9 (vp9 is written as WebM)
+    #[arg(long, default_value = "h264")]
+    video_codec: VideoCodec,
+    
+    /// Video encoder speed (ultrafast ... veryslow); default: the encoder's own default
+    #[arg(long)]
+    preset: Option<VideoPreset>,
+    
+    /// Tune the video encoder for film, animation or grain
+    #[arg(long)]
+    tune: Option<VideoTune>,
+    
+    /// Video profile passed to the encoder (e.g. high, main10)
+    #[arg(long)]
+    video_profile: Option<String>,
+    
+    /// Video level (e.g. 4.1)
+    #[arg(long)]
+    video_level: Option<String>,
+    
+    /// Encode videos in two passes at this average bitrate instead of CRF (e.g. 2500k)
+    #[arg(long, conflicts_with = "target_size")]
+    target_bitrate: Option<String>,
+    
+    /// Encode videos in two passes to about this file size (e.g. 700M, 1.5G)
+    #[arg(long)]
+    target_size: Option<String>,
+    
+    /// Maximum keyframe interval in frames (e.g. 250)
+    #[arg(long)]
+    keyframe_interval: Option<u32>,
+    
+    /// HDR videos: preserve (10-bit, HDR10/HLG signaling) or tone-map to SDR
+    #[arg(long, default_value = "preserve")]
+    hdr: HdrPolicy,
+    
+    /// Streams kept in re-encoded videos: all, first-video (all audio and subtitles) or no-subtitles
+    #[arg(long, default_value = "first-video")]
+    streams: StreamPolicy,
+    
+    /// Trim leading/trailing dead footage from videos: black, silence or any (both detectors)
+    #[arg(long)]
+    trim: Option<TrimMode>,
+    
+    /// Downscale videos so the long edge is at most this many pixels (e.g. 1920)
+    #[arg(long)]
+    max_video_size: Option<u32>,
+    
+    /// Cap the video frame rate (e.g. 30)
+    #[arg(long)]
+    max_fps: Option<u32>,
+    
+    /// Only change the video container (e.g. MOV/MKV -> MP4) without re-encoding
+    #[arg(long)]
+    remux: bool,
+    
+    /// Audio bitrate (video soundtracks and lossy audio files)
     #[arg(short, long, default_value = "128k")]
     audio_bitrate: String,
     
+    /// Package videos longer than --streaming-min-duration for adaptive streaming: hls or dash
+    #[arg(long)]
+    streaming: Option<StreamingFormat>,
+    
+    /// Streaming renditions as height:bitrate, highest first [default: 1080:5000k,720:2800k,480:1400k,360:800k]
+    #[arg(long, value_delimiter = ',')]
+    streaming_ladder: Option<Vec<StreamingRendition>>,
+    
+    /// Minimum video duration in seconds for streaming output
+    #[arg(long, default_value = "120")]
+    streaming_min_duration: f64,
+    
+    /// Video audio codec: auto (AAC in MP4, Opus in WebM), aac, opus, copy or none
+    #[arg(long, default_value = "auto")]
+    audio_codec: AudioCodec,
+    
+    /// Downmix audio with more channels than this (2 = stereo)
+    #[arg(long)]
+    audio_channels: Option<u32>,
+    
+    /// Normalize audio loudness (EBU R128) to this level in LUFS [default: -16]
+    #[arg(long, value_name = "LUFS", num_args = 0..=1, default_missing_value = "-16", allow_negative_numbers = true)]
+    loudnorm: Option<f64>,
+    
+    /// Audio file format: flac (lossless, WAV/AIFF only), opus or aac (M4A)
+    #[arg(long, default_value = "flac")]
+    audio_format: AudioFormat,
+    
     /// Size threshold (keep if new size < original * threshold)
     #[arg(short, long, default_value = "0.9")]
     threshold: f64,
@@ -76,6 +181,22 @@ struct Args {
     #[arg(short, long, default_value = "4")]
     workers: usize,
     
+    /// CPU threads to fill with work (default: every core)
+    #[arg(long)]
+    max_threads: Option<usize>,
+    
+    /// Memory budget for concurrent files, e.g. 8G (default: 75% of the available memory)
+    #[arg(long)]
+    max_memory: Option<String>,
+    
+    /// What to do when a file exceeds its timeout: fail, copy-original or retry-faster (videos re-encoded with the fastest preset)
+    #[arg(long, default_value = "copy-original")]
+    on_timeout: TimeoutPolicy,
+    
+    /// Scale the timeouts estimated from each file's content (e.g. 3 on slow machines)
+    #[arg(long, default_value = "1.0")]
+    timeout_multiplier: f64,
+    
     /// Dry run - don't actually replace files
     #[arg(long)]
     dry_run: bool,
@@ -111,6 +232,89 @@ struct Args {
     /// Create thumbnails with specified sizes (JSON format: {"gallery": [800, 600], "mini": [150, 150]})
     #[arg(long, value_parser = parse_thumbnails)]
     thumbnails: Option<HashMap<String, ThumbnailSize>>,
+    
+    /// Create video storyboard sprites with a WebVTT index (JSON format: {"scrub": [100, 10, 160]}
+    /// = up to 100 frames, 10 per row, 160px wide)
+    #[arg(long, value_parser = parse_storyboards)]
+    storyboards: Option<HashMap<String, StoryboardProfile>>,
+    
+    /// Also create a muted looping preview clip next to each video poster [default: 3 seconds]
+    #[arg(long, value_name = "SECONDS", num_args = 0..=1, default_missing_value = "3")]
+    video_previews: Option<f64>,
+    
+    /// Image metadata to keep: keep-all, strip-all, strip-location,
+    /// keep-only-copyright-and-date or custom:Tag1,Tag2
+    #[arg(long, default_value = "keep-all")]
+    metadata: MetadataPolicy,
+    
+    /// ICC colour profiles: preserve the embedded profile or convert-to-srgb
+    #[arg(long, default_value = "preserve")]
+    color_profile: ColorProfilePolicy,
+    
+    /// Copy owner/group of the originals to outputs (usually requires root)
+    #[arg(long)]
+    preserve_ownership: bool,
+    
+    /// Start over instead of resuming an interrupted run (files it completed are considered again)
+    #[arg(long)]
+    rescan: bool,
+}
+
+/// Segnale di arresto ricevuto dal processo
+enum ShutdownSignal {
+    Interrupt,
+    Terminate,
+}
+
+/// Attende SIGINT (Ctrl-C) o, su Unix, SIGTERM
+#[cfg(unix)]
+async fn next_signal(terminate: &mut tokio::signal::unix::Signal) -> ShutdownSignal {
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => ShutdownSignal::Interrupt,
+        _ = terminate.recv() => ShutdownSignal::Terminate,
+    }
+}
+
+/// Traduce i segnali in stop dell'optimizer (vedi la documentazione del modulo)
+async fn handle_signals(stop: StopHandle) {
+    #[cfg(unix)]
+    let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!("Failed to install SIGTERM handler: {}", e);
+            return;
+        }
+    };
+    
+    let mut interrupts = 0;
+    loop {
+        #[cfg(unix)]
+        let signal = next_signal(&mut terminate).await;
+        #[cfg(not(unix))]
+        let signal = match tokio::signal::ctrl_c().await {
+            Ok(()) => ShutdownSignal::Interrupt,
+            Err(_) => return,
+        };
+        
+        match signal {
+            ShutdownSignal::Interrupt => interrupts += 1,
+            ShutdownSignal::Terminate => interrupts = interrupts.max(2),
+        }
+        match interrupts {
+            1 => {
+                warn!("Interrupt received: finishing files in progress (press Ctrl-C again to cancel them)");
+                stop.drain();
+            }
+            2 => {
+                warn!("Cancelling files in progress...");
+                stop.cancel();
+            }
+            _ => {
+                warn!("Exiting immediately");
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+        }
+    }
 }
 
 #[tokio::main]
@@ -147,10 +351,32 @@ async fn main() -> Result<()> {
     let config = Config {
         jpeg_quality: args.quality,
         video_crf: args.crf,
+        video_codec: args.video_codec,
+        video_preset: args.preset,
+        video_tune: args.tune,
+        video_profile: args.video_profile,
+        video_level: args.video_level,
+        video_target_bitrate: args.target_bitrate,
+        video_target_size: args.target_size,
+        keyframe_interval: args.keyframe_interval,
+        hdr_policy: args.hdr,
+        stream_policy: args.streams,
+        video_trim: args.trim,
+        max_video_dimension: args.max_video_size,
+        max_video_fps: args.max_fps,
+        remux_videos: args.remux,
         audio_bitrate: args.audio_bitrate,
+        audio_codec: args.audio_codec,
+        audio_channels: args.audio_channels,
+        loudness_target: args.loudnorm,
+        audio_format: args.audio_format,
         size_threshold: args.threshold,
         dry_run: args.dry_run,
         workers: args.workers,
+        max_threads: args.max_threads,
+        max_memory: args.max_memory,
+        timeout_policy: args.on_timeout,
+        timeout_multiplier: args.timeout_multiplier,
         output_path: args.output,
         convert_to_webp: args.webp,
         webp_quality: args.webp_quality,
@@ -158,6 +384,15 @@ async fn main() -> Result<()> {
         skip_video_compression: args.skip_video_compression,
         json_output: args.json_output,
         thumbnails: args.thumbnails.unwrap_or_default(),
+        streaming: args.streaming,
+        streaming_ladder: args.streaming_ladder.unwrap_or_else(StreamingRendition::default_ladder),
+        streaming_min_duration: args.streaming_min_duration,
+        storyboards: args.storyboards.unwrap_or_default(),
+        video_preview_seconds: args.video_previews,
+        metadata_policy: args.metadata,
+        color_profile: args.color_profile,
+        preserve_ownership: args.preserve_ownership,
+        rescan: args.rescan,
     };
     
     // Create optimizer with tool detection
@@ -169,7 +404,12 @@ async fn main() -> Result<()> {
         info!("🚀 Starting tool-based media optimization...");
     }
     
-    optimizer.run(&args.media_directory).await?;
+    tokio::spawn(handle_signals(optimizer.stop_handle()));
+    
+    let stats = optimizer.run(&args.media_directory).await?;
+    if stats.is_interrupted() {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
     
     Ok(())
 }
\ No newline at end of file
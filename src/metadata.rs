@@ -0,0 +1,218 @@
+//! # Image Metadata Policy
+//!
+//! Applica la [`MetadataPolicy`] configurata alle immagini ottimizzate.
+//!
+//! ## Perché dopo l'encoding:
+//! Ogni encoder tratta i metadati in modo diverso: jpegoptim li conserva,
+//! oxipng (`--strip safe`) e cwebp (`-metadata icc`) tengono solo il profilo
+//! colore e scartano EXIF, XMP e IPTC. Per avere lo stesso risultato
+//! indipendentemente dal formato, la policy viene applicata con `exiftool`
+//! sul file di output, copiando i tag ammessi dall'originale.
+//!
+//! ## Politiche:
+//! - `KeepAll`: copia tutti i tag dall'originale
+//! - `StripAll`: rimuove tutti i tag
+//! - `StripLocation`: copia tutto tranne GPS e campi di localizzazione (IPTC/XMP)
+//! - `KeepOnlyCopyrightAndDate`: copia solo autore/copyright e date di scatto
+//! - `Custom`: copia solo i tag indicati
+//!
+//! ## Profilo ICC:
+//! Il profilo colore descrive i pixel e non è un metadato: viene sempre
+//! mantenuto (`--ICC_Profile:all`), altrimenti i colori wide-gamut si spostano.
+//! La conversione in sRGB è gestita da `config.color_profile`.
+//!
+//! ## Orientamento:
+//! Se i pixel sono già stati ruotati (pre-resize o auto-orient), il tag
+//! `Orientation` non viene ricopiato, altrimenti l'immagine verrebbe ruotata due volte.
+
+use anyhow::Result;
+use std::path::Path;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::config::MetadataPolicy;
+use crate::platform::PlatformCommands;
+use crate::utils::to_string_vec;
+
+/// Tag esclusi da `StripLocation` (tutti i gruppi: EXIF, XMP, IPTC)
+const LOCATION_TAGS: &[&str] = &[
+    "GPS*",
+    "Location*",
+    "City",
+    "Sub-location",
+    "State",
+    "Province-State",
+    "Country*",
+];
+
+/// Tag copiati da `KeepOnlyCopyrightAndDate`
+const COPYRIGHT_AND_DATE_TAGS: &[&str] = &[
+    "Copyright",
+    "CopyrightNotice",
+    "Rights",
+    "Artist",
+    "Creator",
+    "By-line",
+    "DateTimeOriginal",
+    "CreateDate",
+    "SubSecTimeOriginal",
+    "OffsetTimeOriginal",
+    "DateCreated",
+    "TimeCreated",
+];
+
+impl MetadataPolicy {
+    /// Indica se la policy conserva il tag EXIF `Orientation`.
+    ///
+    /// Quando non lo conserva, i pixel devono essere ruotati prima dell'encoding.
+    pub fn keeps_orientation(&self) -> bool {
+        match self {
+            MetadataPolicy::KeepAll | MetadataPolicy::StripLocation => true,
+            MetadataPolicy::StripAll | MetadataPolicy::KeepOnlyCopyrightAndDate => false,
+            MetadataPolicy::Custom(tags) => tags.iter().any(|tag| {
+                let name = tag.rsplit(':').next().unwrap_or(tag);
+                name.eq_ignore_ascii_case("Orientation") || name == "*" || name.eq_ignore_ascii_case("all")
+            }),
+        }
+    }
+
+    /// Indica se la policy deve leggere i tag dal file originale
+    pub fn copies_from_source(&self) -> bool {
+        !matches!(self, MetadataPolicy::StripAll)
+    }
+}
+
+/// Applica una [`MetadataPolicy`] ai file prodotti dagli encoder
+pub struct MetadataWriter;
+
+impl MetadataWriter {
+    /// Costruisce gli argomenti exiftool per applicare `policy` a `target`.
+    ///
+    /// # Arguments
+    /// * `source` - File originale da cui copiare i tag ammessi
+    /// * `target` - File ottimizzato da modificare
+    /// * `orientation_baked` - I pixel di `target` sono già ruotati
+    pub fn exiftool_args(
+        policy: &MetadataPolicy,
+        source: &Path,
+        target: &Path,
+        orientation_baked: bool,
+    ) -> Vec<String> {
+        // Si parte sempre da un target pulito (tranne il profilo ICC),
+        // poi si ricopia ciò che la policy ammette
+        let mut args = to_string_vec(["-all=", "--ICC_Profile:all"]);
+
+        if policy.copies_from_source() {
+            args.push("-tagsFromFile".to_string());
+            args.push(source.to_string_lossy().to_string());
+
+            match policy {
+                MetadataPolicy::KeepAll => args.push("-all:all".to_string()),
+                MetadataPolicy::StripLocation => {
+                    args.push("-all:all".to_string());
+                    args.extend(LOCATION_TAGS.iter().map(|tag| format!("--{}", tag)));
+                }
+                MetadataPolicy::KeepOnlyCopyrightAndDate => {
+                    args.extend(COPYRIGHT_AND_DATE_TAGS.iter().map(|tag| format!("-{}", tag)));
+                }
+                MetadataPolicy::Custom(tags) => {
+                    args.extend(tags.iter().map(|tag| format!("-{}", tag)));
+                }
+                MetadataPolicy::StripAll => unreachable!(),
+            }
+
+            if orientation_baked {
+                args.push("--Orientation".to_string());
+            }
+        }
+
+        args.push("-overwrite_original".to_string());
+        args.push(target.to_string_lossy().to_string());
+        args
+    }
+
+    /// Applica `policy` a `target` tramite exiftool.
+    ///
+    /// Con `KeepAll` un errore viene solo loggato (come per i video); con le
+    /// policy che rimuovono dati l'errore viene propagato, per non produrre
+    /// silenziosamente file che contengono ancora ciò che andava rimosso.
+    pub async fn apply(
+        policy: &MetadataPolicy,
+        source: &Path,
+        target: &Path,
+        orientation_baked: bool,
+    ) -> Result<()> {
+        let platform = PlatformCommands::instance();
+        let exiftool_cmd = platform.get_command("exiftool");
+        let args = Self::exiftool_args(policy, source, target, orientation_baked);
+
+        debug!("Applying metadata policy {:?} to {}: {:?}", policy, target.display(), args);
+        let outcome = match Command::new(exiftool_cmd).args(&args).kill_on_drop(true).output().await {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(anyhow::anyhow!(
+                "exiftool failed on {}: {}",
+                target.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => Err(anyhow::anyhow!("Failed to execute exiftool: {}", e)),
+        };
+
+        match outcome {
+            Err(e) if *policy == MetadataPolicy::KeepAll => {
+                warn!("Failed to preserve image metadata for {}: {}", source.display(), e);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn args_for(policy: MetadataPolicy, orientation_baked: bool) -> Vec<String> {
+        MetadataWriter::exiftool_args(
+            &policy,
+            &PathBuf::from("/in/photo.jpg"),
+            &PathBuf::from("/out/photo.webp"),
+            orientation_baked,
+        )
+    }
+
+    #[test]
+    fn test_policy_args() {
+        assert_eq!(
+            args_for(MetadataPolicy::StripAll, false),
+            vec!["-all=", "--ICC_Profile:all", "-overwrite_original", "/out/photo.webp"]
+        );
+
+        let keep = args_for(MetadataPolicy::KeepAll, false);
+        assert_eq!(&keep[..5], ["-all=", "--ICC_Profile:all", "-tagsFromFile", "/in/photo.jpg", "-all:all"]);
+        assert!(!keep.contains(&"--Orientation".to_string()));
+        assert!(args_for(MetadataPolicy::KeepAll, true).contains(&"--Orientation".to_string()));
+
+        let no_gps = args_for(MetadataPolicy::StripLocation, false);
+        assert!(no_gps.contains(&"-all:all".to_string()));
+        assert!(no_gps.contains(&"--GPS*".to_string()));
+
+        let minimal = args_for(MetadataPolicy::KeepOnlyCopyrightAndDate, false);
+        assert!(minimal.contains(&"-Copyright".to_string()));
+        assert!(minimal.contains(&"-DateTimeOriginal".to_string()));
+        assert!(!minimal.contains(&"-all:all".to_string()));
+
+        let custom = args_for(MetadataPolicy::Custom(vec!["EXIF:Make".to_string()]), false);
+        assert_eq!(&custom[4..], ["-EXIF:Make", "-overwrite_original", "/out/photo.webp"]);
+    }
+
+    #[test]
+    fn test_keeps_orientation() {
+        assert!(MetadataPolicy::KeepAll.keeps_orientation());
+        assert!(MetadataPolicy::StripLocation.keeps_orientation());
+        assert!(!MetadataPolicy::StripAll.keeps_orientation());
+        assert!(!MetadataPolicy::KeepOnlyCopyrightAndDate.keeps_orientation());
+        assert!(MetadataPolicy::Custom(vec!["IFD0:Orientation".to_string()]).keeps_orientation());
+        assert!(!MetadataPolicy::Custom(vec!["Copyright".to_string()]).keeps_orientation());
+    }
+}
@@ -0,0 +1,180 @@
+//! # Discovery Module
+//!
+//! Primi stadi della pipeline di elaborazione: scansione della directory e
+//! filtro dei file già elaborati.
+//!
+//! ## Responsabilità:
+//! - Scansione in streaming su un thread bloccante ([`spawn_walker`])
+//! - Metadati (path canonico, dimensione, mtime) di ogni file scoperto
+//! - Salto dei file già elaborati: state file in-place, output esistente
+//!   con `keep_processed` ([`FileFilter`])
+//!
+//! ## Backpressure:
+//! Il walker invia i path su un canale limitato a [`DISCOVERY_BUFFER`]: quando
+//! gli stadi successivi sono indietro la scansione si ferma, quindi la memoria
+//! resta costante anche su alberi con milioni di file. Chiudere il receiver
+//! termina la scansione.
+
+use crate::{
+    config::Config,
+    file_manager::FileManager,
+    optimizer::path_resolver::PathResolver,
+    state::StateManager,
+};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Path in attesa tra il walker e il filtro
+pub const DISCOVERY_BUFFER: usize = 1024;
+
+/// File scoperto dalla scansione e da elaborare
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredFile {
+    /// Path come trovato dalla scansione (journal ed eventi)
+    pub path: PathBuf,
+    /// Path canonico, usato per l'elaborazione e lo state file
+    pub canonical: PathBuf,
+    pub size: u64,
+    pub modified_time: u64,
+}
+
+/// Avvia la scansione di `media_dir` su un thread bloccante.
+///
+/// # Returns
+/// Il receiver dei file media trovati e il task della scansione, che
+/// restituisce il numero di file inviati.
+pub fn spawn_walker(media_dir: &Path) -> (mpsc::Receiver<PathBuf>, JoinHandle<usize>) {
+    let (sender, receiver) = mpsc::channel(DISCOVERY_BUFFER);
+    let media_dir = media_dir.to_path_buf();
+    let walker = tokio::task::spawn_blocking(move || {
+        let mut sent = 0;
+        for path in FileManager::walk_media_files(&media_dir) {
+            if sender.blocking_send(path).is_err() {
+                debug!("Scan stopped after {} files", sent);
+                break;
+            }
+            sent += 1;
+        }
+        sent
+    });
+    (receiver, walker)
+}
+
+/// Filtro dei file già elaborati
+pub struct FileFilter<'a> {
+    config: &'a Config,
+    input_base_dir: &'a Path,
+    /// State file dell'ottimizzazione in-place (None: nessun controllo)
+    state: Option<&'a StateManager>,
+}
+
+impl<'a> FileFilter<'a> {
+    pub fn new(config: &'a Config, input_base_dir: &'a Path, state: Option<&'a StateManager>) -> Self {
+        Self { config, input_base_dir, state }
+    }
+
+    /// Legge i metadati di un file scoperto.
+    ///
+    /// # Returns
+    /// * `Ok(None)` - Il file è già elaborato e va saltato
+    /// * `Err` - Il file non è leggibile (rimosso nel frattempo, permessi)
+    pub async fn check(&self, path: &Path) -> Result<Option<DiscoveredFile>> {
+        let canonical = path.canonicalize()
+            .map_err(|e| anyhow::anyhow!("Failed to canonicalize path {}: {}", path.display(), e))?;
+        let (size, modified_time) = FileManager::get_file_info(&canonical).await
+            .map_err(|e| anyhow::anyhow!("Failed to get file info for {}: {}", canonical.display(), e))?;
+
+        if self.is_processed(&canonical, modified_time)? {
+            return Ok(None);
+        }
+        Ok(Some(DiscoveredFile {
+            path: path.to_path_buf(),
+            canonical,
+            size,
+            modified_time,
+        }))
+    }
+
+    /// Controlla se un file deve essere skippato
+    fn is_processed(&self, file_path: &Path, modified_time: u64) -> Result<bool> {
+        if self.config.output_path.is_none() {
+            // Per ottimizzazione in-place, controlla state manager
+            if self.state.is_some_and(|state| state.is_processed(file_path, modified_time)) {
+                debug!("Skipping already processed file (in-place): {}", file_path.display());
+                return Ok(true);
+            }
+        } else if self.config.keep_processed {
+            // Per output directory con --keep-processed, controlla se output esiste
+            let expected_output_path = PathResolver::get_output_path(file_path, self.input_base_dir, self.config)?;
+            // Un'ottimizzazione scartata lascia una copia con l'estensione originale
+            // Un video lungo può essere diventato un bundle di streaming
+            let streaming_manifest = PathResolver::get_streaming_bundle_dir(file_path, self.input_base_dir, self.config)?
+                .zip(self.config.streaming)
+                .map(|(dir, format)| dir.join(format.manifest_name()));
+            if expected_output_path.exists()
+                || PathResolver::get_passthrough_path(file_path, self.input_base_dir, self.config)?.exists()
+                || streaming_manifest.is_some_and(|manifest| manifest.exists()) {
+                debug!("[OK] Skipping file, output already exists: {} -> {}",
+                       file_path.display(), expected_output_path.display());
+                return Ok(true);
+            } else {
+                debug!("[PROCESS] Output does not exist, will process: {}", expected_output_path.display());
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_walker_and_filter() {
+        let temp_dir = TempDir::new().unwrap();
+        let media = temp_dir.path().join("media");
+        let output = temp_dir.path().join("out");
+        std::fs::create_dir_all(media.join("album")).unwrap();
+        std::fs::create_dir_all(&output).unwrap();
+        for name in ["a.jpg", "album/b.png", "notes.txt"] {
+            std::fs::write(media.join(name), b"media").unwrap();
+        }
+        std::fs::write(output.join("a.jpg"), b"done").unwrap();
+
+        let (mut receiver, walker) = spawn_walker(&media);
+        let mut found = Vec::new();
+        while let Some(path) = receiver.recv().await {
+            found.push(path);
+        }
+        found.sort();
+        assert_eq!(found, [media.join("a.jpg"), media.join("album/b.png")]);
+        assert_eq!(walker.await.unwrap(), 2);
+
+        let config = Config {
+            output_path: Some(output),
+            keep_processed: true,
+            ..Config::default()
+        };
+        let filter = FileFilter::new(&config, &media, None);
+        assert_eq!(filter.check(&found[0]).await.unwrap(), None);
+        let file = filter.check(&found[1]).await.unwrap().unwrap();
+        assert_eq!((file.path.as_path(), file.size), (found[1].as_path(), 5));
+        assert!(filter.check(&media.join("missing.jpg")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_walker_stops_when_closed() {
+        let temp_dir = TempDir::new().unwrap();
+        for index in 0..DISCOVERY_BUFFER + 10 {
+            std::fs::write(temp_dir.path().join(format!("{}.jpg", index)), b"").unwrap();
+        }
+
+        let (receiver, walker) = spawn_walker(temp_dir.path());
+        drop(receiver);
+        assert!(walker.await.unwrap() <= DISCOVERY_BUFFER);
+    }
+}
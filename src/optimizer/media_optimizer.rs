@@ -2,178 +2,115 @@
 //!
 //! Orchestratore principale semplificato che delega responsabilità
 //! ai moduli specializzati.
+//!
+//! ## Pipeline:
+//! Gli stadi sono collegati da canali limitati, quindi la memoria resta
+//! costante anche su alberi con milioni di file e il primo file parte appena
+//! scoperto, mentre la scansione prosegue:
+//! 1. walker: scansione su un thread bloccante (vedi `discovery`)
+//! 2. filtro: metadati e salto dei file già elaborati o completati
+//! 3. probe: contenuto (header, ffprobe) e costo di più file in parallelo
+//! 4. scheduler: avvio secondo il costo stimato (vedi `scheduler`), senza
+//!    mai attendere un sottoprocesso
+//! 5. worker: un task per file, finché tiene la sua prenotazione
+//! 6. sink: progress, journal e statistiche, un esito alla volta
+//!
+//! Thumbnail, poster e storyboard vengono creati dagli originali in uno
+//! stadio parallelo alimentato dal filtro, senza ritardare l'ottimizzazione.
+//!
+//! ## Stop e ripresa:
+//! [`MediaOptimizer::stop_handle`] restituisce uno [`StopHandle`] a due livelli:
+//! - `drain`: non vengono avviati altri file, quelli in corso terminano
+//! - `cancel`: anche i file in corso vengono interrotti scartando il loro
+//!   future, il che termina i processi figli (`kill_on_drop`) e rimuove i
+//!   file temporanei
+//!
+//! I file completati sono registrati nel [`RunJournal`]: la run successiva
+//! riparte dalla scansione saltandoli (salvo `config.rescan`). L'insieme dei
+//! file completati resta in memoria per tutta la run ripresa (vedi
+//! [`crate::journal`] per il costo).
+//!
+//! ## Timeout:
+//! Ogni file ha un timeout stimato dal contenuto (vedi `timeout`); allo
+//! scadere si applica `config.timeout_policy`: fallire, copiare l'originale
+//! nella directory di output o ritentare i video con il preset più veloce.
+//! La policy applicata arriva nell'evento JSON `timeout` e nell'errore del file.
 
 use crate::{
-    config::Config,
-    file_manager::FileManager,
+    config::{Config, TimeoutPolicy},
+    error::OptimizeError,
+    file_manager::{FileManager, MediaKind},
     image_processor::ImageProcessor,
+    journal::RunJournal,
     json_output::{JsonConfig, JsonMessage, HistoricalStats},
-    optimizer::{progress_tracker::ProgressTracker, task_optimizer::TaskOptimizer},
-    progress::OptimizationStats,
+    optimizer::{
+        discovery::{self, DiscoveredFile, FileFilter},
+        progress_tracker::ProgressTracker,
+        scheduler::{JobCost, MediaProbe, PendingJob, Reservation, ResourceBudget, Scheduler, LOOKAHEAD},
+        task_optimizer::TaskOptimizer,
+        timeout,
+    },
+    progress::{OptimizationStats, ProgressCallback},
     resize::{ImageResizer, ResizeAlgorithm, ResizeMode},
     state::{StateManager, ProcessedFile},
     video_processor::VideoProcessor,
+    video_thumbnail::VideoThumbnailer,
 };
 use anyhow::Result;
+use futures::StreamExt;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::task::JoinSet;
 use tracing::{debug, error, info, warn};
 
-/// Classificazione dei file per dimensione per gestire la concorrenza
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum FileSize {
-    Small,  // < 5MB
-    Medium, // 5MB - 20MB 
-    Large,  // > 20MB
-}
+/// Esiti in attesa del sink dei risultati
+const RESULT_BUFFER: usize = 256;
 
-impl FileSize {
-    /// Classifica un file in base alla sua dimensione
-    fn classify(size_bytes: u64) -> Self {
-        const MB_5: u64 = 5 * 1024 * 1024;
-        const MB_20: u64 = 20 * 1024 * 1024;
-        
-        if size_bytes < MB_5 {
-            Self::Small
-        } else if size_bytes < MB_20 {
-            Self::Medium
-        } else {
-            Self::Large
-        }
-    }
-    
-    /// Ottieni il numero massimo di worker per questa classe di file
-    fn max_concurrent(&self, total_workers: usize) -> usize {
-        match self {
-            Self::Small => total_workers,           // Tutti i worker disponibili
-            Self::Medium => (total_workers / 2).max(1), // Metà dei worker
-            Self::Large => 1,                       // Solo un worker per file grandi
-        }
-    }
-    
-    /// Descrizione per il logging
-    fn description(&self) -> &'static str {
-        match self {
-            Self::Small => "small (<5MB)",
-            Self::Medium => "medium (5-20MB)",
-            Self::Large => "large (>20MB)",
-        }
-    }
-}
+/// Originali in attesa dello stadio delle anteprime
+const PREVIEW_BUFFER: usize = 256;
+
+/// File letti in parallelo dallo stadio di probe
+const PROBE_CONCURRENCY: usize = 8;
+
+/// File filtrato, con il contenuto letto (serve anche per il timeout) e il costo
+type ProbedJob = PendingJob<(DiscoveredFile, MediaProbe)>;
 
-/// Gestione intelligente della concorrenza basata su dimensioni dei file
-struct ConcurrencyManager {
-    /// Semaforo per file piccoli (può usare tutti i worker)
-    small_semaphore: Arc<Semaphore>,
-    /// Semaforo per file medi (usa metà dei worker)
-    medium_semaphore: Arc<Semaphore>,
-    /// Semaforo per file grandi (solo 1 alla volta)
-    large_semaphore: Arc<Semaphore>,
-    /// Semaforo per video (sempre 1 alla volta)
-    video_semaphore: Arc<Semaphore>,
-    /// Semaforo globale per bloccare tutto quando un file grande è in elaborazione
-    global_large_block: Arc<Semaphore>,
+/// Esito di un file, inviato al sink dal filtro o dal worker
+struct FileOutcome {
+    /// Path come trovato dalla scansione
+    path: PathBuf,
+    result: Result<Option<ProcessedFile>>,
+    /// Policy applicata se il file ha superato il timeout
+    timeout: Option<TimeoutPolicy>,
 }
 
-impl ConcurrencyManager {
-    fn new(max_workers: usize) -> Self {
-        let small_workers = max_workers;
-        let medium_workers = (max_workers / 2).max(1);
-        
-        info!("🔧 Concurrency configuration:");
-        info!("  • Small files (<5MB): {} concurrent workers", small_workers);
-        info!("  • Medium files (5-20MB): {} concurrent workers", medium_workers);
-        info!("  • Large files (>20MB): 1 worker (blocks others)");
-        info!("  • Videos: 1 worker (always serial)");
-        
-        Self {
-            small_semaphore: Arc::new(Semaphore::new(small_workers)),
-            medium_semaphore: Arc::new(Semaphore::new(medium_workers)),
-            large_semaphore: Arc::new(Semaphore::new(1)),
-            video_semaphore: Arc::new(Semaphore::new(1)),
-            global_large_block: Arc::new(Semaphore::new(max_workers)),
-        }
-    }
-    
-    /// Ottieni i permessi appropriati per un file
-    async fn acquire_permits(&self, file_path: &Path, file_size: u64) -> Result<ConcurrencyPermits> {
-        let is_video = FileManager::is_video(file_path);
-        
-        if is_video {
-            // Video: sempre seriale, ottieni permesso video
-            let video_permit = self.video_semaphore.clone().acquire_owned().await?;
-            debug!("Acquired video permit for {}", file_path.display());
-            return Ok(ConcurrencyPermits::Video(video_permit));
-        }
-        
-        let size_class = FileSize::classify(file_size);
-        debug!("File {} ({}) classified as {} ", 
-               file_path.display(), 
-               crate::file_manager::FileManager::format_size(file_size),
-               size_class.description());
-        
-        match size_class {
-            FileSize::Small => {
-                let permit = self.small_semaphore.clone().acquire_owned().await?;
-                debug!("Acquired small file permit for {}", file_path.display());
-                Ok(ConcurrencyPermits::Small(permit))
-            }
-            FileSize::Medium => {
-                let permit = self.medium_semaphore.clone().acquire_owned().await?;
-                debug!("Acquired medium file permit for {}", file_path.display());
-                Ok(ConcurrencyPermits::Medium(permit))
-            }
-            FileSize::Large => {
-                // File grandi: acquisisce TUTTI i permessi globali per bloccare tutto il resto
-                info!("🔒 Large file {} detected - acquiring exclusive processing lock", file_path.display());
-                
-                // Prima acquisisce il permesso per file grandi
-                let large_permit = self.large_semaphore.clone().acquire_owned().await?;
-                
-                // Poi cerca di acquisire tutti i permessi globali per bloccare altri processi
-                let available = self.global_large_block.available_permits();
-                let mut global_permits = Vec::new();
-                
-                for _ in 0..available {
-                    if let Ok(permit) = self.global_large_block.clone().try_acquire_owned() {
-                        global_permits.push(permit);
-                    } else {
-                        break;
-                    }
-                }
-                
-                if !global_permits.is_empty() {
-                    info!("🔒 Exclusive lock acquired for large file {} (blocked {} other processes)", 
-                          file_path.display(), global_permits.len());
-                } else {
-                    debug!("Acquired large file permit for {} (no other processes to block)", file_path.display());
-                }
-                
-                Ok(ConcurrencyPermits::Large(large_permit, global_permits))
-            }
-        }
-    }
+/// Passo dello scheduler
+enum ScheduleStep {
+    Stop,
+    Start((DiscoveredFile, MediaProbe), Reservation),
+    Discovered(Option<ProbedJob>),
 }
 
-/// Permessi di concorrenza per diversi tipi di file
-enum ConcurrencyPermits {
-    Small(tokio::sync::OwnedSemaphorePermit),
-    Medium(tokio::sync::OwnedSemaphorePermit),
-    Large(tokio::sync::OwnedSemaphorePermit, Vec<tokio::sync::OwnedSemaphorePermit>), // Include permessi globali
-    Video(tokio::sync::OwnedSemaphorePermit),
+/// Comando di stop per una run in corso, clonabile e utilizzabile da altri task
+#[derive(Clone)]
+pub struct StopHandle {
+    drain_sender: broadcast::Sender<()>,
+    cancel_sender: broadcast::Sender<()>,
 }
 
-impl Drop for ConcurrencyPermits {
-    fn drop(&mut self) {
-        match self {
-            ConcurrencyPermits::Large(_, global_permits) => {
-                if !global_permits.is_empty() {
-                    debug!("🔓 Released exclusive lock for large file");
-                }
-            }
-            _ => {}
-        }
+impl StopHandle {
+    /// Smette di avviare nuovi file; quelli in corso terminano normalmente
+    pub fn drain(&self) {
+        let _ = self.drain_sender.send(());
+    }
+    
+    /// Smette di avviare nuovi file e interrompe quelli in corso
+    pub fn cancel(&self) {
+        let _ = self.drain_sender.send(());
+        let _ = self.cancel_sender.send(());
     }
 }
 
@@ -182,7 +119,12 @@ pub struct MediaOptimizer {
     config: Config,
     state_manager: StateManager,
     input_base_dir: PathBuf,
-    concurrency_manager: ConcurrencyManager,
+    /// Avvio dei file secondo thread CPU e memoria liberi
+    scheduler: Arc<Scheduler>,
+    /// Stop dello scheduling (vedi [`StopHandle::drain`])
+    drain_sender: broadcast::Sender<()>,
+    /// Cancellazione dei file in corso (vedi [`StopHandle::cancel`])
+    stop_sender: broadcast::Sender<()>,
 }
 
 impl MediaOptimizer {
@@ -190,69 +132,105 @@ impl MediaOptimizer {
     pub async fn new(media_dir: &Path, config: Config) -> Result<Self> {
         config.validate()?;
         let state_manager = StateManager::new(media_dir).await?;
-        let concurrency_manager = ConcurrencyManager::new(config.workers);
+        let scheduler = Scheduler::new(ResourceBudget::detect(&config));
+        let (drain_sender, _) = broadcast::channel(1);
+        let (stop_sender, _) = broadcast::channel(1);
         
         Ok(Self {
             config,
             state_manager,
             input_base_dir: media_dir.to_path_buf(),
-            concurrency_manager,
+            scheduler,
+            drain_sender,
+            stop_sender,
         })
     }
     
-    /// Esegue il processo di ottimizzazione
-    pub async fn run(&mut self, media_dir: &Path) -> Result<()> {
+    /// Restituisce il comando di stop per questa istanza (es. per i segnali)
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle {
+            drain_sender: self.drain_sender.clone(),
+            cancel_sender: self.stop_sender.clone(),
+        }
+    }
+    
+    /// Esegue il processo di ottimizzazione.
+    ///
+    /// Restituisce le statistiche della run: se è stata interrotta da uno stop
+    /// ([`OptimizationStats::is_interrupted`]) la run successiva la riprende.
+    pub async fn run(&mut self, media_dir: &Path) -> Result<OptimizationStats> {
         let start_time = std::time::Instant::now();
+        // Sottoscrizione immediata: uno stop durante i preparativi non va perso
+        let drain_receiver = self.drain_sender.subscribe();
         
-        // Trova tutti i file media
-        let files = FileManager::find_media_files(media_dir)?;
+        // In dry run nessun file viene scritto: niente da riprendere
+        let journal = if self.config.dry_run {
+            None
+        } else {
+            Some(Arc::new(RunJournal::new(media_dir, self.config.output_path.as_deref()).await?))
+        };
+        
+        // File già completati da una run interrotta: la scansione li salta
+        let completed = match &journal {
+            Some(journal) if !self.config.rescan => journal.completed_files().await?.filter(|files| !files.is_empty()),
+            _ => None,
+        };
         
-        self.emit_start_message(media_dir, &files).await;
-        self.log_configuration(&files);
+        self.emit_start_message(media_dir, completed.as_ref()).await;
+        self.log_configuration();
         
         // Controlla dipendenze
         self.check_dependencies().await?;
         self.state_manager.cleanup().await?;
         
-        if files.is_empty() {
-            self.handle_empty_directory(start_time).await;
-            return Ok(());
+        if let Some(ref journal) = journal {
+            journal.start(completed.is_some()).await?;
         }
+        let completed = completed.unwrap_or_default();
         
         // Processa file con concorrenza controllata
-        let progress_tracker = ProgressTracker::new(files.len());
+        let progress_tracker = ProgressTracker::new();
+        let stats = self.process_files_concurrently(
+            media_dir, &completed, progress_tracker.clone(), drain_receiver, journal.clone()
+        ).await?;
         
-        // STRATEGIA MIGLIORATA: Crea thumbnails dalle originali PRIMA dell'ottimizzazione
-        if !self.config.thumbnails.is_empty() && self.config.output_path.is_some() {
-            info!("🖼️ Creating thumbnails from original images (before optimization)...");
-            self.create_thumbnails_from_originals(&files).await?;
+        if let Some(ref journal) = journal {
+            journal.finish(!stats.is_interrupted()).await?;
         }
         
-        let stats = self.process_files_concurrently(files, progress_tracker.clone()).await?;
+        if progress_tracker.total_files() == 0 && !stats.is_interrupted() {
+            progress_tracker.finish("");
+            self.handle_empty_directory(start_time).await;
+            return Ok(stats);
+        }
         
         // Finalizza e stampa statistiche
         progress_tracker.finish(&stats.format_summary());
         self.print_final_stats(&stats, start_time.elapsed().as_secs_f64()).await?;
         
-        Ok(())
+        Ok(stats)
     }
     
     /// Invia messaggio di inizio
-    async fn emit_start_message(&self, media_dir: &Path, files: &[PathBuf]) {
+    async fn emit_start_message(&self, media_dir: &Path, completed: Option<&HashSet<PathBuf>>) {
         if self.config.json_output {
             JsonMessage::start(
                 media_dir.to_path_buf(),
                 self.config.output_path.clone(),
-                files.len(),
+                0,
+                completed.is_some(),
                 JsonConfig::from(&self.config),
             ).emit();
         } else {
             info!("Starting media optimization in: {}", media_dir.display());
+            if let Some(completed) = completed {
+                info!("Resuming interrupted run: skipping {} completed files (use --rescan to start over)", completed.len());
+            }
         }
     }
     
     /// Logga configurazione (solo se non JSON mode)
-    fn log_configuration(&self, files: &[PathBuf]) {
+    fn log_configuration(&self) {
         if self.config.json_output {
             return;
         }
@@ -281,7 +259,7 @@ impl MediaOptimizer {
         if self.config.skip_video_compression {
             info!("Video mode: Skip compression (copy only)");
         } else {
-            info!("Video mode: Compress videos (CRF: {})", self.config.video_crf);
+            info!("Video mode: Compress videos ({:?}, CRF: {})", self.config.video_codec, self.config.video_crf);
         }
         
         // Log thumbnail configuration if enabled
@@ -291,15 +269,14 @@ impl MediaOptimizer {
                 info!("  • {} - {}x{}", name, size.width, size.height);
             }
         }
-        
-        info!("Found {} media files to process", files.len());
     }
     
     /// Gestisce directory vuota
     async fn handle_empty_directory(&self, start_time: std::time::Instant) {
         if self.config.json_output {
             JsonMessage::complete(
-                0, 0, 0, 0, 0, 0.0, start_time.elapsed().as_secs_f64(),
+                &OptimizationStats::new(),
+                start_time.elapsed().as_secs_f64(),
                 HistoricalStats {
                     total_files_ever_processed: 0,
                     total_bytes_saved_historically: 0,
@@ -311,150 +288,492 @@ impl MediaOptimizer {
         }
     }
     
-    /// Processa file con concorrenza controllata basata sulle dimensioni
+    /// Processa i file in parallelo mentre la scansione procede (vedi "Pipeline").
+    ///
+    /// I file in `completed` (journal di una run interrotta) vengono saltati
+    /// senza contarli.
     async fn process_files_concurrently(
         &self,
-        files: Vec<PathBuf>,
-        progress_tracker: ProgressTracker
+        media_dir: &Path,
+        completed: &HashSet<PathBuf>,
+        progress_tracker: ProgressTracker,
+        drain_receiver: broadcast::Receiver<()>,
+        journal: Option<Arc<RunJournal>>,
     ) -> Result<OptimizationStats> {
-        let mut tasks: Vec<tokio::task::JoinHandle<Result<Option<ProcessedFile>, anyhow::Error>>> = Vec::new();
-        let mut stats = OptimizationStats::new();
+        if !self.config.json_output {
+            let budget = self.scheduler.budget();
+            info!("🔧 Scheduler: {} CPU threads, {} memory, up to {} files at once",
+                  budget.threads, FileManager::format_size(budget.memory), budget.jobs);
+        }
+
+        let (paths, walker) = discovery::spawn_walker(media_dir);
+        let (file_sender, file_receiver) = mpsc::channel(LOOKAHEAD);
+        let (job_sender, job_receiver) = mpsc::channel(LOOKAHEAD);
+        let (outcome_sender, outcome_receiver) = mpsc::channel(RESULT_BUFFER);
+        // Le anteprime richiedono una directory di output
+        let previews = !self.config.thumbnails.is_empty() || !self.config.storyboards.is_empty();
+        let (preview_sender, preview_receiver) = if previews && self.config.output_path.is_some() {
+            let (sender, receiver) = mpsc::channel(PREVIEW_BUFFER);
+            (Some(sender), Some(receiver))
+        } else {
+            (None, None)
+        };
 
-        // Pre-calcola le dimensioni di tutti i file per statistiche
-        let mut file_sizes = Vec::new();
-        let mut small_count = 0;
-        let mut medium_count = 0;
-        let mut large_count = 0;
-        let mut video_count = 0;
-
-        for file_path in &files {
-            if let Ok(metadata) = tokio::fs::metadata(file_path).await {
-                let size = metadata.len();
-                file_sizes.push((file_path.clone(), size));
-                
-                if FileManager::is_video(file_path) {
-                    video_count += 1;
-                } else {
-                    match FileSize::classify(size) {
-                        FileSize::Small => small_count += 1,
-                        FileSize::Medium => medium_count += 1,
-                        FileSize::Large => large_count += 1,
-                    }
+        let (scan_complete, not_probed, not_started, mut stats, previews) = tokio::join!(
+            self.filter_files(paths, completed, file_sender, preview_sender, outcome_sender.clone(), &progress_tracker),
+            self.probe_files(file_receiver, job_sender),
+            self.schedule_files(job_receiver, outcome_sender, &progress_tracker, drain_receiver),
+            self.collect_results(outcome_receiver, &progress_tracker, journal.as_deref()),
+            self.create_previews(preview_receiver),
+        );
+        previews?;
+        // Il walker termina da solo quando il filtro chiude il suo canale
+        walker.await?;
+
+        stats.files_remaining = not_probed + not_started?;
+        stats.scan_interrupted = !scan_complete;
+        if stats.is_interrupted() {
+            warn!("Stop requested: {} files not started{}", stats.files_remaining,
+                  if scan_complete { "" } else { ", scan interrupted" });
+        }
+        Ok(stats)
+    }
+
+    /// Stadio di filtro: metadati dei file scoperti e salto di quelli già elaborati.
+    ///
+    /// I file saltati o illeggibili vanno direttamente al sink, gli altri allo
+    /// scheduler. Le immagini e i video vanno anche allo stadio delle anteprime,
+    /// se attivo, compresi quelli già elaborati. Restituisce `true` se la
+    /// scansione è arrivata in fondo.
+    async fn filter_files(
+        &self,
+        mut paths: mpsc::Receiver<PathBuf>,
+        completed: &HashSet<PathBuf>,
+        files: mpsc::Sender<DiscoveredFile>,
+        mut previews: Option<mpsc::Sender<PathBuf>>,
+        outcomes: mpsc::Sender<FileOutcome>,
+        progress_tracker: &ProgressTracker,
+    ) -> bool {
+        let filter = FileFilter::new(&self.config, &self.input_base_dir, Some(&self.state_manager));
+        let mut kinds = [0usize; 3];
+        while let Some(path) = paths.recv().await {
+            // Lo scheduler si è fermato: chiudere il canale ferma anche il walker
+            if files.is_closed() {
+                return false;
+            }
+            if completed.contains(&path) {
+                continue;
+            }
+            progress_tracker.add_discovered();
+            match FileManager::media_kind(&path) {
+                Some(MediaKind::Video) => kinds[1] += 1,
+                Some(MediaKind::Audio) => kinds[2] += 1,
+                _ => kinds[0] += 1,
+            }
+
+            if let Some(sender) = previews.as_ref().filter(|_| self.is_preview_source(&path)) {
+                // Lo stadio delle anteprime si è fermato (stop): non riceve altro
+                if sender.send(path.clone()).await.is_err() {
+                    previews = None;
                 }
-            } else {
-                file_sizes.push((file_path.clone(), 0));
+            }
+
+            let sent = match filter.check(&path).await {
+                Ok(Some(file)) => files.send(file).await.is_ok(),
+                Ok(None) => outcomes.send(FileOutcome { path, result: Ok(None), timeout: None }).await.is_ok(),
+                Err(e) => outcomes.send(FileOutcome { path, result: Err(e), timeout: None }).await.is_ok(),
+            };
+            if !sent {
+                return false;
             }
         }
 
         if !self.config.json_output {
-            info!("📊 File size distribution:");
-            info!("  • Small files (<5MB): {} files", small_count);
-            info!("  • Medium files (5-20MB): {} files", medium_count);
-            info!("  • Large files (>20MB): {} files", large_count);
-            info!("  • Video files: {} files", video_count);
-        }
-
-        for (index, (file_path, file_size)) in file_sizes.into_iter().enumerate() {
-            // Ottieni i permessi appropriati in base alla dimensione del file
-            let permits = self.concurrency_manager.acquire_permits(&file_path, file_size).await?;
-
-            let mut task_optimizer = TaskOptimizer::new(self.config.clone(), self.input_base_dir.clone()).await?;
-            let progress_clone = progress_tracker.clone();
-            let is_video = FileManager::is_video(&file_path);
-
-            let task = tokio::spawn(async move {
-                let _permits = permits; // I permessi vengono rilasciati automaticamente quando il task finisce
-
-                // Emetti evento inizio file
-                if task_optimizer.config.json_output {
-                    JsonMessage::file_start(
-                        file_path.clone(),
-                        file_size,
-                        index,
-                        progress_clone.total_files,
-                    ).emit();
+            info!("📊 Scan complete: {} images, {} videos, {} audio", kinds[0], kinds[1], kinds[2]);
+        }
+        progress_tracker.complete_scan(&self.config).await;
+        true
+    }
+
+    /// Stadio di scheduling: sceglie tra i file in attesa quello da avviare
+    /// appena thread e memoria lo permettono, fino allo stop.
+    ///
+    /// Restituisce il numero di file ricevuti ma non avviati.
+    async fn schedule_files(
+        &self,
+        mut files: mpsc::Receiver<ProbedJob>,
+        outcomes: mpsc::Sender<FileOutcome>,
+        progress_tracker: &ProgressTracker,
+        mut drain_receiver: broadcast::Receiver<()>,
+    ) -> Result<usize> {
+        let mut queue: Vec<ProbedJob> = Vec::new();
+        let mut discovering = true;
+        let mut index = 0;
+        loop {
+            // Accoda i file già letti, senza attendere la scansione
+            while discovering && queue.len() < LOOKAHEAD {
+                match files.try_recv() {
+                    Ok(file) => queue.push(file),
+                    Err(mpsc::error::TryRecvError::Empty) => break,
+                    Err(mpsc::error::TryRecvError::Disconnected) => discovering = false,
                 }
+            }
+            if queue.is_empty() && !discovering {
+                return Ok(0);
+            }
 
-                // Timeout basato sul tipo di file e dimensione
-                let timeout_duration = if is_video {
-                    std::time::Duration::from_secs(900) // 15 minuti per video
-                } else {
-                    match FileSize::classify(file_size) {
-                        FileSize::Small => std::time::Duration::from_secs(120),  // 2 minuti per file piccoli
-                        FileSize::Medium => std::time::Duration::from_secs(300), // 5 minuti per file medi
-                        FileSize::Large => std::time::Duration::from_secs(1200), // 20 minuti per file grandi
+            // Avvia il file scelto dallo scheduler appena le risorse lo
+            // permettono, intanto riempie la finestra di scelta, e smette non
+            // appena arriva lo stop. Il task si sottoscrive prima dell'attesa,
+            // così nessuno stop va perso
+            let task_stop = self.stop_sender.subscribe();
+            let can_start = !queue.is_empty();
+            let can_fill = discovering && queue.len() < LOOKAHEAD;
+            let step = tokio::select! {
+                biased;
+                _ = Self::wait_for_stop(&mut drain_receiver) => ScheduleStep::Stop,
+                (file, reservation) = Self::next_job(&self.scheduler, &mut queue), if can_start => {
+                    ScheduleStep::Start(file, reservation)
+                }
+                file = files.recv(), if can_fill => ScheduleStep::Discovered(file),
+            };
+
+            match step {
+                ScheduleStep::Stop => {
+                    // Svuota i file già letti: probe, filtro e walker si fermano
+                    files.close();
+                    let mut not_started = queue.len();
+                    while files.try_recv().is_ok() {
+                        not_started += 1;
                     }
+                    return Ok(not_started);
+                }
+                ScheduleStep::Discovered(Some(file)) => queue.push(file),
+                ScheduleStep::Discovered(None) => discovering = false,
+                ScheduleStep::Start((file, probe), reservation) => {
+                    self.spawn_worker(file, probe, index, reservation, task_stop, outcomes.clone(), progress_tracker).await?;
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Stadio di probe: legge il contenuto dei file filtrati, fino a
+    /// [`PROBE_CONCURRENCY`] alla volta, e li passa allo scheduler con il
+    /// loro costo.
+    ///
+    /// Restituisce il numero di file ricevuti ma non passati allo scheduler
+    /// perché si è fermato (stop).
+    async fn probe_files(&self, mut files: mpsc::Receiver<DiscoveredFile>, jobs: mpsc::Sender<ProbedJob>) -> usize {
+        let received = AtomicUsize::new(0);
+        let mut forwarded = 0;
+        {
+            let mut probes = futures::stream::poll_fn(|cx| files.poll_recv(cx))
+                .inspect(|_| {
+                    received.fetch_add(1, Ordering::Relaxed);
+                })
+                .map(|file| self.pending_job(file))
+                .buffer_unordered(PROBE_CONCURRENCY);
+            loop {
+                let job = tokio::select! {
+                    _ = jobs.closed() => break,
+                    job = probes.next() => job,
                 };
-                
-                let result = tokio::time::timeout(
-                    timeout_duration,
-                    task_optimizer.process_single_file(file_path.clone())
-                ).await;
-                
-                let result = match result {
-                    Ok(r) => r,
-                    Err(_) => {
-                        error!("File processing timed out after {:?}: {}", timeout_duration, file_path.display());
-                        Self::handle_timeout(&task_optimizer, &file_path).await;
-                        Err(anyhow::anyhow!("Processing timeout"))
-                    }
+                // Il filtro ha finito e tutti i file sono passati allo scheduler
+                let Some(job) = job else {
+                    return 0;
                 };
+                if jobs.send(job).await.is_err() {
+                    break;
+                }
+                forwarded += 1;
+            }
+        }
 
-                // Gestisci risultati e eventi JSON
-                progress_clone.handle_file_completion(&task_optimizer.config, &file_path, &result).await;
-                result
-            });
-
-            tasks.push(task);
+        // Lo scheduler si è fermato: chiudere il canale ferma anche il filtro
+        files.close();
+        let mut not_started = received.into_inner() - forwarded;
+        while files.try_recv().is_ok() {
+            not_started += 1;
         }
-        
-        // Aspetta tutti i task e raccoglie risultati
-        for task in tasks {
-            match task.await? {
+        not_started
+    }
+
+    /// File in attesa con il suo costo stimato (il contenuto letto serve
+    /// anche per il timeout)
+    async fn pending_job(&self, file: DiscoveredFile) -> ProbedJob {
+        let probe = MediaProbe::read(&file.canonical, file.size).await;
+        let cost = JobCost::for_probe(&probe, &file.canonical, file.size, &self.config);
+        PendingJob::new((file, probe), cost)
+    }
+
+    /// Avvia il worker di un file; l'esito va al sink
+    #[allow(clippy::too_many_arguments)]
+    async fn spawn_worker(
+        &self,
+        file: DiscoveredFile,
+        probe: MediaProbe,
+        index: usize,
+        reservation: Reservation,
+        mut task_stop: broadcast::Receiver<()>,
+        outcomes: mpsc::Sender<FileOutcome>,
+        progress_tracker: &ProgressTracker,
+    ) -> Result<()> {
+        let cost = reservation.cost();
+        let limit = timeout::file_timeout(&probe, &file.canonical, file.size, &self.config);
+        debug!("Starting {} ({} threads, {}, timeout {:?})", file.path.display(), cost.threads,
+               FileManager::format_size(cost.memory), limit);
+
+        let mut task_optimizer = TaskOptimizer::new_with_cancellation(
+            self.config.clone(),
+            self.input_base_dir.clone(),
+            self.stop_sender.subscribe(),
+        ).await?;
+        let progress_callback = progress_tracker.file_progress_callback(&self.config);
+        task_optimizer.set_progress_callback(progress_callback.clone());
+        let total_files = progress_tracker.total_files();
+
+        tokio::spawn(async move {
+            let _reservation = reservation; // Thread e memoria tornano allo scheduler quando il task finisce
+            let file_path = file.path.clone();
+
+            // Emetti evento inizio file
+            if task_optimizer.config.json_output {
+                JsonMessage::file_start(
+                    file_path.clone(),
+                    file.size,
+                    index,
+                    total_files,
+                ).emit();
+            }
+
+            let started = std::time::SystemTime::now();
+            // Lo stop scarta il future: i processi figli vengono terminati
+            // (kill_on_drop) e i file temporanei rimossi dai rispettivi guard
+            let result = tokio::select! {
+                biased;
+                _ = Self::wait_for_stop(&mut task_stop) => None,
+                result = tokio::time::timeout(limit, task_optimizer.process_file(&file)) => Some(result),
+            };
+
+            let mut applied = None;
+            let result = match result {
+                Some(Ok(result)) => result,
+                Some(Err(_)) => {
+                    let (result, policy) = Self::handle_timeout(
+                        &task_optimizer, &file, &probe, limit, &mut task_stop, progress_callback, started
+                    ).await;
+                    applied = Some(policy);
+                    result
+                }
+                None => Err(OptimizeError::Cancelled(format!("Processing of {}", file_path.display())).into()),
+            };
+            if matches!(&result, Err(e) if OptimizeError::is_cancelled(e)) {
+                Self::cleanup_cancelled(&task_optimizer, &file.canonical, started).await;
+            }
+
+            let _ = outcomes.send(FileOutcome { path: file_path, result, timeout: applied }).await;
+        });
+        Ok(())
+    }
+
+    /// Sink dei risultati: progress, journal e statistiche di ogni esito.
+    ///
+    /// Termina quando filtro, scheduler e worker hanno chiuso i loro sender.
+    async fn collect_results(
+        &self,
+        mut outcomes: mpsc::Receiver<FileOutcome>,
+        progress_tracker: &ProgressTracker,
+        journal: Option<&RunJournal>,
+    ) -> OptimizationStats {
+        let mut stats = OptimizationStats::new();
+        while let Some(FileOutcome { path, result, timeout }) = outcomes.recv().await {
+            if timeout.is_some() {
+                stats.timeouts += 1;
+            }
+            // Gestisci risultati e eventi JSON
+            progress_tracker.handle_file_completion(&self.config, &path, &result).await;
+
+            // Ottimizzati, saltati e falliti non vengono ripresi
+            let cancelled = matches!(&result, Err(e) if OptimizeError::is_cancelled(e));
+            if let Some(journal) = journal.filter(|_| !cancelled) {
+                journal.mark_done(&path).await;
+            }
+
+            match result {
                 Ok(Some(processed)) => {
-                    stats.add_optimized(processed.original_size, processed.optimized_size);
+                    stats.add_optimized_kind(
+                        FileManager::media_kind(&path),
+                        processed.original_size,
+                        processed.optimized_size,
+                    );
                 }
                 Ok(None) => {
                     stats.add_skipped(0);
                 }
+                Err(e) if cancelled => {
+                    stats.add_cancelled();
+                    debug!("{}", e);
+                }
                 Err(e) => {
                     stats.add_error();
                     error!("Failed to process file: {}", e);
                 }
             }
         }
-        
-        Ok(stats)
+        stats
+    }
+    
+    /// Attende che un file in coda entri nelle risorse libere e lo avvia.
+    async fn next_job<T>(scheduler: &Arc<Scheduler>, queue: &mut Vec<PendingJob<T>>) -> (T, Reservation) {
+        loop {
+            if let Some(next) = scheduler.try_start(queue) {
+                return next;
+            }
+            scheduler.released().await;
+        }
+    }
+    
+    /// Attende il segnale di stop.
+    ///
+    /// Un canale chiuso (nessun sender) non equivale a uno stop: in quel caso
+    /// il future resta pendente per sempre.
+    async fn wait_for_stop(receiver: &mut broadcast::Receiver<()>) {
+        match receiver.recv().await {
+            Ok(()) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+        }
     }
     
-    /// Gestisce timeout di processing
-    async fn handle_timeout(task_optimizer: &TaskOptimizer, file_path: &Path) {
-        // Se abbiamo output directory, copia file originale
-        if let Some(ref _output_dir) = task_optimizer.config.output_path {
-            if let Ok(expected_output) = task_optimizer.get_expected_output_path(file_path) {
-                if let Some(parent) = expected_output.parent() {
-                    let _ = tokio::fs::create_dir_all(parent).await;
+    /// Rimuove l'output parziale di un file cancellato (o fallito per timeout).
+    ///
+    /// Solo con directory di output, e solo se l'output è stato scritto dopo
+    /// l'avvio del file: un output preesistente di una run precedente resta.
+    async fn cleanup_cancelled(task_optimizer: &TaskOptimizer, file_path: &Path, started: std::time::SystemTime) {
+        if task_optimizer.config.output_path.is_none() {
+            return;
+        }
+        let Ok(expected_output) = task_optimizer.get_expected_output_path(file_path) else {
+            return;
+        };
+        if expected_output == file_path {
+            return;
+        }
+        // Tolleranza per filesystem con mtime a bassa risoluzione
+        let threshold = started - std::time::Duration::from_secs(2);
+        let written_now = tokio::fs::metadata(&expected_output).await
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified >= threshold);
+        if written_now {
+            match tokio::fs::remove_file(&expected_output).await {
+                Ok(()) => debug!("Removed partial output of cancelled file: {}", expected_output.display()),
+                Err(e) => warn!("Failed to remove partial output {}: {}", expected_output.display(), e),
+            }
+        }
+    }
+    
+    /// Applica `config.timeout_policy` a un file oltre il suo timeout.
+    ///
+    /// # Returns
+    /// L'esito del file e la policy applicata: `retry-faster` ripiega su
+    /// `copy-original` se il file non ha un preset più veloce o se scade
+    /// anche il nuovo tentativo.
+    async fn handle_timeout(
+        task_optimizer: &TaskOptimizer,
+        file: &DiscoveredFile,
+        probe: &MediaProbe,
+        mut limit: std::time::Duration,
+        task_stop: &mut broadcast::Receiver<()>,
+        progress_callback: ProgressCallback,
+        started: std::time::SystemTime,
+    ) -> (Result<Option<ProcessedFile>>, TimeoutPolicy) {
+        let config = &task_optimizer.config;
+        let mut policy = config.timeout_policy;
+        error!("File processing timed out after {:?}: {}", limit, file.path.display());
+
+        if policy == TimeoutPolicy::RetryFaster {
+            policy = TimeoutPolicy::CopyOriginal;
+            if let Some(faster) = timeout::faster_config(config, &file.canonical) {
+                Self::report_timeout(config, file, limit, TimeoutPolicy::RetryFaster);
+                limit = timeout::file_timeout(probe, &file.canonical, file.size, &faster);
+                warn!("Retrying {} with the fastest preset (timeout {:?})", file.path.display(), limit);
+
+                let retry = TaskOptimizer::new_with_cancellation(
+                    faster, task_optimizer.input_base_dir.clone(), task_stop.resubscribe()
+                ).await;
+                let mut retry = match retry {
+                    Ok(retry) => retry,
+                    Err(e) => {
+                        Self::cleanup_cancelled(task_optimizer, &file.canonical, started).await;
+                        return (Err(e), TimeoutPolicy::RetryFaster);
+                    }
+                };
+                retry.set_progress_callback(progress_callback);
+                let result = tokio::select! {
+                    biased;
+                    _ = Self::wait_for_stop(task_stop) => {
+                        let cancelled = OptimizeError::Cancelled(format!("Processing of {}", file.path.display()));
+                        return (Err(cancelled.into()), TimeoutPolicy::RetryFaster);
+                    }
+                    result = tokio::time::timeout(limit, retry.process_file(file)) => result,
+                };
+                match result {
+                    Ok(result) => return (result, TimeoutPolicy::RetryFaster),
+                    Err(_) => error!("Retry with the fastest preset timed out after {:?}: {}", limit, file.path.display()),
                 }
-                if let Err(e) = std::fs::copy(file_path, &expected_output) {
-                    error!("Failed to copy original file after timeout: {}", e);
-                } else {
-                    debug!("Copied original file to output after timeout: {}", expected_output.display());
+            }
+        }
+
+        Self::report_timeout(config, file, limit, policy);
+        // Con ogni policy: un output parziale con un'altra estensione (.webp,
+        // .webm, .mkv) farebbe saltare il file a `keep_processed` alla run successiva
+        Self::cleanup_cancelled(task_optimizer, &file.canonical, started).await;
+        let outcome = match policy {
+            TimeoutPolicy::CopyOriginal if config.output_path.is_some() => {
+                match Self::copy_original(task_optimizer, &file.canonical).await {
+                    Ok(()) => "original copied to output".to_string(),
+                    Err(e) => format!("copying the original failed: {}", e),
                 }
             }
+            TimeoutPolicy::CopyOriginal => "original left unchanged".to_string(),
+            _ => "no output written".to_string(),
+        };
+        let error = anyhow::anyhow!(
+            "Processing timed out after {:.0}s ({}: {})", limit.as_secs_f64(), policy.as_str(), outcome
+        );
+        (Err(error), policy)
+    }
+
+    /// Segnala la policy applicata a un timeout (evento JSON o log)
+    fn report_timeout(config: &Config, file: &DiscoveredFile, limit: std::time::Duration, policy: TimeoutPolicy) {
+        if config.json_output {
+            JsonMessage::timeout(file.path.clone(), limit, policy).emit();
+        } else {
+            warn!("Timeout policy for {}: {}", file.path.display(), policy.as_str());
         }
     }
+
+    /// Copia l'originale (con la sua estensione) nella directory di output
+    async fn copy_original(task_optimizer: &TaskOptimizer, file_path: &Path) -> Result<()> {
+        let expected_output = task_optimizer.get_passthrough_output_path(file_path)?;
+        if let Some(parent) = expected_output.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(file_path, &expected_output).await?;
+        FileManager::copy_file_attributes(file_path, &expected_output, task_optimizer.config.preserve_ownership).await?;
+        debug!("Copied original file to output after timeout: {}", expected_output.display());
+        Ok(())
+    }
     
     /// Controlla dipendenze
     async fn check_dependencies(&self) -> Result<()> {
         ImageProcessor::check_dependencies().await?;
         VideoProcessor::check_dependencies().await?;
         
-        if self.config.convert_to_webp {
-            if !ImageProcessor::check_webp_support().await {
-                return Err(anyhow::anyhow!(
-                    "cwebp is required for WebP conversion. Please install webp tools."
-                ));
-            }
+        if self.config.convert_to_webp && !ImageProcessor::check_webp_support().await {
+            return Err(anyhow::anyhow!(
+                "cwebp is required for WebP conversion. Please install webp tools."
+            ));
         }
         
         Ok(())
@@ -466,12 +785,7 @@ impl MediaOptimizer {
         
         if self.config.json_output {
             JsonMessage::complete(
-                stats.files_processed,
-                stats.files_optimized,
-                stats.files_skipped,
-                stats.errors,
-                stats.total_bytes_saved,
-                stats.overall_reduction_percent(),
+                stats,
                 duration,
                 HistoricalStats {
                     total_files_ever_processed: total_files,
@@ -485,7 +799,20 @@ impl MediaOptimizer {
             info!("Files optimized this run: {}", stats.files_optimized);
             info!("Files skipped this run: {}", stats.files_skipped);
             info!("Errors this run: {}", stats.errors);
+            if stats.timeouts > 0 {
+                info!("Timeouts this run: {} (policy: {})", stats.timeouts, self.config.timeout_policy.as_str());
+            }
+            if stats.is_interrupted() {
+                info!("Interrupted: {} cancelled, {} not started (run again to resume)",
+                      stats.files_cancelled, stats.files_remaining);
+            }
             info!("Bytes saved this run: {}", FileManager::format_size(stats.total_bytes_saved));
+            for (label, kind_stats) in [("Images", &stats.images), ("Videos", &stats.videos), ("Audio", &stats.audio)] {
+                if kind_stats.files_optimized > 0 {
+                    info!("  • {}: {} files, {} saved", label, kind_stats.files_optimized,
+                          FileManager::format_size(kind_stats.total_bytes_saved));
+                }
+            }
             info!("Average reduction this run: {:.2}%", stats.overall_reduction_percent());
             info!("--- Historical Stats ---");
             info!("Total files ever processed: {}", total_files);
@@ -496,245 +823,170 @@ impl MediaOptimizer {
         Ok(())
     }
     
-    /// Crea thumbnails per tutte le immagini elaborate
-    async fn create_thumbnails_for_all_images(&self) -> Result<()> {
-        if self.config.thumbnails.is_empty() || self.config.output_path.is_none() {
-            return Ok(());
-        }
-
-        info!("🖼️ Creating thumbnails for processed images...");
-        
-        let output_dir = self.config.output_path.as_ref().unwrap();
-        
-        // Trova solo le immagini nella directory principale di output (non nelle sottocartelle thumbnail)
-        let output_images = FileManager::find_media_files(output_dir)?
-            .into_iter()
-            .filter(|path| {
-                // Verifica che sia supportato per il resize
-                if !ImageResizer::is_supported_for_resize(path) {
-                    return false;
-                }
-                
-                // Verifica che NON sia in una cartella di thumbnails
-                // Controlla se il path contiene "thumbnails" in qualsiasi parte del percorso relativo
-                if let Ok(rel_path) = path.strip_prefix(output_dir) {
-                    let path_str = rel_path.to_string_lossy();
-                    // Esclude qualsiasi file che abbia "thumbnails" nel path
-                    if path_str.contains("thumbnails") {
-                        debug!("Skipping thumbnail file for input: {}", path.display());
-                        return false;
-                    }
-                }
-                
-                true
-            })
-            .collect::<Vec<_>>();
-
-        if output_images.is_empty() {
-            info!("No images found for thumbnail creation");
-            return Ok(());
-        }
-
-        info!("Found {} images for thumbnail creation", output_images.len());
-
-        // Crea il resizer con qualità molto alta per minimizzare degrado da doppia compressione
-        let resizer = ImageResizer::new(
-            self.config.clone(),
-            ResizeAlgorithm::Lanczos,
-            ResizeMode::Fit,
-            Some(95), // Qualità molto alta per minimizzare degrado da doppia compressione JPEG
-            true, // Strip metadata for smaller thumbnails
-        )?;
-
-        let estimated_thumbnails = resizer.estimate_thumbnail_count(&output_images);
-        info!("Will create {} thumbnails total", estimated_thumbnails);
-
-        // Processo i thumbnails con concorrenza controllata per file piccoli (thumbnails sono sempre piccoli)
-        let semaphore = Arc::new(Semaphore::new(self.config.workers.min(4))); // Limite per i thumbnails
-        let mut tasks: Vec<tokio::task::JoinHandle<Result<usize, anyhow::Error>>> = Vec::new();
-
-        for image_path in output_images {
-            let permit = semaphore.clone().acquire_owned().await?;
-            let mut resizer_clone = ImageResizer::new(
-                self.config.clone(),
-                ResizeAlgorithm::Lanczos,
-                ResizeMode::Fit,
-                Some(95), // Qualità molto alta per minimizzare degrado da doppia compressione JPEG
-                true, // Strip metadata for smaller thumbnails
-            )?;
-            let output_base = output_dir.clone();
-
-            let task = tokio::spawn(async move {
-                let _permit = permit;
-                
-                match resizer_clone.create_thumbnails(&image_path, &output_base).await {
-                    Ok(thumbnail_paths) => {
-                        if !thumbnail_paths.is_empty() {
-                            info!("Created {} thumbnails for {}", 
-                                  thumbnail_paths.len(),
-                                  image_path.file_name().unwrap_or_default().to_string_lossy());
-                        }
-                        Ok(thumbnail_paths.len())
-                    }
-                    Err(e) => {
-                        error!("Failed to create thumbnails for {}: {}", image_path.display(), e);
-                        Ok(0)
-                    }
-                }
-            });
-
-            tasks.push(task);
-        }
-
-        // Aspetta tutti i task
-        let mut total_created = 0;
-        for task in tasks {
-            match task.await? {
-                Ok(count) => total_created += count,
-                Err(_) => {} // Errore già loggato
-            }
-        }
-
-        info!("✅ Thumbnail creation complete: {} thumbnails created", total_created);
-        Ok(())
+    /// Originale da cui creare anteprime: immagini supportate per il resize
+    /// (se ci sono thumbnails) e video (poster, anteprime e storyboard)
+    fn is_preview_source(&self, path: &Path) -> bool {
+        (!self.config.thumbnails.is_empty() && ImageResizer::is_supported_for_resize(path))
+            || VideoThumbnailer::is_supported(path)
     }
-    
-    /// Crea thumbnails dalle immagini originali (prima dell'ottimizzazione)
-    /// Questa strategia preserva la massima qualità dei thumbnails
-    async fn create_thumbnails_from_originals(&self, files: &[PathBuf]) -> Result<()> {
-        if self.config.thumbnails.is_empty() || self.config.output_path.is_none() {
-            return Ok(());
-        }
 
-        let output_dir = self.config.output_path.as_ref().unwrap();
-        
-        // Calcola la directory base comune dai file originali
-        let base_dir = if let Some(first_file) = files.first() {
-            // Usa la directory del primo file come base, oppure trova il prefisso comune
-            self.find_common_base_dir(files).unwrap_or_else(|| {
-                first_file.parent().unwrap_or(Path::new(".")).to_path_buf()
-            })
-        } else {
+    /// Stadio delle anteprime: thumbnails dalle immagini originali e
+    /// poster/anteprime/storyboard dai video originali.
+    /// Questa strategia preserva la massima qualità dei thumbnails
+    ///
+    /// Gira in parallelo all'ottimizzazione, con concorrenza propria, sui
+    /// file inviati dal filtro; i path sono relativi alla directory media,
+    /// come quelli degli output ottimizzati. Termina quando il filtro chiude
+    /// il canale o con lo stop.
+    async fn create_previews(&self, paths: Option<mpsc::Receiver<PathBuf>>) -> Result<()> {
+        let Some(mut paths) = paths else {
             return Ok(());
         };
-        
-        // Filtra solo le immagini supportate per il resize
-        let image_files: Vec<_> = files
-            .iter()
-            .filter(|path| ImageResizer::is_supported_for_resize(path))
-            .collect();
-
-        if image_files.is_empty() {
-            info!("No images found for thumbnail creation from originals");
-            return Ok(());
-        }
-
-        info!("Found {} original images for thumbnail creation", image_files.len());
-
-        // Crea il resizer con qualità massima (dalle originali)
-        let resizer = ImageResizer::new(
-            self.config.clone(),
-            ResizeAlgorithm::Lanczos, // Migliore qualità per le originali
-            ResizeMode::Fit,
-            Some(95), // Qualità alta per preservare dettagli dalle originali
-            true, // Strip metadata for smaller thumbnails
-        )?;
-
-        let estimated_thumbnails = resizer.estimate_thumbnail_count(&image_files.iter().map(|p| (*p).clone()).collect::<Vec<_>>());
-        info!("Will create {} thumbnails total from originals", estimated_thumbnails);
+        info!("🖼️ Creating thumbnails from original images and videos...");
 
         // Processo i thumbnails con concorrenza controllata per file piccoli (thumbnails sono sempre piccoli)
         let semaphore = Arc::new(Semaphore::new(self.config.workers.min(4))); // Limite per i thumbnails
-        let mut tasks: Vec<tokio::task::JoinHandle<Result<usize, anyhow::Error>>> = Vec::new();
-
-        for image_path in image_files {
-            let permit = semaphore.clone().acquire_owned().await?;
-            let mut resizer_clone = ImageResizer::new(
-                self.config.clone(),
-                ResizeAlgorithm::Lanczos, // Migliore qualità per le originali
-                ResizeMode::Fit,
-                Some(95), // Qualità alta per preservare dettagli dalle originali
-                true, // Strip metadata for smaller thumbnails
-            )?;
-            let output_base = output_dir.clone();
-            let media_base = base_dir.clone();
-            let image_path = image_path.clone();
-
-            let task = tokio::spawn(async move {
-                let _permit = permit;
-                
-                match resizer_clone.create_thumbnails(&image_path, &media_base).await {
-                    Ok(thumbnail_paths) => {
-                        if !thumbnail_paths.is_empty() {
-                            info!("Created {} thumbnails from original {}", 
-                                  thumbnail_paths.len(),
-                                  image_path.file_name().unwrap_or_default().to_string_lossy());
+        let mut tasks: JoinSet<usize> = JoinSet::new();
+        let mut total_created = 0;
+        let (mut image_count, mut video_count) = (0, 0);
+
+        let mut drain_receiver = self.drain_sender.subscribe();
+        while let Some(path) = paths.recv().await {
+            let permit = tokio::select! {
+                biased;
+                _ = Self::wait_for_stop(&mut drain_receiver) => {
+                    warn!("Stop requested: no more thumbnails will be created");
+                    break;
+                }
+                permit = semaphore.clone().acquire_owned() => permit?,
+            };
+            // Raccoglie i task finiti: la loro lista resta limitata
+            while let Some(result) = tasks.try_join_next() {
+                total_created += Self::thumbnail_task_count(result);
+            }
+            let media_base = self.input_base_dir.clone();
+
+            // I video producono un poster (e un'anteprima) per ogni thumbnail
+            // e uno sprite con indice WebVTT per ogni storyboard
+            if !VideoThumbnailer::is_supported(&path) {
+                image_count += 1;
+                let mut resizer = ImageResizer::new_with_cancellation(
+                    self.config.clone(),
+                    ResizeAlgorithm::Lanczos, // Migliore qualità per le originali
+                    ResizeMode::Fit,
+                    Some(95), // Qualità alta per preservare dettagli dalle originali
+                    true, // Strip metadata for smaller thumbnails
+                    self.stop_sender.subscribe(),
+                )?;
+                tasks.spawn(async move {
+                    let _permit = permit;
+                    
+                    match resizer.create_thumbnails(&path, &media_base).await {
+                        Ok(thumbnail_paths) => {
+                            if !thumbnail_paths.is_empty() {
+                                info!("Created {} thumbnails from original {}", 
+                                      thumbnail_paths.len(),
+                                      path.file_name().unwrap_or_default().to_string_lossy());
+                            }
+                            thumbnail_paths.len()
+                        }
+                        Err(e) => {
+                            error!("Failed to create thumbnails from original {}: {}", path.display(), e);
+                            0
                         }
-                        Ok(thumbnail_paths.len())
                     }
-                    Err(e) => {
-                        error!("Failed to create thumbnails from original {}: {}", image_path.display(), e);
-                        Ok(0)
+                });
+            } else {
+                video_count += 1;
+                let mut thumbnailer = VideoThumbnailer::new_with_cancellation(self.config.clone(), self.stop_sender.subscribe());
+                let mut storyboards = VideoProcessor::new_with_cancellation(self.config.clone(), self.stop_sender.subscribe());
+                tasks.spawn(async move {
+                    let _permit = permit;
+
+                    let mut created = 0;
+                    match thumbnailer.create_previews(&path, &media_base).await {
+                        Ok(preview_paths) => created += preview_paths.len(),
+                        Err(e) => error!("Failed to create video previews from {}: {}", path.display(), e),
                     }
-                }
-            });
-
-            tasks.push(task);
+                    match storyboards.create_storyboards(&path, &media_base).await {
+                        Ok(storyboard_paths) => created += storyboard_paths.len(),
+                        Err(e) => error!("Failed to create storyboards from {}: {}", path.display(), e),
+                    }
+                    created
+                });
+            }
         }
+        // Il filtro smette di inviare anteprime quando il canale è chiuso
+        drop(paths);
 
         // Aspetta tutti i task
-        let mut total_created = 0;
-        for task in tasks {
-            match task.await {
-                Ok(Ok(count)) => total_created += count,
-                Ok(Err(e)) => error!("Thumbnail creation task failed: {}", e),
-                Err(e) => error!("Thumbnail creation task panicked: {}", e),
-            }
+        while let Some(result) = tasks.join_next().await {
+            total_created += Self::thumbnail_task_count(result);
         }
 
-        if total_created > 0 {
-            info!("✅ Created {} thumbnails from original images", total_created);
+        if image_count == 0 && video_count == 0 {
+            info!("No images or videos found for thumbnail creation from originals");
+        } else if total_created > 0 {
+            info!("✅ Created {} thumbnails from {} original images and {} videos", total_created, image_count, video_count);
         } else {
-            warn!("No thumbnails were created from original images");
+            warn!("No thumbnails were created from original images and videos");
         }
         
         Ok(())
     }
 
-    /// Trova la directory base comune per un set di file
-    fn find_common_base_dir(&self, files: &[PathBuf]) -> Option<PathBuf> {
-        if files.is_empty() {
-            return None;
-        }
-
-        // Inizia con la directory del primo file
-        let mut common_path = files[0].parent()?.to_path_buf();
-
-        // Per ogni file successivo, trova il prefisso comune
-        for file in files.iter().skip(1) {
-            if let Some(file_parent) = file.parent() {
-                // Trova il prefisso comune tra common_path e file_parent
-                let mut new_common = PathBuf::new();
-                let common_components: Vec<_> = common_path.components().collect();
-                let file_components: Vec<_> = file_parent.components().collect();
-
-                for (c1, c2) in common_components.iter().zip(file_components.iter()) {
-                    if c1 == c2 {
-                        new_common.push(c1);
-                    } else {
-                        break;
-                    }
-                }
+    /// Thumbnail creati da un task dello stadio delle anteprime
+    fn thumbnail_task_count(result: std::result::Result<usize, tokio::task::JoinError>) -> usize {
+        result.unwrap_or_else(|e| {
+            error!("Thumbnail creation task panicked: {}", e);
+            0
+        })
+    }
+}
 
-                common_path = new_common;
-                
-                // Se non c'è più un prefisso comune, usa la root
-                if common_path.as_os_str().is_empty() {
-                    return Some(PathBuf::from("."));
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_probe_stage_accounts_for_every_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let optimizer = MediaOptimizer::new(temp_dir.path(), Config::default()).await.unwrap();
+        let files = |count: usize| {
+            let (sender, receiver) = mpsc::channel(LOOKAHEAD);
+            for index in 0..count {
+                let path = temp_dir.path().join(format!("{}.jpg", index));
+                let file = DiscoveredFile { path: path.clone(), canonical: path, size: 1000, modified_time: 0 };
+                sender.try_send(file).unwrap();
             }
-        }
+            receiver
+        };
 
-        Some(common_path)
+        // Every file reaches the scheduler
+        let (job_sender, mut job_receiver) = mpsc::channel(LOOKAHEAD);
+        assert_eq!(optimizer.probe_files(files(10), job_sender).await, 0);
+        let mut probed = 0;
+        while job_receiver.recv().await.is_some() {
+            probed += 1;
+        }
+        assert_eq!(probed, 10);
+
+        // The scheduler stops after three files: the rest is counted once,
+        // either by the scheduler (already probed) or by the probe stage
+        let (job_sender, mut job_receiver) = mpsc::channel(1);
+        let scheduler = async {
+            for _ in 0..3 {
+                job_receiver.recv().await.unwrap();
+            }
+            job_receiver.close();
+            let mut queued = 0;
+            while job_receiver.try_recv().is_ok() {
+                queued += 1;
+            }
+            queued
+        };
+        let (not_probed, queued) = tokio::join!(optimizer.probe_files(files(10), job_sender), scheduler);
+        assert_eq!(not_probed + queued, 7);
     }
 }
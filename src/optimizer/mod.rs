@@ -5,11 +5,17 @@
 //! - `task_optimizer`: Worker per singoli file
 //! - `progress_tracker`: Gestione progress unificata
 //! - `path_resolver`: Logica di calcolo path centralizzata
+//! - `scheduler`: Avvio dei file in base al costo stimato in thread CPU e memoria
+//! - `discovery`: Scansione in streaming e filtro dei file già elaborati
+//! - `timeout`: Timeout di ogni file stimato dal contenuto
 
 pub mod media_optimizer;
 pub mod task_optimizer;
 pub mod progress_tracker;
 pub mod path_resolver;
+pub mod scheduler;
+pub mod discovery;
+pub mod timeout;
 
 // Re-export delle struct principali per backward compatibility
 pub use media_optimizer::MediaOptimizer;
@@ -3,7 +3,11 @@
 //! Centralizza tutta la logica di calcolo dei path di output.
 //! Evita duplicazione tra ImageProcessor e TaskOptimizer.
 
-use crate::{config::Config, file_manager::FileManager};
+use crate::{
+    audio_processor::AudioProcessor,
+    config::Config,
+    file_manager::{FileManager, MediaKind},
+};
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 use tracing::debug;
@@ -17,14 +21,54 @@ impl PathResolver {
         input_path: &Path, 
         input_base_dir: &Path, 
         config: &Config
+    ) -> Result<PathBuf> {
+        // Determina l'estensione di output
+        let extension = Self::get_output_extension(input_path, config);
+        Self::get_output_path_with_extension(input_path, input_base_dir, config, &extension)
+    }
+    
+    /// Path di output per una copia dell'originale (stessa estensione): usato
+    /// quando l'ottimizzazione viene scartata o il file non va convertito
+    pub fn get_passthrough_path(
+        input_path: &Path, 
+        input_base_dir: &Path, 
+        config: &Config
+    ) -> Result<PathBuf> {
+        let extension = input_path.extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self::get_output_path_with_extension(input_path, input_base_dir, config, &extension)
+    }
+    
+    /// Directory del bundle di streaming (`talk.hls` / `talk.dash`) accanto
+    /// all'output del video; None se lo streaming non è configurato
+    pub fn get_streaming_bundle_dir(
+        input_path: &Path, 
+        input_base_dir: &Path, 
+        config: &Config
+    ) -> Result<Option<PathBuf>> {
+        match config.streaming {
+            Some(format) => Self::get_output_path_with_extension(input_path, input_base_dir, config, format.bundle_extension()).map(Some),
+            None => Ok(None),
+        }
+    }
+    
+    /// Path di output con un'estensione esplicita (es. decisa dopo l'analisi del file)
+    pub fn get_output_path_with_extension(
+        input_path: &Path, 
+        input_base_dir: &Path, 
+        config: &Config,
+        extension: &str,
     ) -> Result<PathBuf> {
         let file_stem = input_path.file_stem()
             .ok_or_else(|| anyhow::anyhow!("Invalid file name: {}", input_path.display()))?
             .to_string_lossy();
         
-        // Determina l'estensione di output
-        let extension = Self::get_output_extension(input_path, config);
-        let filename = format!("{}.{}", file_stem, extension);
+        let filename = if extension.is_empty() {
+            file_stem.to_string()
+        } else {
+            format!("{}.{}", file_stem, extension)
+        };
         
         if let Some(ref output_dir) = config.output_path {
             // Modalità output directory
@@ -35,10 +79,30 @@ impl PathResolver {
         }
     }
     
-    /// Determina l'estensione di output basata sul tipo file e config
+    /// Determina l'estensione di output basata sul tipo file e config.
+    ///
+    /// I video prendono il container del codec scelto (mp4 o webm); con
+    /// `skip_video_compression` il file viene copiato e mantiene l'estensione.
+    /// I file audio prendono l'estensione di `config.audio_format`, tranne le
+    /// sorgenti lossy con formato lossless (non vengono convertite).
     fn get_output_extension(input_path: &Path, config: &Config) -> String {
-        if FileManager::is_video(input_path) {
-            "mp4".to_string()
+        let kind = FileManager::media_kind(input_path);
+        if kind == Some(MediaKind::Video) && !config.skip_video_compression {
+            config.video_codec.container_extension().to_string()
+        } else if kind == Some(MediaKind::Video) {
+            input_path.extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("mp4")
+                .to_string()
+        } else if kind == Some(MediaKind::Audio) {
+            if AudioProcessor::converts_extension(input_path, config.audio_format) {
+                config.audio_format.extension().to_string()
+            } else {
+                input_path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or_default()
+                    .to_string()
+            }
         } else if config.convert_to_webp {
             "webp".to_string()
         } else {
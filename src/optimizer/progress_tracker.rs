@@ -5,10 +5,12 @@
 
 use crate::{
     config::Config,
+    error::OptimizeError,
     json_output::JsonMessage,
-    progress::{OptimizationStats, ProgressManager},
+    progress::{FileProgress, OptimizationStats, ProgressCallback, ProgressManager},
     state::ProcessedFile,
 };
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::Mutex;
@@ -16,29 +18,51 @@ use tokio::sync::Mutex;
 /// Tracker progress unificato che sostituisce GlobalProgress + ProgressManager
 #[derive(Clone)]
 pub struct ProgressTracker {
-    pub total_files: usize,
+    /// File scoperti finora dalla scansione
+    total_files: Arc<AtomicUsize>,
+    scan_complete: Arc<AtomicBool>,
     current_file: Arc<Mutex<usize>>,
     files_optimized: Arc<Mutex<usize>>,
     files_skipped: Arc<Mutex<usize>>,
     errors: Arc<Mutex<usize>>,
+    files_cancelled: Arc<Mutex<usize>>,
     bytes_saved: Arc<Mutex<u64>>,
     progress_manager: ProgressManager,
 }
 
 impl ProgressTracker {
-    /// Crea un nuovo tracker
-    pub fn new(total_files: usize) -> Self {
+    /// Crea un nuovo tracker; il totale cresce con [`Self::add_discovered`]
+    pub fn new() -> Self {
         Self {
-            total_files,
+            total_files: Arc::new(AtomicUsize::new(0)),
+            scan_complete: Arc::new(AtomicBool::new(false)),
             current_file: Arc::new(Mutex::new(0)),
             files_optimized: Arc::new(Mutex::new(0)),
             files_skipped: Arc::new(Mutex::new(0)),
             errors: Arc::new(Mutex::new(0)),
+            files_cancelled: Arc::new(Mutex::new(0)),
             bytes_saved: Arc::new(Mutex::new(0)),
-            progress_manager: ProgressManager::new(total_files as u64),
+            progress_manager: ProgressManager::new(0),
         }
     }
     
+    /// File scoperti finora
+    pub fn total_files(&self) -> usize {
+        self.total_files.load(Ordering::Relaxed)
+    }
+    
+    /// Aggiunge un file scoperto dalla scansione al totale
+    pub fn add_discovered(&self) {
+        self.total_files.fetch_add(1, Ordering::Relaxed);
+        self.progress_manager.inc_length(1);
+    }
+    
+    /// Segna la fine della scansione: il totale è definitivo
+    pub async fn complete_scan(&self, config: &Config) {
+        self.scan_complete.store(true, Ordering::Relaxed);
+        self.emit_progress(config).await;
+    }
+    
     /// Aggiorna progress e invia eventi JSON se necessario
     pub async fn emit_progress(&self, config: &Config) {
         if config.json_output {
@@ -50,7 +74,8 @@ impl ProgressTracker {
             
             JsonMessage::progress(
                 current,
-                self.total_files,
+                self.total_files(),
+                self.scan_complete.load(Ordering::Relaxed),
                 optimized,
                 skipped,
                 errors,
@@ -64,6 +89,38 @@ impl ProgressTracker {
         self.progress_manager.update(message);
     }
     
+    /// Callback per l'avanzamento dei singoli file: aggiorna il messaggio
+    /// della progress bar (senza incrementarla) o emette `file_progress` in JSON
+    pub fn file_progress_callback(&self, config: &Config) -> ProgressCallback {
+        let progress_manager = self.progress_manager.clone();
+        let json_output = config.json_output;
+        
+        Arc::new(move |progress: &FileProgress| {
+            if json_output {
+                JsonMessage::file_progress(progress).emit();
+            } else {
+                progress_manager.set_message(&Self::format_file_progress(progress));
+            }
+        })
+    }
+    
+    /// Messaggio della progress bar, es. "[42%] clip.mov 2.1x ETA 1:05"
+    fn format_file_progress(progress: &FileProgress) -> String {
+        let name = progress.path.file_name().unwrap_or_default().to_string_lossy();
+        let mut message = match progress.percent {
+            Some(percent) => format!("[{:.0}%] {}", percent, name),
+            None => format!("[{:.0}s] {}", progress.processed_seconds, name),
+        };
+        if let Some(speed) = progress.speed {
+            message.push_str(&format!(" {:.1}x", speed));
+        }
+        if let Some(eta) = progress.eta_seconds {
+            let eta = eta as u64;
+            message.push_str(&format!(" ETA {}:{:02}", eta / 60, eta % 60));
+        }
+        message
+    }
+    
     /// Finalizza progress bar
     pub fn finish(&self, summary: &str) {
         self.progress_manager.finish(summary);
@@ -95,6 +152,12 @@ impl ProgressTracker {
         *errors += 1;
     }
     
+    /// Aggiunge file cancellato
+    pub async fn add_cancelled(&self) {
+        let mut cancelled = self.files_cancelled.lock().await;
+        *cancelled += 1;
+    }
+    
     /// Gestisce completamento file con eventi JSON automatici
     pub async fn handle_file_completion(
         &self,
@@ -163,6 +226,19 @@ impl ProgressTracker {
                                file_path.file_name().unwrap_or_default().to_string_lossy());
                 self.update_message(&message);
             }
+            Err(e) if OptimizeError::is_cancelled(e) => {
+                self.increment_current().await;
+                self.add_cancelled().await;
+                
+                if config.json_output {
+                    JsonMessage::cancelled(file_path.to_path_buf()).emit();
+                    self.emit_progress(config).await;
+                }
+                
+                let message = format!("[CANCELLED] {}", 
+                               file_path.file_name().unwrap_or_default().to_string_lossy());
+                self.update_message(&message);
+            }
             Err(e) => {
                 self.increment_current().await;
                 self.add_error().await;
@@ -202,6 +278,7 @@ impl ProgressTracker {
         let optimized = *self.files_optimized.lock().await;
         let skipped = *self.files_skipped.lock().await;
         let errors = *self.errors.lock().await;
+        let cancelled = *self.files_cancelled.lock().await;
         let saved = *self.bytes_saved.lock().await;
         
         // Popola le stats con valori reali
@@ -209,8 +286,15 @@ impl ProgressTracker {
         stats.files_optimized = optimized;
         stats.files_skipped = skipped;
         stats.errors = errors;
+        stats.files_cancelled = cancelled;
         stats.total_bytes_saved = saved;
         
         stats
     }
 }
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,435 @@
+//! # Resource Scheduler
+//!
+//! Avvio dei file in base al loro costo stimato in thread CPU e memoria,
+//! invece che per classi di dimensione fisse.
+//!
+//! ## Responsabilità:
+//! - Misura le risorse della macchina: core e memoria disponibile ([`ResourceBudget`])
+//! - Stima il costo di ogni file dai pixel e dalla durata ([`JobCost`]),
+//!   leggendo l'header delle immagini (`image_probe`) e i video con ffprobe
+//!   ([`MediaProbe`], letto una volta sola anche per il timeout)
+//! - Riserva il costo finché il file è in elaborazione ([`Reservation`], RAII)
+//! - Sceglie il prossimo file da avviare tra quelli in coda ([`Scheduler::try_start`])
+//!
+//! ## Costo stimato:
+//! | File               | Thread                             | Memoria                         |
+//! |--------------------|------------------------------------|---------------------------------|
+//! | JPEG               | 1                                  | 12 byte/pixel                   |
+//! | PNG (oxipng)       | 4 (prove dei filtri in parallelo)  | 24 byte/pixel                   |
+//! | WebP (`cwebp -mt`) | 2                                  | 16 byte/pixel                   |
+//! | Video              | 1 ogni 250k pixel/frame (2-16)     | ~100 frame YUV in volo × codec  |
+//! | Audio              | 1                                  | 96 MB                           |
+//!
+//! Gli encoder ffmpeg usano di default tutti i core che la risoluzione
+//! permette: un video 4K occupa da solo una macchina a 16 thread, un 720p
+//! ne lascia liberi per le immagini. Un costo superiore all'intero budget
+//! viene ridotto al budget: il file parte da solo, mai in parallelo a un altro
+//! che lo farebbe andare in OOM.
+//!
+//! ## Scelta del prossimo file:
+//! Tra i primi [`LOOKAHEAD`] file in coda parte quello con più lavoro
+//! (pixel × frame) che entra nelle risorse libere: i lavori lunghi partono
+//! presto e quelli piccoli riempiono i thread rimasti. Il primo file in coda
+//! può essere scavalcato al massimo [`MAX_BYPASS`] volte, poi lo scheduler
+//! aspetta che si liberino abbastanza risorse per lui.
+
+use crate::config::{Config, VideoCodec};
+use crate::ffmpeg;
+use crate::file_manager::{FileManager, MediaKind};
+use crate::image_probe::ImageProbe;
+use crate::platform::PlatformCommands;
+use crate::utils::parse_file_size;
+use crate::video_processor::VideoInfo;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::debug;
+
+/// File in coda considerati per ogni avvio
+pub const LOOKAHEAD: usize = 32;
+
+/// Volte in cui il primo file in coda può essere scavalcato
+pub const MAX_BYPASS: u32 = 16;
+
+/// Quota della memoria disponibile usata come budget (il resto resta al sistema)
+const MEMORY_SHARE: f64 = 0.75;
+
+/// Budget di memoria quando il sistema non la riporta
+const FALLBACK_MEMORY: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Memoria di base di un processo esterno (eseguibile, librerie, buffer di I/O)
+const PROCESS_MEMORY: u64 = 32 * 1024 * 1024;
+
+/// Memoria di base di ffmpeg (demuxer, decoder, buffer dei pacchetti)
+const FFMPEG_MEMORY: u64 = 192 * 1024 * 1024;
+
+/// Frame in volo in un encoder video (lookahead, riferimenti, frame thread)
+const VIDEO_FRAMES_IN_FLIGHT: f64 = 100.0;
+
+/// Pixel per frame gestiti da un thread dell'encoder video
+const VIDEO_PIXELS_PER_THREAD: u64 = 250_000;
+
+/// Risorse della macchina a disposizione dei file in elaborazione
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceBudget {
+    /// Thread CPU
+    pub threads: u32,
+    /// Memoria in byte
+    pub memory: u64,
+    /// File in parallelo (`config.workers`)
+    pub jobs: usize,
+}
+
+impl ResourceBudget {
+    /// Misura la macchina: `max_threads` e `max_memory` sostituiscono le misure
+    pub fn detect(config: &Config) -> Self {
+        let threads = config.max_threads.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(4, |cores| cores.get())
+        });
+        let memory = config.max_memory.as_deref()
+            .and_then(parse_file_size)
+            .unwrap_or_else(|| {
+                PlatformCommands::available_memory()
+                    .map_or(FALLBACK_MEMORY, |available| (available as f64 * MEMORY_SHARE) as u64)
+            });
+        Self { threads: threads.max(1) as u32, memory, jobs: config.workers.max(1) }
+    }
+}
+
+/// Risorse stimate per l'elaborazione di un file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobCost {
+    /// Thread CPU occupati
+    pub threads: u32,
+    /// Memoria di picco in byte
+    pub memory: u64,
+    /// Lavoro relativo (pixel × frame), per scegliere l'ordine di avvio
+    pub work: f64,
+}
+
+/// Contenuto di un file letto prima dell'avvio, per stimarne costo e timeout
+#[derive(Debug, Clone)]
+pub enum MediaProbe {
+    /// Pixel dell'immagine (dall'header, o stimati dalla dimensione del file)
+    Image { pixels: u64 },
+    /// Video letto da ffprobe, o ricavato dalla dimensione ([`assumed_video`])
+    Video(Box<VideoInfo>),
+    /// File audio (non analizzato: stima dalla dimensione)
+    Audio,
+}
+
+impl MediaProbe {
+    /// Legge il contenuto di un file: header delle immagini, ffprobe per i video.
+    ///
+    /// Se la lettura fallisce il contenuto viene ricavato dalla dimensione.
+    pub async fn read(path: &Path, size: u64) -> Self {
+        match FileManager::media_kind(path) {
+            Some(MediaKind::Video) => {
+                let info = match ffmpeg::probe(path).await {
+                    Ok(json) => VideoInfo::from_ffprobe_json(&json).ok(),
+                    Err(e) => {
+                        debug!("Could not probe {} for scheduling: {}", path.display(), e);
+                        None
+                    }
+                };
+                match info {
+                    Some(info) if info.width > 0 && info.height > 0 => Self::Video(Box::new(info)),
+                    _ => Self::Video(Box::new(assumed_video(size))),
+                }
+            }
+            Some(MediaKind::Audio) => Self::Audio,
+            _ => {
+                let pixels = ImageProbe::probe(path).await
+                    .map(|header| header.pixel_count())
+                    // JPEG/WebP comprimono circa 1:8 rispetto ai pixel RGB
+                    .unwrap_or(size.saturating_mul(8) / 3);
+                Self::Image { pixels }
+            }
+        }
+    }
+}
+
+/// Video non analizzabile: 1080p30 a 10 Mbit/s
+pub fn assumed_video(size: u64) -> VideoInfo {
+    VideoInfo {
+        duration: size as f64 * 8.0 / 10_000_000.0,
+        bitrate: 10_000_000,
+        width: 1920,
+        height: 1080,
+        codec: "unknown".to_string(),
+        audio_codec: None,
+        audio_channels: None,
+        frame_rate: 30.0,
+        rotation: 0,
+        color: Default::default(),
+        streams: Vec::new(),
+        chapters: 0,
+    }
+}
+
+impl JobCost {
+    /// Costo di un file dal contenuto già letto
+    pub fn for_probe(probe: &MediaProbe, path: &Path, size: u64, config: &Config) -> Self {
+        match probe {
+            MediaProbe::Image { pixels } => Self::image(path, *pixels, config),
+            MediaProbe::Video(info) => Self::video(info, config),
+            MediaProbe::Audio => Self::audio(size),
+        }
+    }
+
+    /// Costo di un'immagine secondo il tool che la elabora
+    pub fn image(path: &Path, pixels: u64, config: &Config) -> Self {
+        let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let (threads, bytes_per_pixel) = match extension.as_str() {
+            _ if config.convert_to_webp => (2, 16),
+            "webp" => (2, 16),
+            "png" => (4, 24),
+            _ => (1, 12),
+        };
+        Self {
+            threads,
+            memory: PROCESS_MEMORY + pixels * bytes_per_pixel,
+            work: pixels as f64,
+        }
+    }
+
+    /// Costo di un video: thread e frame in volo crescono con la risoluzione
+    pub fn video(info: &VideoInfo, config: &Config) -> Self {
+        let pixels = info.width as u64 * info.height as u64;
+        let frames = info.frame_rate.max(1.0) * info.duration.max(1.0);
+        if config.skip_video_compression || config.remux_videos {
+            // Copia o stream copy: solo I/O
+            return Self { threads: 1, memory: FFMPEG_MEMORY, work: frames };
+        }
+
+        let codec_factor = match config.video_codec {
+            VideoCodec::H264 => 1.0,
+            VideoCodec::Vp9 => 1.2,
+            VideoCodec::Hevc => 1.5,
+            VideoCodec::Av1 => 2.0,
+        };
+        // La ladder di streaming codifica tutte le rendition insieme
+        let renditions = if config.streaming.is_some() { config.streaming_ladder.len().max(1) as f64 } else { 1.0 };
+        let frame_bytes = pixels as f64 * 1.5;
+        let memory = FFMPEG_MEMORY + (frame_bytes * VIDEO_FRAMES_IN_FLIGHT * codec_factor * renditions) as u64;
+        Self {
+            threads: (pixels / VIDEO_PIXELS_PER_THREAD).clamp(2, 16) as u32,
+            memory,
+            work: pixels as f64 * frames * renditions,
+        }
+    }
+
+    /// Costo di un file audio: un processo ffmpeg a thread singolo
+    pub fn audio(size: u64) -> Self {
+        Self { threads: 1, memory: 96 * 1024 * 1024, work: size as f64 }
+    }
+
+    /// Riduce il costo al budget: un file più grande della macchina parte da solo
+    fn clamp(self, budget: &ResourceBudget) -> Self {
+        Self {
+            threads: self.threads.min(budget.threads),
+            memory: self.memory.min(budget.memory),
+            work: self.work,
+        }
+    }
+}
+
+/// File in attesa di essere avviato
+#[derive(Debug)]
+pub struct PendingJob<T> {
+    pub item: T,
+    pub cost: JobCost,
+    /// Volte in cui è stato scavalcato da un file successivo
+    bypassed: u32,
+}
+
+impl<T> PendingJob<T> {
+    pub fn new(item: T, cost: JobCost) -> Self {
+        Self { item, cost, bypassed: 0 }
+    }
+}
+
+/// Risorse riservate dai file in elaborazione
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Usage {
+    threads: u32,
+    memory: u64,
+    jobs: usize,
+}
+
+impl Usage {
+    fn fits(&self, cost: &JobCost, budget: &ResourceBudget) -> bool {
+        self.jobs < budget.jobs
+            && self.threads + cost.threads <= budget.threads
+            && self.memory + cost.memory <= budget.memory
+    }
+}
+
+/// Scheduler dei file: riserva thread e memoria fino alla fine di ogni file
+#[derive(Debug)]
+pub struct Scheduler {
+    budget: ResourceBudget,
+    usage: Mutex<Usage>,
+    /// Segnala il rilascio di una prenotazione (un solo consumatore: il ciclo di scheduling)
+    released: Notify,
+}
+
+impl Scheduler {
+    pub fn new(budget: ResourceBudget) -> Arc<Self> {
+        Arc::new(Self { budget, usage: Mutex::new(Usage::default()), released: Notify::new() })
+    }
+
+    pub fn budget(&self) -> ResourceBudget {
+        self.budget
+    }
+
+    /// Avvia, se le risorse libere lo permettono, il file scelto tra quelli in coda.
+    ///
+    /// # Returns
+    /// Il file rimosso dalla coda con la sua prenotazione, o None se nessun
+    /// file può partire ora (vedi [`Scheduler::released`])
+    pub fn try_start<T>(self: &Arc<Self>, queue: &mut Vec<PendingJob<T>>) -> Option<(T, Reservation)> {
+        let mut usage = self.usage.lock().unwrap();
+        let index = pick_next(queue, &usage, &self.budget)?;
+        for job in &mut queue[..index] {
+            job.bypassed += 1;
+        }
+        let job = queue.remove(index);
+        let cost = job.cost.clamp(&self.budget);
+        usage.threads += cost.threads;
+        usage.memory += cost.memory;
+        usage.jobs += 1;
+        Some((job.item, Reservation { scheduler: Arc::clone(self), cost }))
+    }
+
+    /// Attende il rilascio di una prenotazione.
+    ///
+    /// Un rilascio avvenuto prima della chiamata non va perso: [`Notify`]
+    /// conserva un permesso per l'unico consumatore.
+    pub async fn released(&self) {
+        self.released.notified().await;
+    }
+}
+
+/// Risorse riservate da un file, rilasciate quando viene scartata
+#[derive(Debug)]
+pub struct Reservation {
+    scheduler: Arc<Scheduler>,
+    cost: JobCost,
+}
+
+impl Reservation {
+    pub fn cost(&self) -> JobCost {
+        self.cost
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut usage = self.scheduler.usage.lock().unwrap();
+        usage.threads -= self.cost.threads;
+        usage.memory -= self.cost.memory;
+        usage.jobs -= 1;
+        drop(usage);
+        self.scheduler.released.notify_one();
+    }
+}
+
+/// Indice del file da avviare (None = nessuno entra nelle risorse libere)
+fn pick_next<T>(queue: &[PendingJob<T>], usage: &Usage, budget: &ResourceBudget) -> Option<usize> {
+    let head = queue.first()?;
+    if head.bypassed >= MAX_BYPASS {
+        return usage.fits(&head.cost.clamp(budget), budget).then_some(0);
+    }
+    queue.iter()
+        .take(LOOKAHEAD)
+        .enumerate()
+        .filter(|(_, job)| usage.fits(&job.cost.clamp(budget), budget))
+        // Più lavoro prima; a parità, l'ordine della coda
+        .max_by(|(a_index, a), (b_index, b)| a.cost.work.total_cmp(&b.cost.work).then(b_index.cmp(a_index)))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video_processor::fixtures;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn budget(threads: u32, memory: u64) -> ResourceBudget {
+        ResourceBudget { threads, memory, jobs: 8 }
+    }
+
+    fn video(width: u32, height: u32) -> VideoInfo {
+        fixtures::video(width, height, 60.0)
+    }
+
+    #[test]
+    fn test_cost_estimates() {
+        let config = Config::default();
+        let hd = JobCost::video(&video(1920, 1080), &config);
+        let uhd = JobCost::video(&video(3840, 2160), &config);
+        assert_eq!((hd.threads, uhd.threads), (8, 16));
+        assert!(uhd.memory > 2 * hd.memory && uhd.work > 3.9 * hd.work);
+
+        let av1 = Config { video_codec: VideoCodec::Av1, ..Default::default() };
+        assert!(JobCost::video(&video(1920, 1080), &av1).memory > hd.memory);
+        let remux = Config { remux_videos: true, ..Default::default() };
+        assert_eq!(JobCost::video(&video(3840, 2160), &remux).threads, 1);
+
+        let photo = JobCost::image(Path::new("/p/a.jpg"), 12_000_000, &config);
+        assert_eq!(photo.threads, 1);
+        assert_eq!(photo.memory, PROCESS_MEMORY + 144_000_000);
+        assert_eq!(JobCost::image(Path::new("/p/a.png"), 1_000_000, &config).threads, 4);
+        let webp = Config { convert_to_webp: true, ..Default::default() };
+        assert_eq!(JobCost::image(Path::new("/p/a.jpg"), 1_000_000, &webp).threads, 2);
+    }
+
+    #[test]
+    fn test_packing() {
+        let budget = budget(8, 4 * GIB);
+        let scheduler = Scheduler::new(budget);
+        let cost = |threads, memory, work| JobCost { threads, memory, work };
+        let mut queue = vec![
+            PendingJob::new("photo", cost(1, 100 << 20, 1e7)),
+            PendingJob::new("video", cost(6, 2 * GIB, 1e11)),
+            PendingJob::new("huge", cost(32, 64 * GIB, 1e12)),
+        ];
+
+        // The biggest job that fits starts first: "huge" is clamped to the
+        // whole budget and runs alone
+        let (first, huge) = scheduler.try_start(&mut queue).unwrap();
+        assert_eq!(first, "huge");
+        assert_eq!(huge.cost().threads, 8);
+        assert!(scheduler.try_start(&mut queue).is_none());
+        drop(huge);
+
+        // The video and the photo share the machine
+        let (second, _video) = scheduler.try_start(&mut queue).unwrap();
+        let (third, photo) = scheduler.try_start(&mut queue).unwrap();
+        assert_eq!((second, third), ("video", "photo"));
+        assert!(queue.is_empty());
+
+        // Memory is a limit as well as threads
+        let mut queue = vec![PendingJob::new("big", cost(1, 3 * GIB, 1.0))];
+        assert!(scheduler.try_start(&mut queue).is_none());
+        drop(photo);
+        assert!(scheduler.try_start(&mut queue).is_none());
+    }
+
+    #[test]
+    fn test_head_is_not_starved() {
+        let budget = budget(4, 8 * GIB);
+        let usage = Usage { threads: 2, memory: 0, jobs: 1 };
+        let cost = |threads, work| JobCost { threads, memory: 1 << 20, work };
+        let mut queue = vec![PendingJob::new(0, cost(4, 1e9)), PendingJob::new(1, cost(1, 1.0))];
+
+        // The head does not fit: the small job backfills
+        assert_eq!(pick_next(&queue, &usage, &budget), Some(1));
+        // After MAX_BYPASS backfills the scheduler waits for the head
+        queue[0].bypassed = MAX_BYPASS;
+        assert_eq!(pick_next(&queue, &usage, &budget), None);
+        assert_eq!(pick_next(&queue, &Usage::default(), &budget), Some(0));
+    }
+}
@@ -4,17 +4,20 @@
 //! Separato dal orchestratore principale per maggiore modularità.
 
 use crate::{
+    audio_processor::AudioProcessor,
     config::Config,
     error::OptimizeError,
-    file_manager::FileManager,
+    file_manager::{FileManager, MediaKind},
     image_processor::ImageProcessor,
-    optimizer::path_resolver::PathResolver,
+    optimizer::{discovery::{DiscoveredFile, FileFilter}, path_resolver::PathResolver},
+    progress::ProgressCallback,
     state::{ProcessedFile, StateManager},
     video_processor::VideoProcessor,
 };
 use anyhow::Result;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
+use tokio::sync::broadcast;
 use tracing::debug;
 
 /// Worker ottimizzato per elaborazione singoli file
@@ -22,6 +25,7 @@ pub struct TaskOptimizer {
     pub config: Config,
     pub image_processor: ImageProcessor,
     pub video_processor: VideoProcessor,
+    pub audio_processor: AudioProcessor,
     pub input_base_dir: PathBuf,
 }
 
@@ -30,41 +34,89 @@ impl TaskOptimizer {
     pub async fn new(config: Config, input_base_dir: PathBuf) -> Result<Self> {
         let image_processor = ImageProcessor::new(config.clone()).await?;
         let video_processor = VideoProcessor::new(config.clone());
+        let audio_processor = AudioProcessor::new(config.clone());
         
         Ok(Self {
             config,
             image_processor,
             video_processor,
+            audio_processor,
             input_base_dir,
         })
     }
     
+    /// Crea un task optimizer che riceve il segnale di stop dell'orchestratore
+    pub async fn new_with_cancellation(
+        config: Config,
+        input_base_dir: PathBuf,
+        stop_receiver: broadcast::Receiver<()>,
+    ) -> Result<Self> {
+        let image_processor = ImageProcessor::new_with_cancellation(config.clone(), stop_receiver.resubscribe()).await?;
+        let video_processor = VideoProcessor::new_with_cancellation(config.clone(), stop_receiver.resubscribe());
+        let audio_processor = AudioProcessor::new_with_cancellation(config.clone(), stop_receiver);
+        
+        Ok(Self {
+            config,
+            image_processor,
+            video_processor,
+            audio_processor,
+            input_base_dir,
+        })
+    }
+    
+    /// Imposta la callback per l'avanzamento dei file lunghi (video e audio)
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.video_processor.set_progress_callback(callback.clone());
+        self.audio_processor.set_progress_callback(callback);
+    }
+    
     /// Calcola path di output atteso (delegato a PathResolver)
     pub fn get_expected_output_path(&self, input_path: &Path) -> Result<PathBuf> {
         PathResolver::get_output_path(input_path, &self.input_base_dir, &self.config)
     }
+    
+    /// Path di output per una copia dell'originale (ottimizzazione scartata)
+    pub fn get_passthrough_output_path(&self, input_path: &Path) -> Result<PathBuf> {
+        PathResolver::get_passthrough_path(input_path, &self.input_base_dir, &self.config)
+    }
 
     /// Processa un singolo file
     pub async fn process_single_file(&mut self, file_path: PathBuf) -> Result<Option<ProcessedFile>> {
-        // debug!("Starting process_single_file for: {}", file_path.display());
-        
-        // Canonicalizza path
-        let file_path = file_path.canonicalize()
-            .map_err(|e| anyhow::anyhow!("Failed to canonicalize path {}: {}", file_path.display(), e))?;
-        // debug!("Canonicalized path: {}", file_path.display());
-            
-        let (original_size, modified_time) = FileManager::get_file_info(&file_path).await
-            .map_err(|e| anyhow::anyhow!("Failed to get file info for {}: {}", file_path.display(), e))?;
-        // debug!("File info - size: {}, modified: {}", original_size, modified_time);
-        
-        // Controlla se skippare il file
-        if self.should_skip_file(&file_path, modified_time).await? {
-            return Ok(None);
+        let state_manager = match self.config.output_path {
+            None => Some(StateManager::new(&self.input_base_dir).await?),
+            Some(_) => None,
+        };
+        let filter = FileFilter::new(&self.config, &self.input_base_dir, state_manager.as_ref());
+        match filter.check(&file_path).await? {
+            Some(file) => self.process_file(&file).await,
+            None => Ok(None),
         }
+    }
+    
+    /// Processa un file già passato dal filtro della pipeline (non ancora elaborato)
+    pub async fn process_file(&mut self, file: &DiscoveredFile) -> Result<Option<ProcessedFile>> {
+        let file_path = &file.canonical;
+        let (original_size, modified_time) = (file.size, file.modified_time);
         
+        // Video lunghi con streaming configurato: bundle HLS/DASH al posto dell'ottimizzazione
+        if FileManager::is_video(file_path) {
+            let bundle = self.video_processor.package_streaming(file_path, &self.input_base_dir).await
+                .map_err(|e| Self::describe_error("Streaming", file_path, e))?;
+            if let Some(bundle) = bundle {
+                debug!("Streaming bundle for {}: {} ({} renditions, {} bytes)", 
+                       file_path.display(), bundle.manifest.display(), bundle.renditions, bundle.size);
+                return Ok(Some(ProcessedFile::new(
+                    file_path.clone(),
+                    modified_time,
+                    original_size,
+                    bundle.size,
+                    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs(),
+                )));
+            }
+        }
         
         // Ottimizza basato sul tipo di file
-        let optimized_path = self.optimize_file(&file_path).await?;
+        let optimized_path = self.optimize_file(file_path).await?;
         // // debug!("Optimized file created at: {}", optimized_path.display());
         
         let optimized_size = FileManager::get_file_info(&optimized_path).await
@@ -81,49 +133,37 @@ impl TaskOptimizer {
         // // debug!("Created ProcessedFile: {:?}", processed_file);
         
         // Controlla se l'ottimizzazione vale la pena
-        self.handle_optimization_result(&file_path, &optimized_path, processed_file).await
+        self.handle_optimization_result(file_path, &optimized_path, processed_file).await
     }
     
-    /// Controlla se un file deve essere skippato
-    async fn should_skip_file(&self, file_path: &Path, modified_time: u64) -> Result<bool> {
-        if self.config.output_path.is_none() {
-            // Per ottimizzazione in-place, controlla state manager
-            let state_manager = StateManager::new(&self.input_base_dir).await?;
-            if state_manager.is_processed(file_path, modified_time) {
-                // debug!("Skipping already processed file (in-place): {}", file_path.display());
-                return Ok(true);
+    /// Ottimizza file basato sul tipo
+    async fn optimize_file(&mut self, file_path: &Path) -> Result<PathBuf> {
+        match FileManager::media_kind(file_path) {
+            Some(MediaKind::Image) => {
+                // ImageProcessor now handles pre-resize internally
+                self.image_processor.optimize(file_path, &self.input_base_dir).await
+                    .map_err(|e| Self::describe_error("Image", file_path, e))
             }
-        } else if self.config.keep_processed {
-            // Per output directory con --keep-processed, controlla se output esiste
-            let expected_output_path = self.get_expected_output_path(file_path)?;
-            // // debug!("Checking if output exists: {} -> {}", file_path.display(), expected_output_path.display());
-            if expected_output_path.exists() {
-                debug!("[OK] Skipping file, output already exists: {} -> {}", 
-                       file_path.display(), expected_output_path.display());
-                return Ok(true);
-            } else {
-                debug!("[PROCESS] Output does not exist, will process: {}", expected_output_path.display());
+            Some(MediaKind::Video) => {
+                self.video_processor.optimize(file_path, &self.input_base_dir).await
+                    .map_err(|e| Self::describe_error("Video", file_path, e))
             }
+            Some(MediaKind::Audio) => {
+                self.audio_processor.optimize(file_path, &self.input_base_dir).await
+                    .map_err(|e| Self::describe_error("Audio", file_path, e))
+            }
+            None => Err(OptimizeError::UnsupportedFormat(
+                format!("Unsupported file type: {}", file_path.display())
+            ).into()),
         }
-        Ok(false)
     }
     
-    /// Ottimizza file basato sul tipo
-    async fn optimize_file(&mut self, file_path: &Path) -> Result<PathBuf> {
-        if FileManager::is_image(file_path) {
-            // debug!("Processing as image: {}", file_path.display());
-            
-            // ImageProcessor now handles pre-resize internally
-            self.image_processor.optimize(file_path, &self.input_base_dir).await
-                .map_err(|e| anyhow::anyhow!("Image optimization failed for {}: {}", file_path.display(), e))
-        } else if FileManager::is_video(file_path) {
-            // debug!("Processing as video: {}", file_path.display());
-            self.video_processor.optimize(file_path, &self.input_base_dir).await
-                .map_err(|e| anyhow::anyhow!("Video optimization failed for {}: {}", file_path.display(), e))
+    /// Aggiunge il file al messaggio d'errore; le cancellazioni restano tipizzate
+    fn describe_error(kind: &str, file_path: &Path, error: anyhow::Error) -> anyhow::Error {
+        if OptimizeError::is_cancelled(&error) {
+            error
         } else {
-            Err(OptimizeError::UnsupportedFormat(
-                format!("Unsupported file type: {}", file_path.display())
-            ).into())
+            anyhow::anyhow!("{} optimization failed for {}: {}", kind, file_path.display(), error)
         }
     }
     
@@ -134,7 +174,9 @@ impl TaskOptimizer {
         optimized_path: &Path,
         processed_file: ProcessedFile
     ) -> Result<Option<ProcessedFile>> {
-        let should_replace = (processed_file.optimized_size as f64) < 
+        // In remux mode the goal is the container, not the size: a same-size MP4 is still kept
+        let remuxed = self.config.remux_videos && FileManager::is_video(file_path);
+        let should_replace = remuxed || (processed_file.optimized_size as f64) < 
                            (processed_file.original_size as f64 * self.config.size_threshold);
         
         debug!("Should replace? {} (optimized: {}, original: {}, threshold: {})", 
@@ -154,16 +196,12 @@ impl TaskOptimizer {
         optimized_path: &Path,
         processed_file: ProcessedFile
     ) -> Result<Option<ProcessedFile>> {
+        let mut final_path = file_path.to_path_buf();
         if !self.config.dry_run {
             if self.config.output_path.is_some() {
                 // debug!("File saved to output directory: {}", optimized_path.display());
             } else {
-                // debug!("Replacing file: {} with {}", file_path.display(), optimized_path.display());
-                FileManager::replace_file(file_path, optimized_path).await
-                    .map_err(|e| anyhow::anyhow!("Failed to replace file {}: {}", file_path.display(), e))?;
-                
-                // debug!("Cleaning up temporary file: {}", optimized_path.display());
-                let _ = std::fs::remove_file(optimized_path);
+                final_path = self.replace_in_place(file_path, optimized_path).await?;
             }
         } else {
             if self.config.output_path.is_some() {
@@ -171,19 +209,73 @@ impl TaskOptimizer {
             } else {
                 // debug!("Dry run: would replace {} with {}", file_path.display(), optimized_path.display());
             }
-            // debug!("Cleaning up temporary file: {}", optimized_path.display());
-            let _ = std::fs::remove_file(optimized_path);
+            // In-place il processore può aver scritto direttamente sull'originale
+            if optimized_path != file_path {
+                let _ = std::fs::remove_file(optimized_path);
+            }
         }
         
         // Marca come processato solo per ottimizzazione in-place
         if self.config.output_path.is_none() {
             let mut state_manager = StateManager::new(&self.input_base_dir).await?;
             state_manager.mark_processed(processed_file.clone()).await?;
+            // Il file rinominato (nuova estensione) è già ottimizzato
+            if final_path != file_path {
+                state_manager.mark_processed(ProcessedFile::new(
+                    final_path,
+                    processed_file.modified_time,
+                    processed_file.optimized_size,
+                    processed_file.optimized_size,
+                    processed_file.processed_at,
+                )).await?;
+            }
         }
         
         Ok(Some(processed_file))
     }
     
+    /// Sostituisce l'originale con il file ottimizzato (modalità in-place).
+    ///
+    /// - Stesso path: il processore ha già scritto sull'originale
+    /// - Stessa estensione: il contenuto viene copiato sull'originale (con backup)
+    /// - Formato cambiato (es. WAV -> FLAC, JPEG -> WebP): il file ottimizzato
+    ///   prende l'estensione nuova e l'originale viene rimosso
+    ///
+    /// # Returns
+    /// Il path finale del file ottimizzato.
+    async fn replace_in_place(&self, file_path: &Path, optimized_path: &Path) -> Result<PathBuf> {
+        if optimized_path == file_path {
+            return Ok(file_path.to_path_buf());
+        }
+        
+        let target = match optimized_path.extension() {
+            Some(ext) => file_path.with_extension(ext),
+            None => file_path.to_path_buf(),
+        };
+        if target == file_path {
+            FileManager::replace_file(file_path, optimized_path, self.config.preserve_ownership).await
+                .map_err(|e| anyhow::anyhow!("Failed to replace file {}: {}", file_path.display(), e))?;
+            let _ = std::fs::remove_file(optimized_path);
+            return Ok(target);
+        }
+        
+        if optimized_path != target {
+            if target.exists() {
+                let _ = std::fs::remove_file(optimized_path);
+                return Err(anyhow::anyhow!(
+                    "Cannot replace {}: {} already exists", file_path.display(), target.display()
+                ));
+            }
+            tokio::fs::copy(optimized_path, &target).await?;
+            let _ = std::fs::remove_file(optimized_path);
+        }
+        FileManager::copy_file_attributes(file_path, &target, self.config.preserve_ownership).await?;
+        tokio::fs::remove_file(file_path).await
+            .map_err(|e| anyhow::anyhow!("Failed to remove original {}: {}", file_path.display(), e))?;
+        debug!("Replaced {} with {}", file_path.display(), target.display());
+        Ok(target)
+    }
+    
     /// Gestisce ottimizzazione insufficiente
     async fn handle_insufficient_optimization(
         &self,
@@ -208,11 +300,16 @@ impl TaskOptimizer {
         
         // Per modalità output directory, copia file originale
         if self.config.output_path.is_some() && !self.config.dry_run {
-            let original_output_path = self.get_expected_output_path(file_path)?;
+            // Stessa estensione dell'originale: il file ottimizzato può averne un'altra
+            let original_output_path = self.get_passthrough_output_path(file_path)?;
             PathResolver::ensure_parent_dirs(&original_output_path).await?;
+            if optimized_path != original_output_path {
+                let _ = std::fs::remove_file(optimized_path);
+            }
             std::fs::copy(file_path, &original_output_path)?;
+            FileManager::copy_file_attributes(file_path, &original_output_path, self.config.preserve_ownership).await?;
             // debug!("Copied original file to output directory (insufficient reduction): {}", original_output_path.display());
-        } else {
+        } else if optimized_path != file_path {
             // debug!("Cleaning up temporary file: {}", optimized_path.display());
             let _ = std::fs::remove_file(optimized_path);
         }
@@ -0,0 +1,196 @@
+//! # Timeout Module
+//!
+//! Timeout di ogni file ricavato dal suo contenuto, invece che da classi di
+//! dimensione fisse: un video 4K di due ore ha bisogno di ore, una foto da
+//! 2 megapixel di un minuto.
+//!
+//! ## Responsabilità:
+//! - Stima del tempo massimo di elaborazione dal contenuto già letto per lo
+//!   scheduler ([`file_timeout`], da [`MediaProbe`])
+//! - Configurazione con il preset più veloce per la policy `retry-faster`
+//!   ([`faster_config`])
+//!
+//! ## Stima:
+//! | File     | Timeout                                                        |
+//! |----------|----------------------------------------------------------------|
+//! | Immagine | 60 s + 10 s per megapixel (PNG ×3, WebP ×2)                    |
+//! | Video    | 300 s + durata × pixel/1080p × fps/30 × preset × codec × passaggi × rendition |
+//! | Audio    | 120 s + metà della durata stimata dalla dimensione (96 kbit/s) |
+//!
+//! Per i video la base è un secondo di lavoro per secondo di video 1080p30
+//! H.264 a preset `medium`: circa tre volte il tempo reale su una macchina
+//! moderna, perché il timeout deve fermare i file bloccati, non quelli lenti.
+//! Senza `video_preset` si usa il preset di default dell'encoder (x264
+//! veryslow). L'analisi di `video_trim` aggiunge metà della durata; la copia
+//! dei video (remux, `skip_video_compression`) costa solo I/O.
+//!
+//! Tutte le stime sono moltiplicate per `config.timeout_multiplier`.
+
+use crate::config::{Config, VideoCodec, VideoPreset};
+use crate::file_manager::FileManager;
+use crate::optimizer::scheduler::MediaProbe;
+use crate::video_processor::VideoInfo;
+use std::path::Path;
+use std::time::Duration;
+
+/// Secondi di encoding per secondo di video 1080p30 H.264 `medium`
+const VIDEO_SECONDS_PER_SECOND: f64 = 1.0;
+
+/// Pixel di un frame 1080p, riferimento della stima video
+const REFERENCE_PIXELS: f64 = 1920.0 * 1080.0;
+
+/// Tempo minimo di un video (avvio di ffmpeg, probe, pre-analisi, muxing)
+const VIDEO_MIN_SECONDS: f64 = 300.0;
+
+/// Tempo minimo di un'immagine
+const IMAGE_MIN_SECONDS: f64 = 60.0;
+
+/// Secondi per megapixel di un'immagine JPEG
+const IMAGE_SECONDS_PER_MEGAPIXEL: f64 = 10.0;
+
+/// Tempo minimo di un file audio
+const AUDIO_MIN_SECONDS: f64 = 120.0;
+
+/// Bitrate minimo ipotizzato per ricavare la durata di un audio dalla
+/// dimensione (una sovrastima per i formati lossless)
+const AUDIO_MIN_BITRATE: f64 = 96_000.0;
+
+/// Timeout di un file dal suo contenuto (vedi "Stima")
+pub fn file_timeout(probe: &MediaProbe, path: &Path, size: u64, config: &Config) -> Duration {
+    let seconds = match probe {
+        MediaProbe::Image { pixels } => image_seconds(path, *pixels, config),
+        MediaProbe::Video(info) => video_seconds(info, config),
+        MediaProbe::Audio => AUDIO_MIN_SECONDS + size as f64 * 8.0 / AUDIO_MIN_BITRATE * 0.5,
+    };
+    Duration::from_secs_f64(seconds * config.timeout_multiplier)
+}
+
+/// Secondi di un'immagine secondo il tool che la elabora
+fn image_seconds(path: &Path, pixels: u64, config: &Config) -> f64 {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let tool_factor = match extension.as_str() {
+        _ if config.convert_to_webp => 2.0,
+        "webp" => 2.0,
+        // oxipng prova tutti i filtri
+        "png" => 3.0,
+        _ => 1.0,
+    };
+    IMAGE_MIN_SECONDS + pixels as f64 / 1e6 * IMAGE_SECONDS_PER_MEGAPIXEL * tool_factor
+}
+
+/// Secondi di un video: durata, risoluzione, frame rate, codec e preset
+fn video_seconds(info: &VideoInfo, config: &Config) -> f64 {
+    let duration = info.duration.max(1.0);
+    if config.skip_video_compression || config.remux_videos {
+        return VIDEO_MIN_SECONDS + duration * 0.1;
+    }
+
+    let pixels = (info.width as f64 * info.height as f64 / REFERENCE_PIXELS).max(0.1);
+    let frames = (info.frame_rate / 30.0).max(0.5);
+    let passes = if config.video_target_bitrate.is_some() || config.video_target_size.is_some() { 2.0 } else { 1.0 };
+    // La ladder di streaming codifica tutte le rendition insieme
+    let renditions = if config.streaming.is_some() { config.streaming_ladder.len().max(1) as f64 } else { 1.0 };
+    let analysis = if config.video_trim.is_some() { duration * 0.5 } else { 0.0 };
+
+    let encoding = duration * VIDEO_SECONDS_PER_SECOND * pixels * frames
+        * preset_factor(effective_preset(config)) * codec_factor(config.video_codec)
+        * passes * renditions;
+    VIDEO_MIN_SECONDS + encoding + analysis
+}
+
+/// Preset usato dall'encoder: quello configurato o il default dell'encoder,
+/// nella scala x264 (vedi `video_codec`)
+fn effective_preset(config: &Config) -> VideoPreset {
+    config.video_preset.unwrap_or(match config.video_codec {
+        VideoCodec::H264 => VideoPreset::Veryslow,
+        // x265 slow, SVT-AV1 6, libvpx cpu-used 2
+        VideoCodec::Hevc | VideoCodec::Av1 | VideoCodec::Vp9 => VideoPreset::Slow,
+    })
+}
+
+/// Tempo di encoding rispetto al preset `medium`
+fn preset_factor(preset: VideoPreset) -> f64 {
+    match preset {
+        VideoPreset::Ultrafast => 0.15,
+        VideoPreset::Superfast => 0.2,
+        VideoPreset::Veryfast => 0.3,
+        VideoPreset::Faster => 0.5,
+        VideoPreset::Fast => 0.7,
+        VideoPreset::Medium => 1.0,
+        VideoPreset::Slow => 1.6,
+        VideoPreset::Slower => 3.0,
+        VideoPreset::Veryslow => 5.0,
+    }
+}
+
+/// Tempo di encoding rispetto a x264 allo stesso preset
+fn codec_factor(codec: VideoCodec) -> f64 {
+    match codec {
+        VideoCodec::H264 => 1.0,
+        VideoCodec::Vp9 => 2.5,
+        VideoCodec::Hevc | VideoCodec::Av1 => 3.0,
+    }
+}
+
+/// Configurazione per ritentare un file con il preset più veloce.
+///
+/// # Returns
+/// * `Option<Config>` - None se il file non è un video ricodificato o se
+///   usa già il preset più veloce
+pub fn faster_config(config: &Config, path: &Path) -> Option<Config> {
+    let encoded = FileManager::is_video(path) && !config.skip_video_compression && !config.remux_videos;
+    if !encoded || effective_preset(config) == VideoPreset::Ultrafast {
+        return None;
+    }
+    Some(Config {
+        video_preset: Some(VideoPreset::Ultrafast),
+        ..config.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video_processor::fixtures;
+
+    fn video(width: u32, height: u32, duration: f64) -> MediaProbe {
+        MediaProbe::Video(Box::new(fixtures::video(width, height, duration)))
+    }
+
+    #[test]
+    fn test_timeouts_follow_content() {
+        let config = Config::default();
+        let path = Path::new("/v/clip.mp4");
+        let timeout = |probe: &MediaProbe, config: &Config| file_timeout(probe, path, 0, config).as_secs();
+
+        // A 2-hour 4K video gets far more than the old fixed 15 minutes
+        let short_hd = timeout(&video(1920, 1080, 60.0), &config);
+        let long_4k = timeout(&video(3840, 2160, 7200.0), &config);
+        assert_eq!(short_hd, 600);
+        assert!(long_4k > 24 * 3600, "{}", long_4k);
+
+        // Faster presets and copies shrink the estimate, the multiplier scales it
+        let fast = Config { video_preset: Some(VideoPreset::Ultrafast), ..Config::default() };
+        assert!(timeout(&video(3840, 2160, 7200.0), &fast) < long_4k / 20);
+        let remux = Config { remux_videos: true, ..Config::default() };
+        assert_eq!(timeout(&video(3840, 2160, 7200.0), &remux), 1020);
+        let slow_machine = Config { timeout_multiplier: 2.0, ..Config::default() };
+        assert_eq!(timeout(&video(1920, 1080, 60.0), &slow_machine), 1200);
+
+        // Images scale with megapixels and the tool
+        let photo = MediaProbe::Image { pixels: 12_000_000 };
+        assert_eq!(file_timeout(&photo, Path::new("a.jpg"), 0, &config).as_secs(), 180);
+        assert_eq!(file_timeout(&photo, Path::new("a.png"), 0, &config).as_secs(), 420);
+    }
+
+    #[test]
+    fn test_faster_config() {
+        let config = Config::default();
+        let faster = faster_config(&config, Path::new("clip.mov")).unwrap();
+        assert_eq!(faster.video_preset, Some(VideoPreset::Ultrafast));
+        assert!(faster_config(&faster, Path::new("clip.mov")).is_none());
+        assert!(faster_config(&config, Path::new("photo.jpg")).is_none());
+        let remux = Config { remux_videos: true, ..Config::default() };
+        assert!(faster_config(&remux, Path::new("clip.mov")).is_none());
+    }
+}
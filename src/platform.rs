@@ -5,6 +5,7 @@
 //! e tool di sistema.
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::OnceLock;
 use crate::tool_resolver::ToolPathResolver;
 
@@ -19,7 +20,7 @@ impl PlatformCommands {
     /// Get the singleton instance
     pub fn instance() -> &'static Self {
         static INSTANCE: OnceLock<PlatformCommands> = OnceLock::new();
-        INSTANCE.get_or_init(|| Self::new())
+        INSTANCE.get_or_init(Self::new)
     }
     
     /// Initialize platform-specific commands
@@ -84,6 +85,7 @@ impl PlatformCommands {
         // Use tokio::process::Command for async execution
         let result = tokio::process::Command::new(self.which_command)
             .arg(command_name)
+            .kill_on_drop(true)
             .output()
             .await;
             
@@ -103,6 +105,48 @@ impl PlatformCommands {
         self.tool_resolver.get_tools_report()
     }
     
+    /// Locate an sRGB ICC profile on disk, needed by ImageMagick for colour
+    /// conversion (`-profile`). libvips has a built-in `srgb` profile instead.
+    ///
+    /// `SRGB_ICC_PROFILE` overrides the well-known system locations.
+    pub fn srgb_icc_profile() -> Option<PathBuf> {
+        static PROFILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+        PROFILE.get_or_init(|| {
+            if let Some(path) = std::env::var_os("SRGB_ICC_PROFILE").map(PathBuf::from) {
+                return path.exists().then_some(path);
+            }
+            let candidates: &[&str] = if cfg!(windows) {
+                &[r"C:\Windows\System32\spool\drivers\color\sRGB Color Space Profile.icm"]
+            } else if cfg!(target_os = "macos") {
+                &["/System/Library/ColorSync/Profiles/sRGB Profile.icc"]
+            } else {
+                &[
+                    "/usr/share/color/icc/sRGB.icc",
+                    "/usr/share/color/icc/colord/sRGB.icc",
+                    "/usr/share/color/icc/ghostscript/srgb.icc",
+                    "/usr/share/ghostscript/iccprofiles/srgb.icc",
+                ]
+            };
+            candidates.iter().map(PathBuf::from).find(|p| p.exists())
+        }).clone()
+    }
+    
+    /// Memoria disponibile in byte, per il budget dello scheduler.
+    ///
+    /// Linux: `MemAvailable` di `/proc/meminfo` (memoria libera più cache
+    /// recuperabile). macOS: memoria fisica totale (`sysctl hw.memsize`), non
+    /// esistendo un valore economico della memoria libera. Altri sistemi: None.
+    pub fn available_memory() -> Option<u64> {
+        if cfg!(target_os = "linux") {
+            parse_mem_available(&std::fs::read_to_string("/proc/meminfo").ok()?)
+        } else if cfg!(target_os = "macos") {
+            let output = std::process::Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+            String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+        } else {
+            None
+        }
+    }
+    
     /// Get system information for // debugging
     pub fn system_info() -> SystemInfo {
         SystemInfo {
@@ -113,6 +157,13 @@ impl PlatformCommands {
     }
 }
 
+/// Legge `MemAvailable` (in kB) da `/proc/meminfo`
+fn parse_mem_available(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemAvailable:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
 /// System information structure
 #[derive(Debug, Clone)]
 pub struct SystemInfo {
@@ -155,6 +206,13 @@ mod tests {
         let _ = has_echo;
     }
     
+    #[test]
+    fn test_mem_available() {
+        let meminfo = "MemTotal:       16314204 kB\nMemFree:         1021444 kB\nMemAvailable:    9470356 kB\n";
+        assert_eq!(parse_mem_available(meminfo), Some(9_470_356 * 1024));
+        assert_eq!(parse_mem_available("MemTotal: 1 kB"), None);
+    }
+    
     #[test]
     fn test_system_info() {
         let info = PlatformCommands::system_info();
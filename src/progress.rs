@@ -12,6 +12,7 @@
 //! ## Componenti principali:
 //! - `ProgressManager`: Gestisce progress bar principale
 //! - `OptimizationStats`: Traccia statistiche cumulative
+//! - `FileProgress`: Avanzamento di un singolo file (encoding video)
 //! 
 //! ## Progress tracking:
 //! - Barra di progresso con percentuale completamento
@@ -23,6 +24,10 @@
 //! - **files_processed**: Totale file elaborati
 //! - **files_optimized**: File effettivamente ottimizzati (sostituiti)
 //! - **files_skipped**: File saltati (riduzione insufficiente)
+//! - **files_cancelled**: File interrotti da uno stop
+//! - **files_remaining**: File scoperti ma non avviati per uno stop (ripresi alla run successiva)
+//! - **scan_interrupted**: La scansione è stata fermata da uno stop prima della fine
+//! - **timeouts**: File che hanno superato il timeout (vedi `config.timeout_policy`)
 //! - **total_bytes_saved**: Byte totali risparmiati
 //! - **total_original_size**: Dimensione totale file originali
 //! - **errors**: Numero di errori durante processing
@@ -34,12 +39,12 @@
 //! - Byte risparmiati formattati (KB, MB, GB)
 //! 
 //! ## Visual feedback:
-//! ```
+//! ```text
 //! ⠋ [00:02:15] [████████████████████████████████████████] 150/150 (100%) ✅ photo.jpg: 45.2% saved
 //! ```
 //! 
 //! ## Esempio:
-//! ```rust
+//! ```rust,ignore
 //! let progress = ProgressManager::new(total_files);
 //! let mut stats = OptimizationStats::new();
 //! 
@@ -51,9 +56,32 @@
 //! progress.finish(&stats.format_summary());
 //! ```
 
+use crate::file_manager::MediaKind;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+/// Avanzamento di un singolo file durante un'elaborazione lunga
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileProgress {
+    pub path: PathBuf,
+    /// Percentuale 0-100 (None se la durata non è nota)
+    pub percent: Option<f64>,
+    /// Secondi di media già elaborati
+    pub processed_seconds: f64,
+    /// Durata totale attesa in secondi
+    pub duration_seconds: Option<f64>,
+    /// Velocità rispetto al tempo reale
+    pub speed: Option<f64>,
+    /// Secondi stimati al termine
+    pub eta_seconds: Option<f64>,
+}
+
+/// Callback per gli aggiornamenti di [`FileProgress`], condivisa tra i task
+pub type ProgressCallback = Arc<dyn Fn(&FileProgress) + Send + Sync>;
+
 /// Manages progress reporting for media optimization
 #[derive(Clone)]
 pub struct ProgressManager {
@@ -83,6 +111,11 @@ impl ProgressManager {
         self.bar.set_message(message.to_string());
     }
     
+    /// Grow the total as more files are discovered
+    pub fn inc_length(&self, delta: u64) {
+        self.bar.inc_length(delta);
+    }
+    
     /// Set a custom message without incrementing
     pub fn set_message(&self, message: &str) {
         self.bar.set_message(message.to_string());
@@ -110,15 +143,33 @@ impl ProgressManager {
     }
 }
 
+/// Risultati di un singolo tipo di media (immagini, video, audio)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaKindStats {
+    pub files_optimized: usize,
+    pub total_original_size: u64,
+    pub total_bytes_saved: u64,
+}
+
 /// Statistics tracker for optimization results
 #[derive(Debug, Default)]
 pub struct OptimizationStats {
     pub files_processed: usize,
     pub files_optimized: usize,
     pub files_skipped: usize,
+    pub files_cancelled: usize,
+    pub files_remaining: usize,
+    /// Lo stop ha fermato anche la scansione: oltre a `files_remaining`
+    /// restano file non ancora scoperti
+    pub scan_interrupted: bool,
+    /// File oltre il timeout, compresi quelli ritentati con successo
+    pub timeouts: usize,
     pub total_bytes_saved: u64,
     pub total_original_size: u64,
     pub errors: usize,
+    pub images: MediaKindStats,
+    pub videos: MediaKindStats,
+    pub audio: MediaKindStats,
 }
 
 impl OptimizationStats {
@@ -133,6 +184,20 @@ impl OptimizationStats {
         self.total_bytes_saved += original_size.saturating_sub(new_size);
     }
     
+    /// Come [`Self::add_optimized`], aggiornando anche le statistiche del tipo di media
+    pub fn add_optimized_kind(&mut self, kind: Option<MediaKind>, original_size: u64, new_size: u64) {
+        self.add_optimized(original_size, new_size);
+        let kind_stats = match kind {
+            Some(MediaKind::Image) => &mut self.images,
+            Some(MediaKind::Video) => &mut self.videos,
+            Some(MediaKind::Audio) => &mut self.audio,
+            None => return,
+        };
+        kind_stats.files_optimized += 1;
+        kind_stats.total_original_size += original_size;
+        kind_stats.total_bytes_saved += original_size.saturating_sub(new_size);
+    }
+    
     pub fn add_skipped(&mut self, original_size: u64) {
         self.files_processed += 1;
         self.files_skipped += 1;
@@ -144,6 +209,16 @@ impl OptimizationStats {
         self.errors += 1;
     }
     
+    /// Un file cancellato non conta come elaborato: verrà ripreso al prossimo avvio
+    pub fn add_cancelled(&mut self) {
+        self.files_cancelled += 1;
+    }
+    
+    /// Indica se la run è stata interrotta prima di elaborare tutti i file
+    pub fn is_interrupted(&self) -> bool {
+        self.files_cancelled > 0 || self.files_remaining > 0 || self.scan_interrupted
+    }
+    
     pub fn overall_reduction_percent(&self) -> f64 {
         if self.total_original_size > 0 {
             (self.total_bytes_saved as f64 / self.total_original_size as f64) * 100.0
@@ -153,7 +228,7 @@ impl OptimizationStats {
     }
     
     pub fn format_summary(&self) -> String {
-        format!(
+        let mut summary = format!(
             "Processed: {} files | Optimized: {} | Skipped: {} | Errors: {} | Total saved: {} ({:.2}%)",
             self.files_processed,
             self.files_optimized,
@@ -161,6 +236,19 @@ impl OptimizationStats {
             self.errors,
             crate::file_manager::FileManager::format_size(self.total_bytes_saved),
             self.overall_reduction_percent()
-        )
+        );
+        if self.files_cancelled > 0 {
+            summary.push_str(&format!(" | Cancelled: {}", self.files_cancelled));
+        }
+        if self.files_remaining > 0 {
+            summary.push_str(&format!(" | Remaining: {}", self.files_remaining));
+        }
+        if self.timeouts > 0 {
+            summary.push_str(&format!(" | Timeouts: {}", self.timeouts));
+        }
+        if self.scan_interrupted {
+            summary.push_str(" | Scan interrupted");
+        }
+        summary
     }
 }
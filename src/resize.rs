@@ -20,7 +20,7 @@
 //! 4. **Error**: Se nessun tool disponibile
 //!
 //! ## Struttura Output
-//! ```
+//! ```text
 //! /output
 //! ├── originali/
 //! │   ├── foto1.jpg
@@ -43,9 +43,23 @@
 //! - **-limit memory 256MB**: Limita uso memoria per non rallentare il sistema
 //! - **-define jpeg:size=WxH**: Pre-riduce JPEG grandi per velocità massima
 //! - **-quality 95**: Preserva la qualità originale senza ricompressione aggressiva
+//!
+//! ## Orientamento EXIF
+//! - **strip_metadata = true**: `-auto-orient` ruota i pixel prima di `-strip`
+//! - **strip_metadata = false**: pixel invariati, il tag Orientation viene copiato
+//! - **vips**: ruota di default, `--no-rotate` quando i metadati sono preservati
+//!
+//! ## Profili colore ICC
+//! - **Preserve**: con `strip_metadata` si usa `+profile "!icc,*"` invece di
+//!   `-strip` quando l'originale ha un profilo ICC (vips lo mantiene sempre)
+//! - **ConvertToSrgb**: profili wide-gamut convertiti con `-profile <sRGB.icc>`
+//!   o `--export-profile srgb` (vips)
 //! - **Nessuna compressione aggiuntiva**: Solo resize, mantiene dimensioni appropriate
 
-use crate::config::{Config, ThumbnailSize};
+use crate::config::{ColorProfilePolicy, Config, ThumbnailSize};
+use crate::error::OptimizeError;
+use crate::file_manager::FileManager;
+use crate::image_probe::{ImageFormat, ImageHeader, ImageProbe};
 use crate::platform::PlatformCommands;
 use crate::utils::to_string_vec;
 use anyhow::Result;
@@ -55,9 +69,10 @@ use tokio::sync::broadcast;
 use tracing::{debug, info, warn, error};
 
 /// Algoritmi di resize disponibili per ImageMagick
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ResizeAlgorithm {
     /// Lanczos - Migliore qualità per downscaling (default per thumbnails)
+    #[default]
     Lanczos,
     /// Mitchell - Buon bilanciamento qualità/velocità
     Mitchell,
@@ -69,12 +84,6 @@ pub enum ResizeAlgorithm {
     Point,
 }
 
-impl Default for ResizeAlgorithm {
-    fn default() -> Self {
-        Self::Lanczos
-    }
-}
-
 impl ResizeAlgorithm {
     /// Converte l'algoritmo in parametro ImageMagick
     pub fn to_imagemagick_filter(&self) -> &'static str {
@@ -89,9 +98,10 @@ impl ResizeAlgorithm {
 }
 
 /// Modalità di resize per gestire aspect ratio
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum ResizeMode {
     /// Ridimensiona mantenendo aspect ratio, aggiunge padding se necessario
+    #[default]
     Fit,
     /// Ridimensiona e croppa al centro per riempire esattamente le dimensioni  
     Fill,
@@ -99,12 +109,6 @@ pub enum ResizeMode {
     Stretch,
 }
 
-impl Default for ResizeMode {
-    fn default() -> Self {
-        Self::Fit
-    }
-}
-
 impl ResizeMode {
     /// Converte la modalità in geometry string per ImageMagick
     pub fn to_imagemagick_geometry(&self, width: u32, height: u32) -> String {
@@ -132,6 +136,10 @@ pub struct ImageResizer {
     stop_receiver: Option<broadcast::Receiver<()>>,
     /// Cache del tool risolto per evitare lookup ripetuti
     cached_tool: Option<(String, PathBuf)>, // (tool_name, tool_path)
+    /// Header dell'immagine corrente, letto una volta per tutti i thumbnails
+    source_header: Option<ImageHeader>,
+    /// Profilo sRGB su disco per la conversione con ImageMagick (immagine corrente)
+    srgb_profile: Option<PathBuf>,
 }
 
 impl ImageResizer {
@@ -161,6 +169,8 @@ impl ImageResizer {
             strip_metadata,
             stop_receiver: None,
             cached_tool: None,
+            source_header: None,
+            srgb_profile: None,
         })
     }
 
@@ -181,6 +191,8 @@ impl ImageResizer {
             strip_metadata,
             stop_receiver: Some(stop_receiver),
             cached_tool: None,
+            source_header: None,
+            srgb_profile: None,
         })
     }
 
@@ -225,7 +237,7 @@ impl ImageResizer {
     ) -> Result<Vec<PathBuf>> {
         // Controlla cancellazione
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Thumbnail creation cancelled by user"));
+            return Err(OptimizeError::Cancelled("Thumbnail creation".to_string()).into());
         }
 
         // Verifica che thumbnails siano configurati
@@ -242,13 +254,21 @@ impl ImageResizer {
 
         debug!("Creating thumbnails for: {}", input_path.display());
 
+        // Legge l'header una sola volta: serve per formato reale e dimensioni sorgente
+        self.source_header = ImageProbe::probe(input_path).await.ok();
+        self.srgb_profile = if self.should_convert_to_srgb() {
+            PlatformCommands::srgb_icc_profile()
+        } else {
+            None
+        };
+
         let mut thumbnail_paths = Vec::new();
 
         // Crea ogni tipo di thumbnail configurato
         let thumbnails_config = self.config.thumbnails.clone(); // Clone per evitare il borrow
         for (thumbnail_name, thumbnail_size) in &thumbnails_config {
             if self.should_stop() {
-                return Err(anyhow::anyhow!("Thumbnail creation cancelled by user"));
+                return Err(OptimizeError::Cancelled("Thumbnail creation".to_string()).into());
             }
 
             let thumbnail_path = self
@@ -314,6 +334,9 @@ impl ImageResizer {
             ));
         }
 
+        // Stessa data dell'originale: le gallerie ordinano per data del file
+        FileManager::copy_file_attributes(input_path, &output_path, self.config.preserve_ownership).await?;
+
         debug!("Saved thumbnail: {}", output_path.display());
         Ok(output_path)
     }
@@ -384,6 +407,7 @@ impl ImageResizer {
         
         let success = Command::new(tool_path)
             .args(&args)
+            .kill_on_drop(true)
             .status()
             .await?
             .success();
@@ -404,11 +428,18 @@ impl ImageResizer {
         let mut args = to_string_vec([input]);
 
         // Pre-ridimensionamento veloce per JPEG grandi (ottimizzazione di velocità)
-        if input.ends_with(".jpg") || input.ends_with(".jpeg") {
-            let pre_size = (thumbnail_size.width.max(thumbnail_size.height) * 2).min(2048);
-            args.extend(to_string_vec([
-                "-define", &format!("jpeg:size={}x{}", pre_size, pre_size),
-            ]));
+        if let Some(hint) = self.jpeg_size_hint(input, thumbnail_size) {
+            args.extend(to_string_vec(["-define", &hint]));
+        }
+
+        // Orientamento EXIF: ruota i pixel prima che -strip rimuova il tag
+        if self.should_auto_orient() {
+            args.push("-auto-orient".to_string());
+        }
+
+        // Conversione wide-gamut -> sRGB prima del resize
+        if let Some(ref profile) = self.srgb_profile {
+            args.extend(to_string_vec(["-intent", "Perceptual", "-profile", &profile.to_string_lossy()]));
         }
 
         // Usa sempre Mitchell per massima velocità
@@ -432,9 +463,13 @@ impl ImageResizer {
             "-limit", "thread", "1",  // Single thread per evitare contesa
         ]));
 
+        if self.strip_metadata {
+            args.extend(self.magick_strip_args());
+        }
+
         // IMPORTANTE: Preserva la qualità originale per tutti i formati
         if output.ends_with(".jpg") || output.ends_with(".jpeg") {
-            args.extend(to_string_vec(["-quality", "95"])); // Alta qualità per preservare l'originale
+            args.extend(to_string_vec(["-quality", &self.jpeg_quality.to_string()])); // Alta qualità per preservare l'originale
         } else if output.ends_with(".webp") {
             // Per WebP thumbnails usa sempre qualità fissa 80 per buon bilanciamento qualità/dimensioni
             args.extend(to_string_vec(["-quality", "80"]));
@@ -451,11 +486,18 @@ impl ImageResizer {
         let mut args = to_string_vec([input]);
 
         // Pre-ridimensionamento veloce per JPEG grandi (ottimizzazione di velocità)
-        if input.ends_with(".jpg") || input.ends_with(".jpeg") {
-            let pre_size = (thumbnail_size.width.max(thumbnail_size.height) * 2).min(2048);
-            args.extend(to_string_vec([
-                "-define", &format!("jpeg:size={}x{}", pre_size, pre_size),
-            ]));
+        if let Some(hint) = self.jpeg_size_hint(input, thumbnail_size) {
+            args.extend(to_string_vec(["-define", &hint]));
+        }
+
+        // Orientamento EXIF: ruota i pixel prima che -strip rimuova il tag
+        if self.should_auto_orient() {
+            args.push("-auto-orient".to_string());
+        }
+
+        // Conversione wide-gamut -> sRGB prima del resize
+        if let Some(ref profile) = self.srgb_profile {
+            args.extend(to_string_vec(["-intent", "Perceptual", "-profile", &profile.to_string_lossy()]));
         }
 
         // Usa sempre Mitchell per massima velocità
@@ -479,9 +521,13 @@ impl ImageResizer {
             "-limit", "thread", "1",  // Single thread per evitare contesa
         ]));
 
+        if self.strip_metadata {
+            args.extend(self.magick_strip_args());
+        }
+
         // IMPORTANTE: Preserva la qualità originale per tutti i formati
         if output.ends_with(".jpg") || output.ends_with(".jpeg") {
-            args.extend(to_string_vec(["-quality", "95"])); // Alta qualità per preservare l'originale
+            args.extend(to_string_vec(["-quality", &self.jpeg_quality.to_string()])); // Alta qualità per preservare l'originale
         } else if output.ends_with(".webp") {
             // Per WebP thumbnails usa sempre qualità fissa 80 per buon bilanciamento qualità/dimensioni
             args.extend(to_string_vec(["-quality", "80"]));
@@ -515,13 +561,24 @@ impl ImageResizer {
                 args.extend(to_string_vec(["--crop", "centre"]));
             }
             ResizeMode::Stretch => {
-                args.push("--no-rotate".to_string());
+                args.extend(to_string_vec(["--size", "force"]));
             }
         }
 
+        // vips mantiene il profilo ICC: va convertito solo se richiesto
+        if self.should_convert_to_srgb() {
+            args.extend(to_string_vec(["--export-profile", "srgb"]));
+        }
+
+        // vips thumbnail ruota automaticamente e azzera il tag: se i metadati
+        // vengono preservati lascia i pixel invariati e mantieni il tag
+        if !self.strip_metadata {
+            args.push("--no-rotate".to_string());
+        }
+
         // IMPORTANTE: Preserva la qualità originale
         if output.ends_with(".jpg") || output.ends_with(".jpeg") {
-            args.extend(to_string_vec(["--Q", "95"])); // Alta qualità per JPEG
+            args.extend(to_string_vec(["--Q", &self.jpeg_quality.to_string()])); // Alta qualità per JPEG
         } else if output.ends_with(".webp") {
             // Per WebP thumbnails usa sempre qualità fissa 80 per buon bilanciamento qualità/dimensioni
             args.extend(to_string_vec(["--Q", "80"]));
@@ -530,6 +587,56 @@ impl ImageResizer {
         args
     }
 
+    /// Indica se il thumbnail va convertito in sRGB: richiesto dalla
+    /// configurazione e l'originale ha un profilo ICC non sRGB.
+    fn should_convert_to_srgb(&self) -> bool {
+        self.config.color_profile == ColorProfilePolicy::ConvertToSrgb
+            && self.source_header.as_ref().is_some_and(|h| h.has_non_srgb_profile())
+    }
+
+    /// Argomenti ImageMagick per rimuovere i metadati.
+    ///
+    /// `-strip` rimuove anche il profilo ICC: se l'originale ne ha uno che non
+    /// è stato convertito in sRGB, si rimuovono tutti i profili tranne l'ICC.
+    fn magick_strip_args(&self) -> Vec<String> {
+        let has_icc = self.source_header.as_ref().is_some_and(|h| h.icc_profile.is_some());
+        if has_icc && self.srgb_profile.is_none() {
+            to_string_vec(["+profile", "!icc,*"])
+        } else {
+            vec!["-strip".to_string()]
+        }
+    }
+
+    /// Decide se applicare `-auto-orient` con ImageMagick.
+    ///
+    /// Con metadati rimossi il tag Orientation sparisce, quindi i pixel vanno
+    /// ruotati fisicamente; con metadati preservati il tag resta valido e i
+    /// pixel non vengono toccati. Se l'header non è leggibile si ruota comunque
+    /// (operazione innocua su immagini già dritte).
+    fn should_auto_orient(&self) -> bool {
+        self.strip_metadata
+            && self.source_header.as_ref().is_none_or(|header| header.needs_auto_orient())
+    }
+
+    /// Calcola l'hint `jpeg:size` per la decodifica ridotta dei JPEG.
+    ///
+    /// Usa l'header già letto per riconoscere il JPEG dal contenuto e per
+    /// evitare l'hint quando la sorgente è già più piccola della pre-dimensione.
+    /// Senza header ricade sul controllo dell'estensione.
+    fn jpeg_size_hint(&self, input: &str, thumbnail_size: &ThumbnailSize) -> Option<String> {
+        let pre_size = (thumbnail_size.width.max(thumbnail_size.height) * 2).min(2048);
+
+        let is_large_jpeg = match &self.source_header {
+            Some(header) => header.format == ImageFormat::Jpeg && header.width.max(header.height) > pre_size,
+            None => {
+                let lower = input.to_lowercase();
+                lower.ends_with(".jpg") || lower.ends_with(".jpeg")
+            }
+        };
+
+        is_large_jpeg.then(|| format!("jpeg:size={}x{}", pre_size, pre_size))
+    }
+
     /// Calcola il path per un thumbnail
     fn get_thumbnail_path(
         &self,
@@ -624,10 +731,10 @@ impl ImageResizer {
 impl ImageResizer {
     /// Verifica se un file è un'immagine supportata per il resize
     pub fn is_supported_for_resize(path: &Path) -> bool {
-        match path.extension().and_then(|s| s.to_str()).map(|s| s.to_lowercase()).as_deref() {
-            Some("jpg") | Some("jpeg") | Some("png") | Some("webp") => true,
-            _ => false,
-        }
+        matches!(
+            path.extension().and_then(|s| s.to_str()).map(|s| s.to_lowercase()).as_deref(),
+            Some("jpg") | Some("jpeg") | Some("png") | Some("webp")
+        )
     }
 
     /// Crea un canale di cancellazione per i thumbnails
@@ -645,3 +752,91 @@ impl ImageResizer {
         supported_files * self.config.thumbnails.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_probe::fixtures::{jpeg_with_icc, jpeg_with_orientation};
+    use std::io::Cursor;
+
+    fn resizer_for(orientation: u16, strip_metadata: bool) -> ImageResizer {
+        let mut resizer = ImageResizer::new(
+            Config::default(),
+            ResizeAlgorithm::Lanczos,
+            ResizeMode::Fit,
+            Some(95),
+            strip_metadata,
+        )
+        .unwrap();
+        let fixture = jpeg_with_orientation(64, 32, orientation);
+        resizer.source_header = Some(ImageProbe::from_reader(&mut Cursor::new(fixture)).unwrap());
+        resizer
+    }
+
+    #[test]
+    fn test_auto_orient_when_stripping_metadata() {
+        let size = ThumbnailSize::new(150, 150);
+        for orientation in 1..=8u16 {
+            let resizer = resizer_for(orientation, true);
+            let magick = resizer.build_magick_args("in.jpg", "out.jpg", &size);
+            let convert = resizer.build_convert_args("in.jpg", "out.jpg", &size);
+            let expected = orientation > 1;
+            assert_eq!(magick.contains(&"-auto-orient".to_string()), expected, "orientation {}", orientation);
+            assert_eq!(convert.contains(&"-auto-orient".to_string()), expected, "orientation {}", orientation);
+            assert!(magick.contains(&"-strip".to_string()));
+
+            // -auto-orient deve precedere il resize, altrimenti la geometria è sbagliata
+            if expected {
+                let orient_at = magick.iter().position(|a| a == "-auto-orient").unwrap();
+                let resize_at = magick.iter().position(|a| a == "-resize").unwrap();
+                assert!(orient_at < resize_at);
+            }
+
+            let vips = resizer.build_vips_args("in.jpg", "out.jpg", &size);
+            assert!(!vips.contains(&"--no-rotate".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_orientation_tag_kept_when_preserving_metadata() {
+        let size = ThumbnailSize::new(150, 150);
+        for orientation in 1..=8u16 {
+            let resizer = resizer_for(orientation, false);
+            let magick = resizer.build_magick_args("in.jpg", "out.jpg", &size);
+            assert!(!magick.contains(&"-auto-orient".to_string()));
+            assert!(!magick.contains(&"-strip".to_string()));
+
+            let vips = resizer.build_vips_args("in.jpg", "out.jpg", &size);
+            assert!(vips.contains(&"--no-rotate".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_wide_gamut_profile_handling() {
+        let size = ThumbnailSize::new(150, 150);
+        let fixture = jpeg_with_icc(64, 32, "Display P3");
+        let header = ImageProbe::from_reader(&mut Cursor::new(fixture)).unwrap();
+
+        // Preserve: il profilo ICC sopravvive allo strip
+        let mut resizer = ImageResizer::new(Config::default(), ResizeAlgorithm::Lanczos, ResizeMode::Fit, None, true).unwrap();
+        resizer.source_header = Some(header.clone());
+        let magick = resizer.build_magick_args("in.jpg", "out.jpg", &size);
+        assert!(!magick.contains(&"-strip".to_string()));
+        assert!(magick.windows(2).any(|w| w == ["+profile", "!icc,*"]));
+        assert!(!resizer.build_vips_args("in.jpg", "out.jpg", &size).contains(&"--export-profile".to_string()));
+
+        // ConvertToSrgb: pixel convertiti, poi lo strip può rimuovere tutto
+        let config = Config { color_profile: ColorProfilePolicy::ConvertToSrgb, ..Default::default() };
+        let mut resizer = ImageResizer::new(config, ResizeAlgorithm::Lanczos, ResizeMode::Fit, None, true).unwrap();
+        resizer.source_header = Some(header);
+        resizer.srgb_profile = Some(PathBuf::from("/icc/sRGB.icc"));
+        let convert = resizer.build_convert_args("in.jpg", "out.jpg", &size);
+        let profile_at = convert.iter().position(|a| a == "-profile").unwrap();
+        let resize_at = convert.iter().position(|a| a == "-resize").unwrap();
+        assert_eq!(convert[profile_at + 1], "/icc/sRGB.icc");
+        assert!(profile_at < resize_at);
+        assert!(convert.contains(&"-strip".to_string()));
+        let vips = resizer.build_vips_args("in.jpg", "out.jpg", &size);
+        assert!(vips.windows(2).any(|w| w == ["--export-profile", "srgb"]));
+    }
+}
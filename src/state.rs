@@ -19,6 +19,8 @@
 //! - Salvataggio in `~/.media-optimizer/processed_files_<hash>.json`
 //! - Tracking basato su modification time del file
 //! - Cleanup automatico di file inesistenti
+//! - Scrittura atomica (file temporaneo + rename): un'interruzione a metà
+//!   salvataggio non corrompe lo stato
 //! 
 //! ## Prevenzione rielaborazione:
 //! - Controlla se file è già stato processato
@@ -46,6 +48,7 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::fs;
 
 /// Information about a processed file
@@ -99,18 +102,7 @@ pub struct StateManager {
 impl StateManager {
     /// Create a new state manager for a specific media directory
     pub async fn new(media_dir: &Path) -> Result<Self> {
-        let state_dir = dirs::home_dir()
-            .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
-            .join(".media-optimizer");
-        
-        fs::create_dir_all(&state_dir).await?;
-        
-        // Create unique state file based on media directory hash
-        let mut hasher = Sha256::new();
-        hasher.update(media_dir.to_string_lossy().as_bytes());
-        let hash = hex::encode(hasher.finalize())[..16].to_string();
-        
-        let state_file_path = state_dir.join(format!("processed_files_{}.json", hash));
+        let state_file_path = state_file_path(media_dir, "processed_files").await?;
         
         let state = if state_file_path.exists() {
             let content = fs::read_to_string(&state_file_path).await?;
@@ -128,8 +120,7 @@ impl StateManager {
     /// Save current state to file
     pub async fn save(&self) -> Result<()> {
         let content = serde_json::to_string_pretty(&self.state)?;
-        fs::write(&self.state_file_path, content).await?;
-        Ok(())
+        write_atomic(&self.state_file_path, content.as_bytes()).await
     }
     
     /// Check if a file has been processed and is up to date
@@ -192,3 +183,37 @@ impl StateManager {
         Ok(())
     }
 }
+
+/// Path di un file di stato per `media_dir` in `~/.media-optimizer`.
+///
+/// Il nome è `<prefix>_<hash>.json`, con l'hash del path della directory media.
+pub(crate) async fn state_file_path(media_dir: &Path, prefix: &str) -> Result<PathBuf> {
+    let state_dir = dirs::home_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+        .join(".media-optimizer");
+    
+    fs::create_dir_all(&state_dir).await?;
+    
+    // Create unique state file based on media directory hash
+    let mut hasher = Sha256::new();
+    hasher.update(media_dir.to_string_lossy().as_bytes());
+    let hash = hex::encode(hasher.finalize())[..16].to_string();
+    
+    Ok(state_dir.join(format!("{}_{}.json", prefix, hash)))
+}
+
+/// Scrive `content` in un file temporaneo accanto a `path` e lo rinomina
+pub(crate) async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    // Un nome per scrittura: più task possono salvare lo stesso file insieme
+    static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".{}.{}.tmp", std::process::id(), NEXT_TEMP.fetch_add(1, Ordering::Relaxed)));
+    let temp_path = path.with_file_name(temp_name);
+    
+    fs::write(&temp_path, content).await?;
+    if let Err(e) = fs::rename(&temp_path, path).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! # Storyboard Module
+//!
+//! Sprite di frame (contact sheet) con indice WebVTT per lo scrubbing dei video.
+//!
+//! ## Responsabilità:
+//! - Calcola la griglia dello sprite da un [`StoryboardProfile`] e da [`VideoInfo`]
+//! - Costruisce gli argomenti ffmpeg (`fps` + `scale` + `tile`) per un solo sprite
+//! - Genera la traccia WebVTT che associa intervalli di tempo a coordinate
+//!
+//! La generazione vera e propria è in `VideoProcessor::create_storyboards`.
+//!
+//! ## Struttura Output
+//! ```text
+//! /output/storyboards/scrub/trip/
+//! ├── clip.jpg   (sprite: 10 tile per riga)
+//! └── clip.vtt   (indice WebVTT)
+//! ```
+//!
+//! ## Formato WebVTT:
+//! Ogni cue punta a un tile con un media fragment `#xywh`, il formato letto
+//! dai player (video.js, Plyr, JW Player) per le anteprime sulla timeline:
+//! ```text
+//! WEBVTT
+//!
+//! 00:00:00.000 --> 00:00:06.000
+//! clip.jpg#xywh=0,0,160,90
+//! ```
+//!
+//! ## Campionamento:
+//! I frame sono equidistanti, al più uno al secondo. Si decodificano solo i
+//! keyframe (`-skip_frame nokey`): lo sprite di un video lungo si crea in una
+//! frazione del tempo di una decodifica completa, con una precisione pari alla
+//! distanza tra keyframe.
+
+use crate::config::StoryboardProfile;
+use crate::utils::to_string_vec;
+use crate::video_color::TONE_MAP_FILTER;
+use crate::video_processor::VideoInfo;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Griglia di uno sprite
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoryboardLayout {
+    /// Frame effettivamente campionati
+    pub frames: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Secondi coperti da ogni tile
+    pub interval: f64,
+    /// Durata del video in secondi
+    pub duration: f64,
+}
+
+impl StoryboardLayout {
+    /// Calcola la griglia; None se la durata del video è sconosciuta
+    pub fn new(profile: &StoryboardProfile, info: &VideoInfo) -> Option<Self> {
+        if info.duration <= 0.0 {
+            return None;
+        }
+        let frames = profile.frames.min(info.duration.floor() as u32).max(1);
+        let columns = profile.columns.min(frames);
+        let rows = frames.div_ceil(columns);
+
+        // Dimensioni visualizzate (rotazione applicata); 16:9 se sconosciute
+        let (width, height) = match info.display_dimensions() {
+            (0, _) | (_, 0) => (16, 9),
+            dimensions => dimensions,
+        };
+        let tile_height = ((profile.tile_width as f64 * height as f64 / width as f64 / 2.0).round() as u32 * 2).max(2);
+
+        Some(Self {
+            frames,
+            columns,
+            rows,
+            tile_width: profile.tile_width,
+            tile_height,
+            interval: info.duration / frames as f64,
+            duration: info.duration,
+        })
+    }
+
+    /// Dimensioni dello sprite in pixel
+    pub fn sheet_size(&self) -> (u32, u32) {
+        (self.columns * self.tile_width, self.rows * self.tile_height)
+    }
+
+    /// Catena di filtri: un frame ogni `interval`, ridotto e affiancato
+    pub fn filter(&self) -> String {
+        format!(
+            "fps=1/{:.6},scale={}:{},setsar=1,tile={}x{}",
+            self.interval, self.tile_width, self.tile_height, self.columns, self.rows
+        )
+    }
+
+    /// Argomenti ffmpeg per lo sprite (`tone_map`: sorgente HDR da convertire in SDR)
+    pub fn ffmpeg_args(&self, input_path: &Path, tone_map: bool, output_path: &Path) -> Vec<String> {
+        // Tone mapping dopo fps: solo sui frame campionati
+        let filter = match tone_map {
+            true => self.filter().replacen(",scale=", &format!(",{},scale=", TONE_MAP_FILTER), 1),
+            false => self.filter(),
+        };
+        to_string_vec([
+            "-skip_frame", "nokey",
+            "-i", &input_path.to_string_lossy(),
+            "-map", "0:v:0",
+            "-an", "-sn", "-dn",
+            "-vf", &filter,
+            "-frames:v", "1",
+            "-update", "1",
+            "-q:v", "4",
+            "-map_metadata", "-1",
+            "-y", &output_path.to_string_lossy(),
+        ])
+    }
+
+    /// Traccia WebVTT; `sprite` è il riferimento allo sprite (relativo al .vtt)
+    pub fn webvtt(&self, sprite: &str) -> String {
+        let mut vtt = String::from("WEBVTT\n");
+        for index in 0..self.frames {
+            let start = index as f64 * self.interval;
+            let end = if index + 1 == self.frames { self.duration } else { start + self.interval };
+            let x = (index % self.columns) * self.tile_width;
+            let y = (index / self.columns) * self.tile_height;
+            let _ = write!(
+                vtt,
+                "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+                vtt_timestamp(start), vtt_timestamp(end), sprite, x, y, self.tile_width, self.tile_height
+            );
+        }
+        vtt
+    }
+}
+
+/// Timestamp WebVTT `hh:mm:ss.mmm`
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video_processor::fixtures;
+
+    fn video(duration: f64, width: u32, height: u32, rotation: u32) -> VideoInfo {
+        VideoInfo { rotation, ..fixtures::video(width, height, duration) }
+    }
+
+    #[test]
+    fn test_storyboard_layout() {
+        let profile = StoryboardProfile::new(100, 10, 160);
+        let layout = StoryboardLayout::new(&profile, &video(600.0, 1920, 1080, 0)).unwrap();
+        assert_eq!((layout.frames, layout.columns, layout.rows), (100, 10, 10));
+        assert_eq!((layout.tile_width, layout.tile_height), (160, 90));
+        assert_eq!(layout.sheet_size(), (1600, 900));
+        assert_eq!(layout.interval, 6.0);
+        assert_eq!(layout.filter(), "fps=1/6.000000,scale=160:90,setsar=1,tile=10x10");
+        let args = layout.ffmpeg_args(Path::new("/in/hdr.mov"), true, Path::new("/out/hdr.jpg"));
+        assert!(args.contains(&format!("fps=1/6.000000,{},scale=160:90,setsar=1,tile=10x10", TONE_MAP_FILTER)));
+
+        // Al più un frame al secondo; verticale (rotazione) -> tile alti
+        let layout = StoryboardLayout::new(&profile, &video(12.5, 3840, 2160, 90)).unwrap();
+        assert_eq!((layout.frames, layout.columns, layout.rows), (12, 10, 2));
+        assert_eq!(layout.tile_height, 284);
+
+        assert_eq!(StoryboardLayout::new(&profile, &video(0.0, 1920, 1080, 0)), None);
+    }
+
+    #[test]
+    fn test_webvtt_track() {
+        let profile = StoryboardProfile::new(3, 2, 160);
+        let layout = StoryboardLayout::new(&profile, &video(3700.5, 1280, 720, 0)).unwrap();
+        let vtt = layout.webvtt("clip.jpg");
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\
+             \n00:00:00.000 --> 00:20:33.500\nclip.jpg#xywh=0,0,160,90\n\
+             \n00:20:33.500 --> 00:41:07.000\nclip.jpg#xywh=160,0,160,90\n\
+             \n00:41:07.000 --> 01:01:40.500\nclip.jpg#xywh=0,90,160,90\n"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! # Streaming Module
+//!
+//! Packaging dei video lunghi per lo streaming adattivo (HLS o DASH).
+//!
+//! ## Responsabilità:
+//! - Sceglie i gradini della ladder adatti alla sorgente (mai upscale)
+//! - Costruisce un unico comando ffmpeg che codifica tutti i gradini
+//! - Descrive il bundle prodotto ([`StreamingBundle`])
+//!
+//! Il packaging vero e proprio è in `VideoProcessor::package_streaming`; il
+//! bundle viene registrato nello state come un solo file elaborato.
+//!
+//! ## Struttura Output
+//! ```text
+//! /output/trip/talk.hls/            /output/trip/talk.dash/
+//! ├── master.m3u8                   ├── manifest.mpd
+//! ├── stream_0/ (audio)             ├── master.m3u8 (HLS sugli stessi segmenti)
+//! │   ├── playlist.m3u8             ├── media_0.m3u8 ...
+//! │   ├── init.mp4                  ├── init_0.m4s ...
+//! │   └── segment_00001.m4s ...     └── segment_0_00001.m4s ...
+//! └── stream_1/ (1080p) ...
+//! ```
+//!
+//! ## Codifica:
+//! - Segmenti fMP4 da [`SEGMENT_SECONDS`] secondi, keyframe forzati a ogni
+//!   confine di segmento: tutti i gradini sono allineati e il player può
+//!   cambiare qualità a ogni segmento
+//! - Video con il codec configurato, in bitrate medio con `maxrate` al 107%
+//!   e buffer di 1,5 volte il bitrate
+//! - Una sola traccia audio AAC stereo, condivisa da tutti i gradini
+//! - Il gradino si riferisce al lato corto: 1080 è 1920x1080 o 1080x1920
+//! - Colore e HDR come nella codifica singola (`video_color`): con il tone
+//!   mapping il filtro viene applicato una sola volta, prima dello split
+
+use crate::config::{AudioCodec, Config, StreamingFormat, StreamingRendition};
+use crate::utils::{parse_bitrate, to_string_vec};
+use crate::video_codec::VideoEncoder;
+use crate::video_color::{ColorPlan, TONE_MAP_FILTER};
+use crate::video_processor::VideoInfo;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Durata dei segmenti in secondi
+pub const SEGMENT_SECONDS: u32 = 6;
+
+impl StreamingFormat {
+    /// Suffisso della directory del bundle (`talk.hls`)
+    pub fn bundle_extension(self) -> &'static str {
+        self.as_str()
+    }
+
+    /// Manifest principale del bundle
+    pub fn manifest_name(self) -> &'static str {
+        match self {
+            Self::Hls => "master.m3u8",
+            Self::Dash => "manifest.mpd",
+        }
+    }
+}
+
+/// Gradino effettivo, con le dimensioni di output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LadderRung {
+    pub width: u32,
+    pub height: u32,
+    /// Bitrate video in bit/s
+    pub bitrate: u64,
+}
+
+/// Bundle di streaming prodotto per un video
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingBundle {
+    /// Directory del bundle
+    pub dir: PathBuf,
+    /// Manifest principale (master playlist o MPD)
+    pub manifest: PathBuf,
+    /// Dimensione totale di playlist e segmenti
+    pub size: u64,
+    /// Numero di gradini video
+    pub renditions: usize,
+}
+
+/// Arrotonda al pari più vicino (richiesto da yuv420p)
+fn even(value: f64) -> u32 {
+    ((value / 2.0).round() as u32 * 2).max(2)
+}
+
+/// Gradini della ladder non più grandi della sorgente, dal più alto.
+///
+/// Una sorgente più piccola del gradino più basso viene codificata alla sua
+/// risoluzione con il bitrate di quel gradino.
+pub fn select_rungs(ladder: &[StreamingRendition], info: &VideoInfo) -> Vec<LadderRung> {
+    let (width, height) = match info.display_dimensions() {
+        (0, _) | (_, 0) => (1920, 1080),
+        dimensions => dimensions,
+    };
+    let short_edge = width.min(height);
+    let size_for = |short: u32| {
+        let long = even(short as f64 * width.max(height) as f64 / short_edge as f64);
+        if width >= height { (long, short) } else { (short, long) }
+    };
+
+    let mut ladder: Vec<&StreamingRendition> = ladder.iter().collect();
+    ladder.sort_by_key(|rendition| std::cmp::Reverse(rendition.height));
+    ladder.dedup_by_key(|rendition| rendition.height);
+
+    let mut rungs: Vec<LadderRung> = ladder.iter()
+        .filter(|rendition| rendition.height <= short_edge)
+        .filter_map(|rendition| {
+            let (width, height) = size_for(rendition.height);
+            parse_bitrate(&rendition.bitrate).map(|bitrate| LadderRung { width, height, bitrate })
+        })
+        .collect();
+
+    if rungs.is_empty() {
+        if let Some(bitrate) = ladder.last().and_then(|lowest| parse_bitrate(&lowest.bitrate)) {
+            let (width, height) = size_for(even(short_edge as f64 - 1.0));
+            rungs.push(LadderRung { width, height, bitrate });
+        }
+    }
+    rungs
+}
+
+/// Argomenti ffmpeg per codificare e segmentare tutti i gradini in `output_dir`
+pub fn package_args(
+    config: &Config,
+    format: StreamingFormat,
+    encoder: &VideoEncoder,
+    info: &VideoInfo,
+    rungs: &[LadderRung],
+    input_path: &Path,
+    output_dir: &Path,
+) -> Vec<String> {
+    let has_audio = info.audio_codec.is_some() && config.audio_codec != AudioCodec::Disabled;
+    let color = ColorPlan::new(config, Some(&info.color));
+
+    // [0:v] -> (tone mapping) -> split -> una scala per gradino
+    let (mut graph, input) = if color.tone_map {
+        (format!("[0:v]{}[t];", TONE_MAP_FILTER), "[t]")
+    } else {
+        (String::new(), "[0:v]")
+    };
+    if rungs.len() > 1 {
+        let outputs: String = (0..rungs.len()).map(|i| format!("[s{}]", i)).collect();
+        graph.push_str(&format!("{}split={}{};", input, rungs.len(), outputs));
+    }
+    let scales: Vec<String> = rungs.iter().enumerate()
+        .map(|(i, rung)| {
+            let source = if rungs.len() > 1 { format!("[s{}]", i) } else { input.to_string() };
+            format!("{}scale={}:{},setsar=1[v{}]", source, rung.width, rung.height, i)
+        })
+        .collect();
+    graph.push_str(&scales.join(";"));
+
+    let mut args = to_string_vec(["-i", &input_path.to_string_lossy(), "-filter_complex", &graph]);
+    for i in 0..rungs.len() {
+        args.extend(to_string_vec(["-map", &format!("[v{}]", i)]));
+    }
+    if has_audio {
+        args.extend(to_string_vec(["-map", "0:a:0"]));
+    }
+
+    args.extend(to_string_vec(["-c:v", encoder.name]));
+    let speed = encoder.speed(config.video_preset);
+    match encoder.name {
+        "libaom-av1" => args.extend(to_string_vec(["-cpu-used", &speed, "-row-mt", "1"])),
+        _ => args.extend(to_string_vec(["-preset", &speed])),
+    }
+    let mut params = color.encoder_params(encoder.name);
+    if encoder.name == "libx265" {
+        params.insert(0, "log-level=error".to_string());
+        args.extend(to_string_vec(["-tag:v", "hvc1", "-x265-params", &params.join(":")]));
+    } else if !params.is_empty() {
+        args.extend(to_string_vec(["-svtav1-params", &params.join(":")]));
+    }
+    for (i, rung) in rungs.iter().enumerate() {
+        args.extend(to_string_vec([
+            &format!("-b:v:{}", i), &rung.bitrate.to_string(),
+            &format!("-maxrate:v:{}", i), &(rung.bitrate * 107 / 100).to_string(),
+            &format!("-bufsize:v:{}", i), &(rung.bitrate * 3 / 2).to_string(),
+        ]));
+    }
+    args.extend(color.args());
+    args.extend(to_string_vec(["-force_key_frames", &format!("expr:gte(t,n_forced*{})", SEGMENT_SECONDS)]));
+
+    if has_audio {
+        args.extend(to_string_vec(["-c:a", "aac", "-b:a", &config.audio_bitrate, "-ac", "2"]));
+        if let Some(lufs) = config.loudness_target {
+            args.extend(to_string_vec(["-af", &format!("loudnorm=I={}:TP=-1.5:LRA=11", lufs), "-ar", "48000"]));
+        }
+    }
+    args.extend(to_string_vec(["-map_metadata", "-1"]));
+
+    let output_dir = output_dir.to_string_lossy();
+    match format {
+        StreamingFormat::Hls => {
+            // L'audio è lo stream 0 del gruppo, condiviso da tutti i gradini
+            let mut stream_map: Vec<String> = Vec::new();
+            if has_audio {
+                stream_map.push("a:0,agroup:audio,default:yes".to_string());
+            }
+            stream_map.extend((0..rungs.len()).map(|i| {
+                if has_audio { format!("v:{},agroup:audio", i) } else { format!("v:{}", i) }
+            }));
+            args.extend(to_string_vec([
+                "-f", "hls",
+                "-hls_time", &SEGMENT_SECONDS.to_string(),
+                "-hls_playlist_type", "vod",
+                "-hls_segment_type", "fmp4",
+                "-hls_flags", "independent_segments",
+                "-hls_fmp4_init_filename", "init.mp4",
+                "-hls_segment_filename", &format!("{}/stream_%v/segment_%05d.m4s", output_dir),
+                "-master_pl_name", format.manifest_name(),
+                "-var_stream_map", &stream_map.join(" "),
+                &format!("{}/stream_%v/playlist.m3u8", output_dir),
+            ]));
+        }
+        StreamingFormat::Dash => {
+            let adaptation_sets = if has_audio { "id=0,streams=v id=1,streams=a" } else { "id=0,streams=v" };
+            args.extend(to_string_vec([
+                "-f", "dash",
+                "-seg_duration", &SEGMENT_SECONDS.to_string(),
+                "-use_template", "1",
+                "-use_timeline", "1",
+                "-init_seg_name", "init_$RepresentationID$.m4s",
+                "-media_seg_name", "segment_$RepresentationID$_$Number%05d$.m4s",
+                "-adaptation_sets", adaptation_sets,
+                "-hls_playlist", "1",
+                "-hls_master_name", "master.m3u8",
+                &format!("{}/{}", output_dir, format.manifest_name()),
+            ]));
+        }
+    }
+    args
+}
+
+/// Numero di stream (audio + gradini) per cui HLS crea una sottodirectory
+pub fn stream_count(config: &Config, info: &VideoInfo, rungs: &[LadderRung]) -> usize {
+    let has_audio = info.audio_codec.is_some() && config.audio_codec != AudioCodec::Disabled;
+    rungs.len() + usize::from(has_audio)
+}
+
+/// Dimensione totale dei file di un bundle
+pub fn bundle_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VideoCodec;
+    use crate::video_processor::fixtures;
+
+    fn video(width: u32, height: u32, rotation: u32, audio: bool) -> VideoInfo {
+        VideoInfo {
+            bitrate: 12_000_000,
+            audio_codec: audio.then(|| "aac".to_string()),
+            audio_channels: audio.then_some(2),
+            rotation,
+            ..fixtures::video(width, height, 600.0)
+        }
+    }
+
+    #[test]
+    fn test_select_rungs() {
+        let ladder = StreamingRendition::default_ladder();
+        let rungs = select_rungs(&ladder, &video(1280, 720, 0, true));
+        assert_eq!(rungs, vec![
+            LadderRung { width: 1280, height: 720, bitrate: 2_800_000 },
+            LadderRung { width: 854, height: 480, bitrate: 1_400_000 },
+            LadderRung { width: 640, height: 360, bitrate: 800_000 },
+        ]);
+
+        // Verticale (rotazione 90°): il gradino è il lato corto
+        let rungs = select_rungs(&ladder, &video(3840, 2160, 90, true));
+        assert_eq!(rungs[0], LadderRung { width: 1080, height: 1920, bitrate: 5_000_000 });
+
+        // Sorgente più piccola del gradino più basso: risoluzione originale
+        let rungs = select_rungs(&ladder, &video(320, 240, 0, true));
+        assert_eq!(rungs, vec![LadderRung { width: 320, height: 240, bitrate: 800_000 }]);
+    }
+
+    #[test]
+    fn test_package_args() {
+        let config = Config::default();
+        let encoder = VideoEncoder { codec: VideoCodec::H264, name: "libx264" };
+        let info = video(1920, 1080, 0, true);
+        let rungs = select_rungs(&[StreamingRendition::new(1080, "5000k"), StreamingRendition::new(720, "2800k")], &info);
+        assert_eq!(stream_count(&config, &info, &rungs), 3);
+
+        let args = package_args(&config, StreamingFormat::Hls, &encoder, &info, &rungs, Path::new("/in/talk.mov"), Path::new("/out/.staging"));
+        assert!(args.windows(2).any(|w| w == ["-filter_complex", "[0:v]split=2[s0][s1];[s0]scale=1920:1080,setsar=1[v0];[s1]scale=1280:720,setsar=1[v1]"]));
+        assert!(args.windows(2).any(|w| w == ["-b:v:1", "2800000"]));
+        assert!(args.windows(2).any(|w| w == ["-maxrate:v:0", "5350000"]));
+        assert!(args.windows(2).any(|w| w == ["-force_key_frames", "expr:gte(t,n_forced*6)"]));
+        assert!(args.windows(2).any(|w| w == ["-var_stream_map", "a:0,agroup:audio,default:yes v:0,agroup:audio v:1,agroup:audio"]));
+        assert_eq!(args.last().unwrap(), "/out/.staging/stream_%v/playlist.m3u8");
+
+        // HDR convertito in SDR una sola volta, prima dello split
+        let mut hdr = info.clone();
+        hdr.color.transfer = Some("smpte2084".to_string());
+        let tone_map = Config { hdr_policy: crate::config::HdrPolicy::ToneMap, ..Config::default() };
+        let args = package_args(&tone_map, StreamingFormat::Hls, &encoder, &hdr, &rungs, Path::new("/in/talk.mov"), Path::new("/out/.staging"));
+        let graph = &args[args.iter().position(|a| a == "-filter_complex").unwrap() + 1];
+        assert!(graph.starts_with(&format!("[0:v]{}[t];[t]split=2[s0][s1];", TONE_MAP_FILTER)));
+
+        // DASH senza audio: un solo adaptation set e playlist HLS condivise
+        let silent = video(1920, 1080, 0, false);
+        let args = package_args(&config, StreamingFormat::Dash, &encoder, &silent, &rungs[..1], Path::new("/in/talk.mov"), Path::new("/out/.staging"));
+        assert!(args.windows(2).any(|w| w == ["-filter_complex", "[0:v]scale=1920:1080,setsar=1[v0]"]));
+        assert!(args.windows(2).any(|w| w == ["-adaptation_sets", "id=0,streams=v"]));
+        assert!(!args.contains(&"0:a:0".to_string()));
+        assert_eq!(args.last().unwrap(), "/out/.staging/manifest.mpd");
+    }
+}
@@ -213,8 +213,8 @@ impl ToolPathResolver {
             .collect()
     }
 
-    /// Helper methods for specific tools (convenience wrappers)
-    
+    // Helper methods for specific tools (convenience wrappers)
+
     /// Get path to cwebp tool
     pub fn cwebp(&self) -> Option<PathBuf> {
         self.resolve_tool("cwebp")
@@ -269,7 +269,7 @@ impl ToolPathResolver {
     /// Get a report of tool availability
     pub fn get_tools_report(&self) -> String {
         let mut report = String::new();
-        report.push_str(&format!("Tool Path Resolver Report\n"));
+        report.push_str("Tool Path Resolver Report\n");
         report.push_str(&format!("Development mode: {}\n", self.is_development));
         if cfg!(target_os = "linux") {
             report.push_str("Platform: Linux (using system tools)\n");
@@ -293,7 +293,7 @@ impl ToolPathResolver {
                     Ok(path) => {
                         report.push_str(&format!("  ✅ {} -> {:?}\n", tool, path));
                     }
-                    Err(msg) => {
+                    Err(_) => {
                         if cfg!(target_os = "linux") {
                             let install_cmd = self.get_linux_install_instructions(tool);
                             report.push_str(&format!("  ❌ {} (install with: {})\n", tool, install_cmd));
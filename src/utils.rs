@@ -1,7 +1,8 @@
 //! # Utility Functions Module
 //!
 //! This module provides utility functions that improve code readability
-//! and reduce boilerplate across the application.
+//! and reduce boilerplate across the application, plus the bitrate and
+//! file size parsers shared by configuration, scheduling and encoding.
 
 /// Converts a vector of string-like items to Vec<String>.
 /// 
@@ -20,7 +21,7 @@
 /// 
 /// # Example
 /// ```rust
-/// use crate::utils::to_string_vec;
+/// use space_media_optimizer::utils::to_string_vec;
 /// 
 /// // Instead of:
 /// let args = vec![
@@ -35,6 +36,7 @@
 /// // Also works with mixed types:
 /// let quality = 85;
 /// let args = to_string_vec(["--quality", &quality.to_string(), "--optimize"]);
+/// assert_eq!(args, ["--quality", "85", "--optimize"]);
 /// ```
 pub fn to_string_vec<T, I>(items: I) -> Vec<String>
 where
@@ -51,18 +53,43 @@ where
 /// 
 /// # Example
 /// ```rust
-/// use crate::args;
+/// use space_media_optimizer::args;
 /// 
 /// let quality = 85;
 /// let args = args!["--quality", quality, "--optimize"];
+/// assert_eq!(args, ["--quality", "85", "--optimize"]);
 /// ```
 #[macro_export]
 macro_rules! args {
     [$($item:expr),* $(,)?] => {
-        $crate::utils::to_string_vec([$($item),*])
+        vec![$($item.to_string()),*]
     };
 }
 
+/// Parses an ffmpeg-style bitrate ("128k", "1.5M", "96000") into bits per second.
+pub fn parse_bitrate(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last()? {
+        'k' | 'K' => (&value[..value.len() - 1], 1_000.0),
+        'm' | 'M' => (&value[..value.len() - 1], 1_000_000.0),
+        _ => (value, 1.0),
+    };
+    number.parse::<f64>().ok().map(|n| (n * multiplier) as u64)
+}
+
+/// Parses a file size ("700M", "1.5G", "650MB", "500000") into bytes (binary units, as shown in the logs).
+pub fn parse_file_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = value.strip_suffix(['B', 'b']).filter(|rest| rest.ends_with(|c: char| c.is_ascii_alphabetic())).unwrap_or(value);
+    let (number, multiplier) = match value.chars().last()? {
+        'k' | 'K' => (&value[..value.len() - 1], 1024.0),
+        'm' | 'M' => (&value[..value.len() - 1], 1024.0 * 1024.0),
+        'g' | 'G' => (&value[..value.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        _ => (value, 1.0),
+    };
+    number.parse::<f64>().ok().filter(|n| *n >= 0.0).map(|n| (n * multiplier) as u64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,4 +119,21 @@ mod tests {
         let result = args!["--quality", quality, "--optimize"];
         assert_eq!(result, vec!["--quality".to_string(), "85".to_string(), "--optimize".to_string()]);
     }
+
+    #[test]
+    fn test_parse_bitrate() {
+        assert_eq!(parse_bitrate("128k"), Some(128_000));
+        assert_eq!(parse_bitrate("1.5M"), Some(1_500_000));
+        assert_eq!(parse_bitrate("96000"), Some(96_000));
+        assert_eq!(parse_bitrate(""), None);
+    }
+
+    #[test]
+    fn test_parse_file_size() {
+        assert_eq!(parse_file_size("700M"), Some(700 * 1024 * 1024));
+        assert_eq!(parse_file_size("1.5GB"), Some(1536 * 1024 * 1024));
+        assert_eq!(parse_file_size("500000"), Some(500_000));
+        assert_eq!(parse_file_size("B"), None);
+        assert_eq!(parse_file_size("-1M"), None);
+    }
 }
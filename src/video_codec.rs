@@ -0,0 +1,550 @@
+//! # Video Codec Module
+//!
+//! Traduce il [`VideoCodec`] configurato negli argomenti ffmpeg corretti.
+//!
+//! ## Responsabilità:
+//! - Sceglie l'encoder ffmpeg disponibile per il codec (`ffmpeg -encoders`)
+//! - Container di output, pixel format, tag e codec audio compatibile
+//! - Converte il CRF (scala x264, 0-51) nella scala dell'encoder
+//!
+//! ## Codec supportati:
+//! | Codec | Encoder                    | Container | Audio |
+//! |-------|----------------------------|-----------|-------|
+//! | H.264 | libx264                    | MP4       | AAC   |
+//! | HEVC  | libx265 (`-tag:v hvc1`)    | MP4       | AAC   |
+//! | AV1   | libsvtav1, libaom-av1      | MP4       | AAC   |
+//! | VP9   | libvpx-vp9                 | WebM      | Opus  |
+//!
+//! `hvc1` (invece del default `hev1`) è necessario perché QuickTime, Safari e
+//! iOS riproducano HEVC in MP4.
+//!
+//! ## Audio:
+//! La colonna Audio è il default (`AudioCodec::Auto`). `config.audio_codec`
+//! può imporre AAC o Opus (anche in MP4, tipico con AV1), copiare la traccia
+//! (`copy`) o rimuoverla (`none`). Downmix (`-ac`) e `loudnorm` richiedono
+//! la ricodifica; dopo `loudnorm` l'audio torna a 48 kHz, perché il filtro
+//! lavora internamente a 192 kHz.
+//!
+//! ## Preset e tune:
+//! `config.video_preset` usa i nomi x264; per gli altri encoder viene
+//! tradotto nella scala equivalente (più lento = più compresso):
+//!
+//! | Preset    | x264/x265 | SVT-AV1 `-preset` | libaom `-cpu-used` | libvpx `-cpu-used` |
+//! |-----------|-----------|-------------------|--------------------|--------------------|
+//! | ultrafast | ultrafast | 12                | 8                  | 5                  |
+//! | medium    | medium    | 7                 | 4                  | 3                  |
+//! | veryslow  | veryslow  | 4                 | 1                  | 0                  |
+//!
+//! Senza preset ogni encoder usa il proprio default (x264 veryslow, x265 slow,
+//! SVT-AV1 6, libaom 4, libvpx 2). Le opzioni che un encoder non supporta
+//! (es. `tune=film` su x265, il livello su AV1/VP9) vengono ignorate con un log.
+//!
+//! ## Bitrate:
+//! Di default la qualità è costante (CRF). Con un bitrate obiettivo
+//! ([`RateControl`]) si usano i due passaggi di ffmpeg (`-pass` e
+//! `-passlogfile`; per x265 `pass`/`stats` in `-x265-params`). SVT-AV1 non
+//! espone i due passaggi a ffmpeg: codifica in VBR a passaggio singolo.
+
+use crate::config::{AudioCodec, Config, VideoCodec, VideoPreset, VideoTune};
+use crate::error::OptimizeError;
+use crate::platform::PlatformCommands;
+use crate::utils::to_string_vec;
+use crate::video_color::ColorPlan;
+use crate::video_processor::VideoInfo;
+use crate::video_streams::StreamKind;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+impl VideoCodec {
+    /// Encoder ffmpeg in ordine di preferenza
+    pub fn encoders(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &["libx264"],
+            VideoCodec::Hevc => &["libx265"],
+            VideoCodec::Av1 => &["libsvtav1", "libaom-av1"],
+            VideoCodec::Vp9 => &["libvpx-vp9"],
+        }
+    }
+
+    /// Estensione del container di output
+    pub fn container_extension(self) -> &'static str {
+        match self {
+            VideoCodec::Vp9 => "webm",
+            _ => "mp4",
+        }
+    }
+
+    /// Indica se l'output è WebM (Matroska): niente movflags né exiftool
+    pub fn is_webm(self) -> bool {
+        self.container_extension() == "webm"
+    }
+
+    /// Indica se uno stream video (nome codec ffprobe) può stare nel container
+    /// di output senza ricodifica
+    pub fn container_accepts(self, stream_codec: &str) -> bool {
+        if self.is_webm() {
+            matches!(stream_codec, "vp9" | "vp8" | "av1")
+        } else {
+            matches!(stream_codec, "h264" | "hevc" | "av1")
+        }
+    }
+
+    /// Indica se uno stream audio (nome codec ffprobe) può essere copiato nel container
+    pub fn container_accepts_audio(self, stream_codec: &str) -> bool {
+        if self.is_webm() {
+            matches!(stream_codec, "opus" | "vorbis")
+        } else {
+            matches!(stream_codec, "aac" | "mp3" | "alac" | "ac3" | "eac3")
+        }
+    }
+
+    /// Encoder audio compatibile con il container
+    pub fn audio_encoder(self) -> &'static str {
+        if self.is_webm() { "libopus" } else { "aac" }
+    }
+
+    /// Encoder dei sottotitoli testuali compatibile con il container
+    pub fn subtitle_encoder(self) -> &'static str {
+        if self.is_webm() { "webvtt" } else { "mov_text" }
+    }
+}
+
+/// Encoder ffmpeg risolto per un codec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoEncoder {
+    pub codec: VideoCodec,
+    /// Nome dell'encoder ffmpeg (es. "libsvtav1")
+    pub name: &'static str,
+}
+
+impl VideoEncoder {
+    /// Sceglie il primo encoder del codec compilato nell'ffmpeg in uso
+    pub async fn resolve(codec: VideoCodec) -> Result<Self> {
+        let available = available_encoders().await;
+        codec.encoders()
+            .iter()
+            .find(|name| available.contains(**name))
+            .map(|name| Self { codec, name })
+            .ok_or_else(|| OptimizeError::MissingDependency(format!(
+                "ffmpeg has no encoder for {:?} (tried: {})",
+                codec,
+                codec.encoders().join(", ")
+            )).into())
+    }
+
+    /// Converte il CRF dalla scala x264 (0-51) a quella dell'encoder
+    pub fn crf(&self, x264_crf: u8) -> u8 {
+        match self.name {
+            "libx264" | "libx265" => x264_crf,
+            // libsvtav1, libaom-av1 e libvpx-vp9 usano 0-63
+            _ => ((x264_crf as u32 * 63 + 25) / 51) as u8,
+        }
+    }
+
+    /// Valore di velocità dell'encoder per `preset` (None = default dell'encoder)
+    pub fn speed(&self, preset: Option<VideoPreset>) -> String {
+        // Indicizzate da VideoPreset::index(): ultrafast ... veryslow
+        const SVTAV1: [u8; 9] = [12, 11, 10, 9, 8, 7, 6, 5, 4];
+        const AOM: [u8; 9] = [8, 7, 6, 6, 5, 4, 3, 2, 1];
+        const VPX: [u8; 9] = [5, 5, 4, 4, 3, 3, 2, 1, 0];
+
+        match (self.name, preset) {
+            ("libx264", None) => "veryslow".to_string(),
+            ("libx265", None) => "slow".to_string(),
+            ("libsvtav1", None) => "6".to_string(),
+            ("libaom-av1", None) => "4".to_string(),
+            ("libvpx-vp9", None) => "2".to_string(),
+            ("libsvtav1", Some(p)) => SVTAV1[p.index()].to_string(),
+            ("libaom-av1", Some(p)) => AOM[p.index()].to_string(),
+            ("libvpx-vp9", Some(p)) => VPX[p.index()].to_string(),
+            (_, Some(p)) => p.as_str().to_string(),
+            (_, None) => VideoPreset::Medium.as_str().to_string(),
+        }
+    }
+
+    /// Argomenti di encoding video (codec, qualità, velocità, tune, profilo, GOP)
+    pub fn video_args(&self, config: &Config) -> Vec<String> {
+        self.video_args_with_rate(config, &RateControl::Crf, &ColorPlan::new(config, None))
+    }
+
+    /// L'encoder accetta le opzioni ffmpeg dei due passaggi (SVT-AV1 no)
+    pub fn supports_two_pass(&self) -> bool {
+        self.name != "libsvtav1"
+    }
+
+    /// Come [`Self::video_args`], con il controllo del bitrate e il colore indicati
+    pub fn video_args_with_rate(&self, config: &Config, rate: &RateControl, color: &ColorPlan) -> Vec<String> {
+        let crf = self.crf(config.video_crf).to_string();
+        let speed = self.speed(config.video_preset);
+        // libaom e libvpx sono in modalità qualità costante solo con -b:v 0
+        let rate_args = match rate {
+            RateControl::Crf if matches!(self.name, "libaom-av1" | "libvpx-vp9") => to_string_vec(["-crf", &crf, "-b:v", "0"]),
+            RateControl::Crf => to_string_vec(["-crf", &crf]),
+            RateControl::Bitrate(bitrate) => to_string_vec(["-b:v", &bitrate.to_string()]),
+            RateControl::TwoPass { bitrate, pass, log_prefix } => {
+                let mut args = to_string_vec(["-b:v", &bitrate.to_string()]);
+                // x265 riceve passaggio e statistiche tra i propri parametri
+                if self.name != "libx265" && self.supports_two_pass() {
+                    args.extend(to_string_vec(["-pass", &pass.to_string(), "-passlogfile", &log_prefix.to_string_lossy()]));
+                }
+                args
+            }
+        };
+        let mut args = to_string_vec(["-c:v", self.name]);
+        // Parametri privati dell'encoder (-x265-params / -svtav1-params)
+        let mut params: Vec<String> = Vec::new();
+        let mut tune_applied = config.video_tune.is_none();
+        let mut level_applied = config.video_level.is_none();
+
+        match self.name {
+            "libx264" => {
+                args.extend(to_string_vec(["-preset", &speed]));
+                args.extend(rate_args);
+                if let Some(tune) = config.video_tune {
+                    args.extend(to_string_vec(["-tune", tune.as_str()]));
+                    tune_applied = true;
+                }
+                if let Some(ref level) = config.video_level {
+                    args.extend(to_string_vec(["-level", level]));
+                    level_applied = true;
+                }
+            }
+            "libx265" => {
+                args.extend(to_string_vec(["-preset", &speed]));
+                args.extend(rate_args);
+                args.extend(to_string_vec(["-tag:v", "hvc1"]));
+                // x265 non ha un tune "film"
+                if let Some(tune @ (VideoTune::Animation | VideoTune::Grain)) = config.video_tune {
+                    args.extend(to_string_vec(["-tune", tune.as_str()]));
+                    tune_applied = true;
+                }
+                params.push("log-level=error".to_string());
+                if let RateControl::TwoPass { pass, log_prefix, .. } = rate {
+                    params.push(format!("pass={}:stats={}", pass, x265_param_path(log_prefix)));
+                }
+                if let Some(ref level) = config.video_level {
+                    params.push(format!("level-idc={}", level));
+                    level_applied = true;
+                }
+            }
+            "libsvtav1" => {
+                args.extend(to_string_vec(["-preset", &speed]));
+                args.extend(rate_args);
+                match config.video_tune {
+                    // tune=0: ottimizzazione per qualità visiva invece che PSNR
+                    Some(VideoTune::Film) => params.push("tune=0".to_string()),
+                    // Sintesi della grana: la rimuove prima dell'encoding e la ricrea in decodifica
+                    Some(VideoTune::Grain) => params.push("film-grain=8".to_string()),
+                    _ => {}
+                }
+                tune_applied |= matches!(config.video_tune, Some(VideoTune::Film | VideoTune::Grain));
+            }
+            "libaom-av1" => {
+                args.extend(rate_args);
+                args.extend(to_string_vec(["-cpu-used", &speed, "-row-mt", "1"]));
+                if config.video_tune == Some(VideoTune::Grain) {
+                    args.extend(to_string_vec(["-denoise-noise-level", "8"]));
+                    tune_applied = true;
+                }
+            }
+            "libvpx-vp9" => {
+                args.extend(rate_args);
+                args.extend(to_string_vec(["-deadline", "good", "-cpu-used", &speed, "-row-mt", "1"]));
+                if config.video_tune == Some(VideoTune::Film) {
+                    args.extend(to_string_vec(["-tune-content", "film"]));
+                    tune_applied = true;
+                }
+            }
+            _ => args.extend(rate_args),
+        }
+
+        if !tune_applied {
+            debug!("{} has no equivalent for tune {:?}, ignoring it", self.name, config.video_tune);
+        }
+        if !level_applied {
+            debug!("{} does not support setting the level, ignoring {:?}", self.name, config.video_level);
+        }
+
+        if let Some(ref profile) = config.video_profile {
+            args.extend(to_string_vec(["-profile:v", profile]));
+        }
+        if let Some(interval) = config.keyframe_interval {
+            args.extend(to_string_vec(["-g", &interval.to_string()]));
+        }
+        params.extend(color.encoder_params(self.name));
+        if !params.is_empty() {
+            let option = if self.name == "libx265" { "-x265-params" } else { "-svtav1-params" };
+            args.extend(to_string_vec([option, &params.join(":")]));
+        }
+
+        // Formato pixel e tag colore: 8 bit 4:2:0, o 10 bit per l'HDR
+        args.extend(color.args());
+        args
+    }
+
+    /// Argomenti audio secondo `config.audio_codec`, canali e loudness.
+    ///
+    /// Le tracce vengono copiate con `copy`, o in remux (`prefer_copy`) se non
+    /// serve ricodificarle, purché il container accetti il codec di ogni traccia;
+    /// altrimenti vengono ricodificate (senza analisi `source` è sempre sicuro).
+    ///
+    /// # Arguments
+    /// * `config` - Codec, bitrate, canali e loudness audio
+    /// * `source` - Analisi ffprobe della sorgente, se disponibile
+    /// * `prefer_copy` - Copia la traccia quando il risultato non cambierebbe
+    pub fn audio_args(&self, config: &Config, source: Option<&VideoInfo>, prefer_copy: bool) -> Vec<String> {
+        if config.audio_codec == AudioCodec::Disabled {
+            return to_string_vec(["-an"]);
+        }
+
+        let source_codec = source.and_then(|info| info.audio_codec.as_deref());
+        // Downmix solo se la sorgente ha più canali (mai upmix)
+        let downmix = config.audio_channels
+            .filter(|max| source.and_then(|info| info.audio_channels).is_none_or(|channels| channels > *max));
+        let copy = match config.audio_codec {
+            AudioCodec::Copy => true,
+            AudioCodec::Auto => prefer_copy,
+            AudioCodec::Aac => prefer_copy && source_codec == Some("aac"),
+            AudioCodec::Opus => prefer_copy && source_codec == Some("opus"),
+            AudioCodec::Disabled => false,
+        };
+        let accepted = |codec: &str| self.codec.container_accepts_audio(codec)
+            || (config.audio_codec == AudioCodec::Opus && codec == "opus");
+        // Every mapped track shares `-c:a`: one incompatible track forces a transcode
+        let all_tracks_accepted = source.is_none_or(|info| info.streams.iter()
+            .filter(|stream| stream.kind == StreamKind::Audio)
+            .all(|stream| accepted(&stream.codec)));
+        if copy && downmix.is_none() && config.loudness_target.is_none() {
+            match source_codec {
+                Some(codec) if accepted(codec) && all_tracks_accepted => {
+                    return to_string_vec(["-c:a", "copy"]);
+                }
+                Some(codec) => debug!(
+                    "{} audio cannot be copied into {}: transcoding",
+                    codec, self.codec.container_extension()
+                ),
+                None => {}
+            }
+        }
+
+        let encoder = match config.audio_codec {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Opus => "libopus",
+            _ => self.codec.audio_encoder(),
+        };
+        let mut args = to_string_vec(["-c:a", encoder, "-b:a", &config.audio_bitrate]);
+        if let Some(channels) = downmix {
+            args.extend(to_string_vec(["-ac", &channels.to_string()]));
+        }
+        if let Some(lufs) = config.loudness_target {
+            args.extend(to_string_vec(["-af", &format!("loudnorm=I={}:TP=-1.5:LRA=11", lufs), "-ar", "48000"]));
+        }
+        args
+    }
+
+    /// Argomenti specifici del container
+    pub fn container_args(&self) -> Vec<String> {
+        if self.codec.is_webm() {
+            Vec::new()
+        } else {
+            to_string_vec(["-movflags", "use_metadata_tags"])
+        }
+    }
+
+    /// Argomenti del container per il remux: in MP4 l'atomo `moov` viene
+    /// spostato in testa (`+faststart`) per la riproduzione progressiva nel browser
+    pub fn remux_container_args(&self) -> Vec<String> {
+        if self.codec.is_webm() {
+            Vec::new()
+        } else {
+            to_string_vec(["-movflags", "+faststart+use_metadata_tags"])
+        }
+    }
+}
+
+/// Controllo del bitrate video
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateControl {
+    /// Qualità costante (`config.video_crf`)
+    Crf,
+    /// Bitrate medio in bit/s, in un solo passaggio
+    Bitrate(u64),
+    /// Passaggio `pass` (1 o 2) a bitrate medio; le statistiche del primo
+    /// passaggio sono nei file che iniziano con `log_prefix`
+    TwoPass { bitrate: u64, pass: u8, log_prefix: PathBuf },
+}
+
+/// Path per `-x265-params`: `:` separa i parametri e va protetto
+fn x265_param_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").replace(':', "\\:")
+}
+
+/// Estrae i nomi degli encoder dall'output di `ffmpeg -encoders`.
+///
+/// Le righe utili hanno la forma ` V....D libx264   libx264 H.264 / AVC ...`.
+pub fn parse_encoder_list(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let flags = parts.next()?;
+            let name = parts.next()?;
+            (flags.len() == 6).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Encoder disponibili nell'ffmpeg in uso, letti una sola volta per processo
+pub async fn available_encoders() -> &'static HashSet<String> {
+    static ENCODERS: OnceCell<HashSet<String>> = OnceCell::const_new();
+    ENCODERS
+        .get_or_init(|| async {
+            let ffmpeg_cmd = PlatformCommands::instance().get_command("ffmpeg");
+            match Command::new(ffmpeg_cmd).args(["-hide_banner", "-encoders"]).kill_on_drop(true).output().await {
+                Ok(output) => parse_encoder_list(&String::from_utf8_lossy(&output.stdout)),
+                Err(e) => {
+                    debug!("Failed to list ffmpeg encoders: {}", e);
+                    HashSet::new()
+                }
+            }
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimizer::path_resolver::PathResolver;
+    use std::path::Path;
+
+    fn encoder(codec: VideoCodec, name: &'static str) -> VideoEncoder {
+        VideoEncoder { codec, name }
+    }
+
+    #[test]
+    fn test_encoder_args_per_codec() {
+        let config = Config::default();
+
+        let h264 = encoder(VideoCodec::H264, "libx264").video_args(&config);
+        assert_eq!(&h264[..2], ["-c:v", "libx264"]);
+        assert!(h264.windows(2).any(|w| w == ["-crf", "26"]));
+
+        let hevc = encoder(VideoCodec::Hevc, "libx265").video_args(&config);
+        assert!(hevc.windows(2).any(|w| w == ["-tag:v", "hvc1"]));
+
+        // Scala 0-63: CRF 26 (x264) -> 32
+        let aom = encoder(VideoCodec::Av1, "libaom-av1").video_args(&config);
+        assert!(aom.windows(2).any(|w| w == ["-crf", "32"]));
+        assert!(aom.windows(2).any(|w| w == ["-b:v", "0"]));
+
+        let vp9 = encoder(VideoCodec::Vp9, "libvpx-vp9");
+        assert_eq!(vp9.audio_args(&config, None, false), ["-c:a", "libopus", "-b:a", "128k"]);
+        assert!(vp9.container_args().is_empty());
+        assert_eq!(encoder(VideoCodec::H264, "libx264").container_args(), ["-movflags", "use_metadata_tags"]);
+        assert_eq!(vp9.crf(51), 63);
+        assert_eq!(vp9.crf(0), 0);
+    }
+
+    #[test]
+    fn test_two_pass_rate_control() {
+        let config = Config::default();
+        let pass = |pass| RateControl::TwoPass { bitrate: 2_500_000, pass, log_prefix: PathBuf::from("/tmp/log/pass") };
+
+        let h264 = encoder(VideoCodec::H264, "libx264").video_args_with_rate(&config, &pass(1), &ColorPlan::new(&config, None));
+        assert!(h264.windows(2).any(|w| w == ["-b:v", "2500000"]));
+        assert!(h264.windows(4).any(|w| w == ["-pass", "1", "-passlogfile", "/tmp/log/pass"]));
+        assert!(!h264.contains(&"-crf".to_string()));
+
+        // x265: passaggio e statistiche nei parametri privati
+        let hevc = encoder(VideoCodec::Hevc, "libx265").video_args_with_rate(&config, &pass(2), &ColorPlan::new(&config, None));
+        assert!(hevc.windows(2).any(|w| w == ["-x265-params", "log-level=error:pass=2:stats=/tmp/log/pass"]));
+        assert!(!hevc.contains(&"-pass".to_string()));
+        assert_eq!(x265_param_path(Path::new("C:\\tmp\\pass")), "C\\:/tmp/pass");
+
+        // libaom: niente "-b:v 0" fuori dalla modalità CRF
+        let aom = encoder(VideoCodec::Av1, "libaom-av1").video_args_with_rate(&config, &pass(2), &ColorPlan::new(&config, None));
+        assert!(!aom.windows(2).any(|w| w == ["-b:v", "0"]));
+        assert!(aom.windows(2).any(|w| w == ["-pass", "2"]));
+
+        let svt = encoder(VideoCodec::Av1, "libsvtav1");
+        assert!(!svt.supports_two_pass());
+        let svt = svt.video_args_with_rate(&config, &RateControl::Bitrate(2_500_000), &ColorPlan::new(&config, None));
+        assert!(svt.windows(2).any(|w| w == ["-b:v", "2500000"]));
+        assert!(!svt.contains(&"-pass".to_string()));
+    }
+
+    #[test]
+    fn test_preset_tune_mapping() {
+        // Senza opzioni restano i default di ciascun encoder
+        let config = Config::default();
+        let h264 = encoder(VideoCodec::H264, "libx264").video_args(&config);
+        assert!(h264.windows(2).any(|w| w == ["-preset", "veryslow"]));
+        assert!(!h264.contains(&"-g".to_string()));
+
+        let config = Config {
+            video_preset: Some(VideoPreset::Fast),
+            video_tune: Some(VideoTune::Grain),
+            video_profile: Some("main".to_string()),
+            video_level: Some("4.1".to_string()),
+            keyframe_interval: Some(120),
+            ..Default::default()
+        };
+
+        let h264 = encoder(VideoCodec::H264, "libx264").video_args(&config);
+        assert!(h264.windows(2).any(|w| w == ["-preset", "fast"]));
+        assert!(h264.windows(2).any(|w| w == ["-tune", "grain"]));
+        assert!(h264.windows(2).any(|w| w == ["-level", "4.1"]));
+        assert!(h264.windows(2).any(|w| w == ["-profile:v", "main"]));
+        assert!(h264.windows(2).any(|w| w == ["-g", "120"]));
+
+        let hevc = encoder(VideoCodec::Hevc, "libx265").video_args(&config);
+        assert!(hevc.windows(2).any(|w| w == ["-x265-params", "log-level=error:level-idc=4.1"]));
+        assert!(!hevc.contains(&"-level".to_string()));
+
+        let svt = encoder(VideoCodec::Av1, "libsvtav1").video_args(&config);
+        assert!(svt.windows(2).any(|w| w == ["-preset", "8"]));
+        assert!(svt.windows(2).any(|w| w == ["-svtav1-params", "film-grain=8"]));
+
+        let vp9 = encoder(VideoCodec::Vp9, "libvpx-vp9").video_args(&config);
+        assert!(vp9.windows(2).any(|w| w == ["-cpu-used", "3"]));
+        assert!(!vp9.contains(&"-tune-content".to_string()));
+
+        // x265 non supporta tune=film: l'opzione viene ignorata
+        let film = Config { video_tune: Some(VideoTune::Film), ..Default::default() };
+        let hevc = encoder(VideoCodec::Hevc, "libx265").video_args(&film);
+        assert!(!hevc.contains(&"-tune".to_string()));
+    }
+
+    #[test]
+    fn test_parse_encoder_list() {
+        let output = "Encoders:\n V..... = Video\n ------\n V....D libx264              libx264 H.264\n V....D libsvtav1            SVT-AV1\n A....D aac                  AAC\n";
+        let encoders = parse_encoder_list(output);
+        assert!(encoders.contains("libx264"));
+        assert!(encoders.contains("libsvtav1"));
+        assert!(encoders.contains("aac"));
+        assert!(!encoders.contains("="));
+    }
+
+    #[test]
+    fn test_output_extension_follows_codec() {
+        let input = Path::new("/media/clip.mov");
+        let config = Config { video_codec: VideoCodec::Vp9, ..Default::default() };
+        let output = PathResolver::get_output_path(input, Path::new("/media"), &config).unwrap();
+        assert_eq!(output, Path::new("/media/clip.webm"));
+
+        let config = Config { video_codec: VideoCodec::Hevc, ..Default::default() };
+        let output = PathResolver::get_output_path(input, Path::new("/media"), &config).unwrap();
+        assert_eq!(output, Path::new("/media/clip.mp4"));
+
+        // Copia senza compressione: il container originale resta invariato
+        let config = Config { skip_video_compression: true, ..Default::default() };
+        let output = PathResolver::get_output_path(input, Path::new("/media"), &config).unwrap();
+        assert_eq!(output, Path::new("/media/clip.mov"));
+    }
+}
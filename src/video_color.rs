@@ -0,0 +1,390 @@
+//! # Video Color Module
+//!
+//! Spazio colore, HDR e profondità di bit dei video.
+//!
+//! ## Responsabilità:
+//! - Legge da ffprobe primarie, transfer, matrice, range, bit depth e i
+//!   metadati HDR10 (mastering display, content light level)
+//! - Decide come codificare il colore secondo `config.hdr_policy` ([`ColorPlan`])
+//! - Costruisce il filtro di tone mapping HDR -> SDR
+//!
+//! ## Politica HDR:
+//! | Sorgente | `preserve`                               | `tone-map`                 |
+//! |----------|------------------------------------------|----------------------------|
+//! | SDR      | 8 bit, tag colore della sorgente         | uguale                     |
+//! | HDR10    | 10 bit, tag BT.2020/PQ, mastering e CLL  | 8 bit BT.709 (tone mapping)|
+//! | HLG      | 10 bit, tag BT.2020/HLG                  | 8 bit BT.709 (tone mapping)|
+//!
+//! Senza i tag un player interpreta i valori come BT.709: un video HDR
+//! codificato a 8 bit senza segnalazione appare slavato. H.264 a 10 bit
+//! (High 10) è poco supportato dai player: per HDR conviene HEVC o AV1.
+//!
+//! ## Tone mapping:
+//! Usa `zscale` (libzimg), presente nelle build statiche più diffuse:
+//! linearizza, applica l'operatore `hable` e converte in BT.709. I poster e
+//! gli storyboard (JPEG/WebP, sempre SDR) delle sorgenti HDR vengono
+//! convertiti quando il filtro è disponibile, indipendentemente dalla politica.
+
+use crate::config::{Config, HdrPolicy};
+use crate::platform::PlatformCommands;
+use crate::utils::to_string_vec;
+use std::collections::HashSet;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+/// Catena di filtri HDR (PQ/HLG, BT.2020) -> SDR BT.709 8 bit
+pub const TONE_MAP_FILTER: &str =
+    "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p";
+
+/// Transfer HDR: PQ (HDR10, Dolby Vision profilo 8.1) e HLG
+const PQ: &str = "smpte2084";
+const HLG: &str = "arib-std-b67";
+
+/// Metadati del mastering display (SMPTE ST 2086)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MasteringDisplay {
+    /// Coordinate CIE 1931 (x, y) dei primari e del punto di bianco
+    pub red: (f64, f64),
+    pub green: (f64, f64),
+    pub blue: (f64, f64),
+    pub white_point: (f64, f64),
+    /// Luminanza in cd/m²
+    pub max_luminance: f64,
+    pub min_luminance: f64,
+}
+
+impl MasteringDisplay {
+    /// Formato di x265 (`master-display`): cromaticità in 0,00002 e luminanza in 0,0001 cd/m²
+    pub fn x265(&self) -> String {
+        let xy = |(x, y): (f64, f64)| format!("({},{})", (x * 50000.0).round() as u32, (y * 50000.0).round() as u32);
+        format!(
+            "G{}B{}R{}WP{}L({},{})",
+            xy(self.green), xy(self.blue), xy(self.red), xy(self.white_point),
+            (self.max_luminance * 10000.0).round() as u64, (self.min_luminance * 10000.0).round() as u64
+        )
+    }
+
+    /// Formato di SVT-AV1 (`mastering-display`): valori decimali
+    pub fn svtav1(&self) -> String {
+        let xy = |(x, y): (f64, f64)| format!("({:.4},{:.4})", x, y);
+        format!(
+            "G{}B{}R{}WP{}L({:.4},{:.4})",
+            xy(self.green), xy(self.blue), xy(self.red), xy(self.white_point),
+            self.max_luminance, self.min_luminance
+        )
+    }
+}
+
+/// Descrizione del colore di uno stream video (valori con i nomi di ffmpeg)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorInfo {
+    /// Primarie (es. "bt709", "bt2020")
+    pub primaries: Option<String>,
+    /// Funzione di trasferimento (es. "bt709", "smpte2084", "arib-std-b67")
+    pub transfer: Option<String>,
+    /// Matrice YUV (es. "bt709", "bt2020nc")
+    pub matrix: Option<String>,
+    /// Range: "tv" (limitato) o "pc" (completo)
+    pub range: Option<String>,
+    /// Formato pixel (es. "yuv420p10le")
+    pub pix_fmt: Option<String>,
+    /// Bit per componente (8 se sconosciuto)
+    pub bit_depth: u32,
+    pub mastering_display: Option<MasteringDisplay>,
+    /// Content light level (MaxCLL, MaxFALL) in cd/m²
+    pub content_light: Option<(u32, u32)>,
+}
+
+impl ColorInfo {
+    /// Legge il colore da uno stream di `ffprobe -show_streams`
+    pub fn from_stream(stream: &serde_json::Value) -> Self {
+        let tag = |key: &str| stream[key].as_str()
+            .filter(|value| !value.is_empty() && *value != "unknown" && *value != "reserved")
+            .map(|value| value.to_string());
+        let pix_fmt = tag("pix_fmt");
+        let bit_depth = stream["bits_per_raw_sample"].as_str()
+            .and_then(|bits| bits.parse::<u32>().ok())
+            .filter(|bits| *bits > 0)
+            .or_else(|| pix_fmt.as_deref().map(pix_fmt_bit_depth))
+            .unwrap_or(8);
+
+        let side_data = stream["side_data_list"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+        let mastering_display = side_data.iter()
+            .find(|data| data["side_data_type"] == "Mastering display metadata")
+            .and_then(|data| {
+                let value = |key: &str| data[key].as_str().and_then(parse_ratio);
+                let xy = |color: &str| Some((value(&format!("{}_x", color))?, value(&format!("{}_y", color))?));
+                Some(MasteringDisplay {
+                    red: xy("red")?,
+                    green: xy("green")?,
+                    blue: xy("blue")?,
+                    white_point: xy("white_point")?,
+                    max_luminance: value("max_luminance")?,
+                    min_luminance: value("min_luminance")?,
+                })
+            });
+        let content_light = side_data.iter()
+            .find(|data| data["side_data_type"] == "Content light level metadata")
+            .and_then(|data| Some((data["max_content"].as_u64()? as u32, data["max_average"].as_u64()? as u32)));
+
+        Self {
+            primaries: tag("color_primaries"),
+            transfer: tag("color_transfer"),
+            matrix: tag("color_space"),
+            range: tag("color_range"),
+            pix_fmt,
+            bit_depth,
+            mastering_display,
+            content_light,
+        }
+    }
+
+    /// Transfer PQ o HLG
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer.as_deref(), Some(PQ | HLG))
+    }
+
+    /// HDR10: PQ, con i metadati statici se presenti
+    pub fn is_hdr10(&self) -> bool {
+        self.transfer.as_deref() == Some(PQ)
+    }
+
+    /// Descrizione breve per i log (es. "bt2020/smpte2084 10 bit")
+    pub fn describe(&self) -> String {
+        format!(
+            "{}/{} {} bit",
+            self.primaries.as_deref().unwrap_or("?"),
+            self.transfer.as_deref().unwrap_or("?"),
+            self.bit_depth
+        )
+    }
+}
+
+/// Come codificare il colore di un video
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorPlan {
+    /// Formato pixel di output
+    pub pix_fmt: &'static str,
+    /// Converte HDR -> SDR (filtro [`TONE_MAP_FILTER`])
+    pub tone_map: bool,
+    /// Colore scritto nell'output (tag e metadati HDR)
+    pub output: ColorInfo,
+}
+
+impl ColorPlan {
+    /// Decide formato, tag e tone mapping per la sorgente (None = sorgente non analizzata)
+    pub fn new(config: &Config, source: Option<&ColorInfo>) -> Self {
+        let Some(source) = source else {
+            return Self { pix_fmt: "yuv420p", tone_map: false, output: ColorInfo::default() };
+        };
+        if !source.is_hdr() {
+            // 8 bit 4:2:0: l'unico formato riproducibile ovunque
+            let output = ColorInfo { pix_fmt: None, bit_depth: 8, mastering_display: None, content_light: None, ..source.clone() };
+            return Self { pix_fmt: "yuv420p", tone_map: false, output };
+        }
+        match config.hdr_policy {
+            HdrPolicy::Preserve => Self {
+                pix_fmt: "yuv420p10le",
+                tone_map: false,
+                output: ColorInfo { pix_fmt: None, bit_depth: 10, ..source.clone() },
+            },
+            HdrPolicy::ToneMap => Self {
+                pix_fmt: "yuv420p",
+                tone_map: true,
+                output: ColorInfo {
+                    primaries: Some("bt709".to_string()),
+                    transfer: Some("bt709".to_string()),
+                    matrix: Some("bt709".to_string()),
+                    range: Some("tv".to_string()),
+                    bit_depth: 8,
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    /// Argomenti ffmpeg generici: formato pixel e tag colore dell'output
+    pub fn args(&self) -> Vec<String> {
+        let mut args = to_string_vec(["-pix_fmt", self.pix_fmt]);
+        let tags = [
+            ("-color_primaries", &self.output.primaries),
+            ("-color_trc", &self.output.transfer),
+            ("-colorspace", &self.output.matrix),
+            ("-color_range", &self.output.range),
+        ];
+        for (option, value) in tags {
+            if let Some(value) = value {
+                args.extend(to_string_vec([option, value]));
+            }
+        }
+        args
+    }
+
+    /// Parametri privati dell'encoder per i metadati HDR10
+    /// (`-x265-params` o `-svtav1-params`, separati da `:`)
+    pub fn encoder_params(&self, encoder: &str) -> Vec<String> {
+        if !self.output.is_hdr10() {
+            return Vec::new();
+        }
+        let mut params = Vec::new();
+        match encoder {
+            "libx265" => {
+                // SEI HDR10 ripetuti a ogni keyframe
+                params.push("hdr10=1".to_string());
+                params.push("repeat-headers=1".to_string());
+                if let Some(display) = self.output.mastering_display {
+                    params.push(format!("master-display={}", display.x265()));
+                }
+                if let Some((max_cll, max_fall)) = self.output.content_light {
+                    params.push(format!("max-cll={},{}", max_cll, max_fall));
+                }
+            }
+            "libsvtav1" => {
+                if let Some(display) = self.output.mastering_display {
+                    params.push(format!("mastering-display={}", display.svtav1()));
+                }
+                if let Some((max_cll, max_fall)) = self.output.content_light {
+                    params.push(format!("content-light={},{}", max_cll, max_fall));
+                }
+            }
+            _ => {}
+        }
+        params
+    }
+}
+
+/// Bit per componente da un formato pixel ffmpeg ("yuv420p10le" -> 10, "p010le" -> 10)
+fn pix_fmt_bit_depth(pix_fmt: &str) -> u32 {
+    let name = pix_fmt.trim_end_matches("le").trim_end_matches("be");
+    let digits_start = name.rfind(|c: char| !c.is_ascii_digit()).map(|i| i + 1).unwrap_or(0);
+    let depth = &name[digits_start..];
+    if digits_start > 0 && name[..digits_start].ends_with('p') {
+        depth.parse::<u32>().ok().filter(|bits| *bits > 8).unwrap_or(8)
+    } else {
+        8
+    }
+}
+
+/// Frazione di ffprobe ("35400/50000") come decimale
+fn parse_ratio(value: &str) -> Option<f64> {
+    match value.split_once('/') {
+        Some((num, den)) => {
+            let den = den.parse::<f64>().ok().filter(|den| *den != 0.0)?;
+            Some(num.parse::<f64>().ok()? / den)
+        }
+        None => value.parse::<f64>().ok(),
+    }
+}
+
+/// Il filtro di tone mapping (`zscale`) è compilato nell'ffmpeg in uso; letto una sola volta
+pub async fn tone_mapping_available() -> bool {
+    static FILTERS: OnceCell<HashSet<String>> = OnceCell::const_new();
+    let filters = FILTERS
+        .get_or_init(|| async {
+            let ffmpeg_cmd = PlatformCommands::instance().get_command("ffmpeg");
+            match Command::new(ffmpeg_cmd).args(["-hide_banner", "-filters"]).kill_on_drop(true).output().await {
+                Ok(output) => parse_filter_list(&String::from_utf8_lossy(&output.stdout)),
+                Err(e) => {
+                    debug!("Failed to list ffmpeg filters: {}", e);
+                    HashSet::new()
+                }
+            }
+        })
+        .await;
+    filters.contains("zscale") && filters.contains("tonemap")
+}
+
+/// Estrae i nomi dei filtri dall'output di `ffmpeg -filters`.
+///
+/// Le righe utili hanno la forma ` ... zscale            V->V       Apply resizing, ...`.
+fn parse_filter_list(output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("->"))
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hdr10_stream() -> serde_json::Value {
+        serde_json::json!({
+            "codec_type": "video",
+            "pix_fmt": "yuv420p10le",
+            "color_range": "tv",
+            "color_space": "bt2020nc",
+            "color_transfer": "smpte2084",
+            "color_primaries": "bt2020",
+            "side_data_list": [
+                {
+                    "side_data_type": "Mastering display metadata",
+                    "red_x": "34000/50000", "red_y": "16000/50000",
+                    "green_x": "13250/50000", "green_y": "34500/50000",
+                    "blue_x": "7500/50000", "blue_y": "3000/50000",
+                    "white_point_x": "15635/50000", "white_point_y": "16450/50000",
+                    "min_luminance": "50/10000", "max_luminance": "10000000/10000"
+                },
+                { "side_data_type": "Content light level metadata", "max_content": 1000, "max_average": 400 }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_color_parsing() {
+        let color = ColorInfo::from_stream(&hdr10_stream());
+        assert!(color.is_hdr10());
+        assert_eq!(color.bit_depth, 10);
+        assert_eq!(color.matrix.as_deref(), Some("bt2020nc"));
+        assert_eq!(color.content_light, Some((1000, 400)));
+        let display = color.mastering_display.unwrap();
+        assert_eq!(display.x265(), "G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,50)");
+        assert_eq!(display.svtav1(), "G(0.2650,0.6900)B(0.1500,0.0600)R(0.6800,0.3200)WP(0.3127,0.3290)L(1000.0000,0.0050)");
+
+        // Untagged 8-bit SDR: nothing to carry over
+        let sdr = ColorInfo::from_stream(&serde_json::json!({ "pix_fmt": "yuv420p", "color_space": "unknown" }));
+        assert!(!sdr.is_hdr());
+        assert_eq!((sdr.bit_depth, sdr.matrix.clone()), (8, None));
+
+        assert_eq!(pix_fmt_bit_depth("p010le"), 10);
+        assert_eq!(pix_fmt_bit_depth("yuv444p12be"), 12);
+        assert_eq!(pix_fmt_bit_depth("nv12"), 8);
+    }
+
+    #[test]
+    fn test_color_plan() {
+        let hdr = ColorInfo::from_stream(&hdr10_stream());
+
+        let preserve = ColorPlan::new(&Config::default(), Some(&hdr));
+        assert!(!preserve.tone_map);
+        let args = preserve.args();
+        assert!(args.windows(2).any(|w| w == ["-pix_fmt", "yuv420p10le"]));
+        assert!(args.windows(2).any(|w| w == ["-color_trc", "smpte2084"]));
+        assert!(args.windows(2).any(|w| w == ["-colorspace", "bt2020nc"]));
+        let x265 = preserve.encoder_params("libx265");
+        assert!(x265.contains(&"hdr10=1".to_string()));
+        assert!(x265.contains(&"max-cll=1000,400".to_string()));
+        assert!(preserve.encoder_params("libx264").is_empty());
+
+        let config = Config { hdr_policy: HdrPolicy::ToneMap, ..Default::default() };
+        let tone_map = ColorPlan::new(&config, Some(&hdr));
+        assert!(tone_map.tone_map);
+        assert!(tone_map.args().windows(2).any(|w| w == ["-color_primaries", "bt709"]));
+        assert!(tone_map.encoder_params("libx265").is_empty());
+
+        // Unanalyzed source: plain 8-bit output, as before
+        assert_eq!(ColorPlan::new(&config, None).args(), ["-pix_fmt", "yuv420p"]);
+    }
+
+    #[test]
+    fn test_parse_filter_list() {
+        let output = "Filters:\n  T.. = Timeline support\n ---\n ..C zscale            V->V       Apply resizing, colorspace and bit depth conversion.\n TSC tonemap           V->V       Conversion to/from different dynamic ranges.\n";
+        let filters = parse_filter_list(output);
+        assert!(filters.contains("zscale"));
+        assert!(filters.contains("tonemap"));
+        assert!(!filters.contains("Timeline"));
+    }
+}
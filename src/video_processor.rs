@@ -5,7 +5,7 @@
 //! and leverage the most advanced encoding algorithms available.
 //! 
 //! ## Core Responsibilities
-//! - **Video Compression**: High-quality compression using H.264/H.265 codecs
+//! - **Video Compression**: High-quality compression using H.264, HEVC, AV1 or VP9
 //! - **Quality Control**: Precise quality control via CRF (Constant Rate Factor) settings
 //! - **Audio Processing**: Audio re-encoding with AAC codec and configurable bitrate
 //! - **Metadata Preservation**: Complete preservation of video metadata using exiftool
@@ -14,7 +14,7 @@
 //! 
 //! ## Supported Formats
 //! - **Input Formats**: MP4, MOV, AVI, MKV, WebM, 3GP, FLV, WMV
-//! - **Output Format**: MP4 (H.264 + AAC) for maximum compatibility across devices and platforms
+//! - **Output Format**: MP4 (H.264/HEVC/AV1 + AAC) or WebM (VP9 + Opus), see `config.video_codec`
 //! 
 //! ## Optimization Pipeline
 //! 
@@ -23,11 +23,40 @@
 //! - Extracts resolution, framerate, duration, codec information
 //! - Determines optimal encoding parameters based on source characteristics
 //! 
+//! ### Pre-flight Decision
+//! Before any CPU-heavy work, `plan` compares the source with what the configured
+//! codec would need at the configured CRF (bits per pixel per frame):
+//! - **Encode**: the source bitrate is clearly above the expected output bitrate,
+//!   or filters (scale/fps) are required
+//! - **Remux**: re-encoding would not pass `size_threshold`, but the container
+//!   must change (e.g. HEVC in `.mov` -> `.mp4`): video is stream-copied.
+//!   With `remux_videos` every video whose codec fits the target container is
+//!   remuxed, regardless of its bitrate
+//! - **Skip**: the source is already efficient and in the target container
+//!
+//! The decision is logged and emitted as a `video_analysis` JSON event.
+//!
+//! ### Dead Segment Trimming (Optional)
+//! With `video_trim`, an extra decoding pass (`blackdetect`/`silencedetect`, see
+//! `video_trim`) finds black or silent footage at the start and end. The trim
+//! points are emitted as a `video_trim` JSON event and applied by the encode
+//! (`-ss`/`-t`); a video with something to trim is always encoded.
+//! 
 //! ### 2. Video Compression
-//! - **Video Codec**: libx264 (H.264) with optimized settings
-//! - **Encoding Preset**: `veryslow` for maximum compression efficiency
+//! - **Video Codec**: `config.video_codec`, mapped to an ffmpeg encoder by `video_codec`
+//! - **Encoding Preset**: `config.video_preset` (default: the encoder's own, `veryslow` for x264)
+//! - **Tune/Profile/Level/GOP**: `video_tune`, `video_profile`, `video_level`, `keyframe_interval`
+//! - **Downscaling**: `max_video_dimension` caps the long edge (`scale`), never upscaling
+//! - **Frame Rate**: `max_video_fps` caps the frame rate (`fps`)
+//!
+//! Scaling is computed on the *displayed* dimensions: ffmpeg auto-rotates frames
+//! with rotation metadata before user filters run, so a portrait iPhone clip
+//! stored as 3840x2160 + 90° is scaled as 2160x3840.
 //! - **Quality Control**: CRF-based encoding for consistent quality
-//! - **Rate Control**: Two-pass encoding for critical applications (optional)
+//! - **Rate Control**: two-pass encoding at `video_target_bitrate`, or at the bitrate
+//!   that fits `video_target_size` into the duration (see `target_bitrate`);
+//!   first-pass logs go to a temporary directory removed on success, error,
+//!   cancellation and timeout
 //! 
 //! ### 3. Audio Processing
 //! - **Audio Codec**: AAC-LC for broad compatibility
@@ -52,7 +81,7 @@
 //! 
 //! ## Performance Optimizations
 //! 
-//! ### Encoding Presets (Speed vs Compression)
+//! ### Encoding Presets (Speed vs Compression, `--preset`)
 //! - **ultrafast**: Fastest encoding, larger files
 //! - **superfast**: Very fast encoding, moderately larger files
 //! - **veryfast**: Fast encoding, good compression
@@ -61,7 +90,10 @@
 //! - **medium**: Default preset, balanced speed/compression
 //! - **slow**: Slower encoding, better compression
 //! - **slower**: Much slower encoding, excellent compression
-//! - **veryslow**: Slowest encoding, maximum compression (default choice)
+//! - **veryslow**: Slowest encoding, maximum compression (x264 default)
+//!
+//! Non-x26x encoders get the equivalent SVT-AV1 preset or `-cpu-used` value.
+//! Faster presets are the way to keep long videos within the per-file timeout.
 //! 
 //! ### Hardware Acceleration Support
 //! Future versions may include:
@@ -80,7 +112,7 @@
 //! 
 //! ## Usage Examples
 //! 
-//! ```rust
+//! ```rust,ignore
 //! use video_processor::VideoProcessor;
 //! use config::Config;
 //! 
@@ -127,25 +159,46 @@
 //! ## Analisi video (VideoInfo):
 //! - Durata, bitrate, risoluzione
 //! - Codec originale
+//! - Rotazione, colore (primarie, transfer, matrice), HDR e bit depth (`video_color`)
 //! - Stima dimensione compressa
 //! 
+//! ## Storyboard:
+//! - `create_storyboards`: sprite di frame + indice WebVTT per ogni profilo
+//!   di `config.storyboards` (griglia e formato nel modulo `storyboard`)
+//! 
+//! ## Streaming adattivo:
+//! - `package_streaming`: bundle HLS/DASH con la ladder di `config.streaming_ladder`
+//!   per i video più lunghi di `config.streaming_min_duration` (modulo `streaming`)
+//! 
 //! ## Dipendenze richieste:
 //! - `ffmpeg`: Compressione video
 //! - `ffprobe`: Analisi proprietà video
 //! - `exiftool`: Preservazione metadata
 //! 
 //! ## Esempio:
-//! ```rust
+//! ```rust,ignore
 //! let processor = VideoProcessor::new(config);
 //! let optimized = processor.optimize(&video_path).await?;
 //! let info = processor.get_video_info(&video_path).await?;
 //! ```
 
-use crate::config::Config;
+use crate::config::{AudioCodec, Config, StreamPolicy, StreamingFormat, VideoCodec};
 use crate::error::OptimizeError;
+use crate::ffmpeg;
+use crate::file_manager::{FileManager, TempFileGuard};
+use crate::json_output::JsonMessage;
 use crate::optimizer::path_resolver::PathResolver;
 use crate::platform::PlatformCommands;
+use crate::progress::ProgressCallback;
+use crate::storyboard::StoryboardLayout;
+use crate::streaming::{self, StreamingBundle};
+use crate::utils::{parse_bitrate, parse_file_size, to_string_vec};
+use crate::video_codec::{RateControl, VideoEncoder};
+use crate::video_color::{self, ColorInfo, ColorPlan};
+use crate::video_streams::{StreamInfo, StreamKind, StreamPlan};
+use crate::video_trim::{self, TrimPoints};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use tempfile::NamedTempFile;
 use tokio::process::Command;
@@ -172,6 +225,8 @@ pub struct VideoProcessor {
     config: Config,
     /// Cancellation receiver for stopping operations
     stop_receiver: Option<broadcast::Receiver<()>>,
+    /// Receives live encoding progress (percentage, speed, ETA)
+    progress: Option<ProgressCallback>,
 }
 
 impl VideoProcessor {
@@ -181,7 +236,7 @@ impl VideoProcessor {
     /// * `config` - Configuration containing video quality, audio settings, and output paths
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let config = Config {
     ///     video_crf: 23,
     ///     audio_bitrate: "192k".to_string(),
@@ -194,6 +249,7 @@ impl VideoProcessor {
         Self { 
             config,
             stop_receiver: None,
+            progress: None,
         }
     }
 
@@ -207,7 +263,7 @@ impl VideoProcessor {
     /// * `Self` - A new VideoProcessor instance with cancellation support
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let (stop_sender, stop_receiver) = broadcast::channel(1);
     /// let config = Config::default();
     /// let processor = VideoProcessor::new_with_cancellation(config, stop_receiver);
@@ -219,9 +275,18 @@ impl VideoProcessor {
         Self { 
             config,
             stop_receiver: Some(stop_receiver),
+            progress: None,
         }
     }
 
+    /// Sets the callback that receives live ffmpeg progress for each file.
+    /// 
+    /// Updates arrive roughly twice per second while ffmpeg runs, with the
+    /// percentage computed against `VideoInfo::duration`.
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        self.progress = Some(callback);
+    }
+
     /// Checks if a stop signal has been received.
     /// 
     /// # Returns
@@ -262,7 +327,8 @@ impl VideoProcessor {
     /// * `input_base_dir` - Base directory for calculating relative output paths
     /// 
     /// # Returns
-    /// * `Result<PathBuf>` - Path to the optimized video file
+    /// * `Result<PathBuf>` - Path to the optimized video file (the input itself
+    ///   when the pre-flight analysis skips it in in-place mode)
     /// 
     /// # Errors
     /// - Returns error if operation is cancelled
@@ -274,12 +340,13 @@ impl VideoProcessor {
     /// 2. Validates input and calculates output path
     /// 3. Creates output directories if needed
     /// 4. Optionally skips compression if configured
-    /// 5. Performs video compression using FFmpeg with cancellation checks
-    /// 6. Preserves original metadata using exiftool
-    /// 7. Returns path to optimized file
+    /// 5. Analyzes the source with ffprobe and decides encode, remux or skip
+    /// 6. Performs video compression using FFmpeg with cancellation checks
+    /// 7. Preserves original metadata using exiftool
+    /// 8. Returns path to optimized file
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let processor = VideoProcessor::new(config);
     /// let optimized_path = processor.optimize(
     ///     Path::new("/input/video.mov"),
@@ -289,7 +356,7 @@ impl VideoProcessor {
     pub async fn optimize(&mut self, input_path: &Path, input_base_dir: &Path) -> Result<PathBuf> {
         // Check for cancellation before starting
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Video optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("Video optimization".to_string()).into());
         }
 
         info!("🎬 Starting video optimization for: {}", input_path.display());
@@ -324,44 +391,96 @@ impl VideoProcessor {
         
         // Calculate final output path using centralized logic
         let final_output_path = PathResolver::get_output_path(input_path, input_base_dir, &self.config)?;
+
+        // Captured now: in-place mode overwrites the original
+        let source_metadata = tokio::fs::metadata(input_path).await?;
         
         // Ensure output directory exists
         PathResolver::ensure_parent_dirs(&final_output_path).await?;
 
         // Check for cancellation after path setup
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Video optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("Video optimization".to_string()).into());
         }
         
         // Handle skip compression mode
         if self.config.skip_video_compression {
             info!("⏩ Skipping video compression, copying original: {}", 
                   input_path.file_name().unwrap_or_default().to_string_lossy());
+            // In-place the output is the original itself: copying a file onto
+            // itself truncates it
+            if final_output_path == input_path {
+                return Ok(final_output_path);
+            }
             tokio::fs::copy(input_path, &final_output_path).await?;
+            FileManager::apply_file_attributes(&final_output_path, &source_metadata, self.config.preserve_ownership).await?;
             info!("✅ Video copied without compression: {}", 
                   input_path.file_name().unwrap_or_default().to_string_lossy());
             return Ok(final_output_path);
         }
         
-        // Create temporary file for safe processing
-        let temp_file = NamedTempFile::with_suffix(".mp4")?;
+        // Pre-flight analysis: decide whether the encode is worth the CPU time
+        let video_info = match self.get_video_info(input_path).await {
+            Ok(info) => Some(info),
+            Err(e) => {
+                warn!("Could not analyze {}, encoding without pre-flight checks: {}", input_path.display(), e);
+                None
+            }
+        };
+        let mut decision = match video_info {
+            Some(ref info) => Self::plan(&self.config, info, input_path, source_metadata.len()),
+            None => VideoDecision::unanalyzed(),
+        };
+        let trim = match video_info {
+            Some(ref info) if self.config.video_trim.is_some() => self.detect_trim(input_path, info).await?,
+            _ => None,
+        };
+        if let Some(ref trim) = trim {
+            // Stream copy cannot cut precisely: trimming needs an encode
+            if decision.action != VideoAction::Encode {
+                decision.action = VideoAction::Encode;
+                decision.reason = format!("{}; encoding to trim dead segments", decision.reason);
+            }
+            decision.estimated_size = (decision.estimated_size as f64 * trim.duration() / trim.source_duration) as u64;
+        }
+        self.report_decision(input_path, video_info.as_ref(), &decision);
+        
+        if decision.action == VideoAction::Skip {
+            // Same container and extension: the output is the original itself
+            if final_output_path != input_path {
+                tokio::fs::copy(input_path, &final_output_path).await?;
+                FileManager::apply_file_attributes(&final_output_path, &source_metadata, self.config.preserve_ownership).await?;
+            }
+            return Ok(final_output_path);
+        }
+        
+        // Create temporary file for safe processing (same container as the output)
+        let temp_file = NamedTempFile::with_suffix(format!(".{}", self.config.video_codec.container_extension()))?;
         let temp_path = temp_file.path().to_path_buf();
         
-        // Perform video compression with cancellation support
-        self.compress_video(input_path, &temp_path).await?;
+        // Perform video compression (or stream copy) with cancellation support
+        match decision.action {
+            VideoAction::Remux => self.remux_video(input_path, &temp_path, video_info.as_ref()).await?,
+            _ => self.compress_video(input_path, &temp_path, video_info.as_ref(), trim.as_ref()).await?,
+        }
 
         // Check for cancellation after compression
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Video optimization cancelled by user"));
+            return Err(OptimizeError::Cancelled("Video optimization".to_string()).into());
         }
         
-        // Preserve original metadata
+        // Preserve original metadata (exiftool cannot write Matroska/WebM)
         // debug!("📝 Preserving video metadata...");
-        self.preserve_metadata(input_path, &temp_path).await?;
+        if !self.config.video_codec.is_webm() {
+            // Encoded frames are already upright: only a remux keeps the source rotation
+            let upright = decision.action != VideoAction::Remux;
+            self.preserve_metadata(input_path, &temp_path, upright).await?;
+        }
         
         // Move optimized video to final destination
         info!("💾 Saving optimized video to: {}", final_output_path.display());
         tokio::fs::copy(&temp_path, &final_output_path).await?;
+        FileManager::apply_file_attributes(&final_output_path, &source_metadata, self.config.preserve_ownership).await?;
         
         info!("✅ Video optimization completed: {}", 
               input_path.file_name().unwrap_or_default().to_string_lossy());
@@ -370,7 +489,7 @@ impl VideoProcessor {
         Ok(final_output_path)
     }
     
-    /// Compresses video using FFmpeg with optimized H.264 encoding settings and cancellation support.
+    /// Compresses video using FFmpeg with the configured codec and cancellation support.
     /// 
     /// This method handles the core video compression using FFmpeg with carefully
     /// selected parameters for optimal quality-to-size ratio. The encoding uses
@@ -378,12 +497,12 @@ impl VideoProcessor {
     /// Includes cancellation checks and proper error handling.
     /// 
     /// # Encoding Parameters
-    /// - **Video Codec**: libx264 (H.264) for maximum compatibility
-    /// - **Encoding Preset**: `veryslow` for maximum compression efficiency
-    /// - **Quality Control**: CRF-based encoding for consistent quality
-    /// - **Audio Codec**: AAC-LC with configurable bitrate
+    /// - **Video Codec**: `config.video_codec` (libx264, libx265, libsvtav1/libaom-av1, libvpx-vp9)
+    /// - **Quality Control**: CRF-based encoding, scaled to the encoder's range
+    /// - **Audio Codec**: AAC-LC (MP4) or Opus (WebM) with configurable bitrate
     /// - **Metadata**: Preserved using `-map_metadata 0`
-    /// - **Compatibility**: Uses `movflags use_metadata_tags` for broad support
+    /// - **Compatibility**: Uses `movflags use_metadata_tags` for MP4 outputs
+    /// - **Progress**: Streamed from `-progress pipe:1` to the progress callback
     /// 
     /// # Quality Settings (CRF)
     /// The CRF value controls the quality-size tradeoff:
@@ -391,9 +510,15 @@ impl VideoProcessor {
     /// - Higher values = lower quality, smaller files
     /// - Typical range: 18-28 (default: 26)
     /// 
+    /// # Target Bitrate / Size
+    /// With `video_target_bitrate` or `video_target_size` the CRF is replaced by
+    /// an average bitrate, encoded in two passes (single-pass VBR on SVT-AV1).
+    /// 
     /// # Arguments
     /// * `input_path` - Path to the input video file
     /// * `output_path` - Path where the compressed video will be saved
+    /// * `video_info` - Source analysis, used for the scale/fps filters
+    /// * `trim` - Part of the source to keep (`video_trim`), if any
     /// 
     /// # Returns
     /// * `Result<()>` - Success or detailed error information
@@ -404,69 +529,494 @@ impl VideoProcessor {
     /// - Logs compression progress and timing information
     /// - Handles various FFmpeg exit codes appropriately
     /// - Returns error if operation is cancelled
-    async fn compress_video(&mut self, input_path: &Path, output_path: &Path) -> Result<()> {
+    async fn compress_video(
+        &mut self,
+        input_path: &Path,
+        output_path: &Path,
+        video_info: Option<&VideoInfo>,
+        trim: Option<&TrimPoints>,
+    ) -> Result<()> {
+        // Durations from here on (progress, target size) refer to the trimmed output
+        let trimmed_info = video_info.zip(trim).map(|(info, trim)| VideoInfo { duration: trim.duration(), ..info.clone() });
+        let video_info = trimmed_info.as_ref().or(video_info);
+        
+        let encoder = VideoEncoder::resolve(self.config.video_codec).await?;
+        let target_bitrate = Self::target_bitrate(&self.config, video_info)?;
+        let rate_description = match target_bitrate {
+            Some(bitrate) => format!("target: {} kbps", bitrate / 1000),
+            None => format!("CRF: {}", encoder.crf(self.config.video_crf)),
+        };
         info!(
-            "🎬 Compressing video: {} (CRF: {}, audio: {})",
+            "🎬 Compressing video: {} ({}, {}, audio: {} {})",
             input_path.file_name().unwrap_or_default().to_string_lossy(),
-            self.config.video_crf,
+            encoder.name,
+            rate_description,
+            self.config.audio_codec.as_str(),
             self.config.audio_bitrate
         );
 
         // Check for cancellation before starting compression
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Video compression cancelled by user"));
+            return Err(OptimizeError::Cancelled("Video compression".to_string()).into());
         }
         
-        let platform = PlatformCommands::instance();
-        let ffmpeg_cmd = platform.get_command("ffmpeg");
-        
-        // Build FFmpeg command with optimized parameters
-        let mut cmd = Command::new(ffmpeg_cmd);
-        cmd.args([
-            "-i", input_path.to_str().unwrap(),        // Input file
-            "-c:v", "libx264",                         // Video codec: H.264
-            "-preset", "veryslow",                     // Encoding speed vs compression trade-off
-            "-crf", &self.config.video_crf.to_string(), // Quality control (Constant Rate Factor)
-            "-c:a", "aac",                             // Audio codec: AAC-LC
-            "-b:a", &self.config.audio_bitrate,        // Audio bitrate
-            "-map_metadata", "0",                      // Copy all metadata from input
-            "-movflags", "use_metadata_tags",          // Ensure metadata compatibility
-            "-y", output_path.to_str().unwrap(),       // Output file (overwrite if exists)
-        ]);
+        if let Some(info) = video_info {
+            let filters = Self::video_filters(&self.config, info);
+            if !filters.is_empty() {
+                info!("📐 Video filters for {}x{} @ {:.2} fps: {}", info.width, info.height, info.frame_rate, filters.join(","));
+            }
+        }
         
-        // Configure FFmpeg logging level based on our log level
-        if !tracing::enabled!(tracing::Level::DEBUG) {
-            cmd.args(["-loglevel", "warning"]);        // Quiet mode for production
-        } else {
-            cmd.args(["-progress", "pipe:2"]);         // Progress output for // debugging
+        let color = ColorPlan::new(&self.config, video_info.map(|info| &info.color));
+        if let Some(info) = video_info.filter(|info| info.color.is_hdr()) {
+            let handling = if color.tone_map { "tone-mapped to SDR" } else { "kept as 10-bit HDR" };
+            info!("🌈 HDR source ({}): {}", info.color.describe(), handling);
+            if !color.tone_map && encoder.name == "libx264" {
+                warn!("10-bit H.264 (High 10) has limited player support: prefer --video-codec hevc or av1 for HDR, or --hdr tone-map");
+            }
+        }
+        let streams = Self::stream_plan(&self.config, video_info);
+        if let Some(ref plan) = streams {
+            self.log_stream_plan(plan, video_info);
         }
+        if color.tone_map && !video_color::tone_mapping_available().await {
+            return Err(OptimizeError::MissingDependency(
+                "HDR tone mapping needs an ffmpeg built with the zscale filter (libzimg)".to_string()
+            ).into());
+        }
+        
+        // First-pass statistics live in a temporary directory: dropping it removes
+        // them even when the future is discarded on stop or timeout
+        let pass_log_dir = match target_bitrate {
+            Some(_) if encoder.supports_two_pass() => Some(tempfile::Builder::new().prefix("smo-passlog-").tempdir()?),
+            _ => None,
+        };
+        let rate = match (target_bitrate, &pass_log_dir) {
+            (Some(bitrate), Some(dir)) => RateControl::TwoPass { bitrate, pass: 2, log_prefix: dir.path().join("pass") },
+            (Some(bitrate), None) => RateControl::Bitrate(bitrate),
+            (None, _) => RateControl::Crf,
+        };
         
         info!("🔄 Starting FFmpeg compression...");
         let start_time = std::time::Instant::now();
         
-        // Execute FFmpeg asynchronously
-        let output = cmd.output().await
-            .map_err(|e| anyhow::anyhow!("Failed to execute {}: {}", ffmpeg_cmd, e))?;
+        if let RateControl::TwoPass { bitrate, ref log_prefix, .. } = rate {
+            // Analysis pass: statistics only, no audio and no output file
+            let first_pass = RateControl::TwoPass { bitrate, pass: 1, log_prefix: log_prefix.clone() };
+            let mut args = Self::encode_args(&self.config, &encoder, input_path, video_info, streams.as_ref(), trim, &first_pass, &color);
+            args.extend(to_string_vec(["-an", "-f", "null", "-"]));
+            ffmpeg::run(&args, input_path, video_info.map(|info| info.duration), self.progress.as_ref()).await?;
+            
+            if self.should_stop() {
+                return Err(OptimizeError::Cancelled("Video compression".to_string()).into());
+            }
+            debug!("First pass completed in {:.1}s", start_time.elapsed().as_secs_f64());
+        }
+        
+        // Build FFmpeg arguments: input, codec-specific video/audio, metadata, output
+        let mut args = Self::encode_args(&self.config, &encoder, input_path, video_info, streams.as_ref(), trim, &rate, &color);
+        if let Some(ref plan) = streams {
+            args.extend(plan.other_args());
+        }
+        args.extend(encoder.audio_args(&self.config, video_info, false));
+        args.extend(to_string_vec(["-map_metadata", "0"]));    // Copy all metadata from input
+        args.extend(encoder.container_args());
+        args.extend(to_string_vec(["-y", output_path.to_str().unwrap()])); // Output file (overwrite if exists)
+        
+        // Execute FFmpeg, streaming progress to the tracker
+        ffmpeg::run(&args, input_path, video_info.map(|info| info.duration), self.progress.as_ref()).await?;
         
         let duration = start_time.elapsed();
         
-        // Check for cancellation after compression (before checking results)
+        // Check for cancellation after compression
         if self.should_stop() {
-            return Err(anyhow::anyhow!("Video compression cancelled by user"));
+            return Err(OptimizeError::Cancelled("Video compression".to_string()).into());
         }
         
-        // Check for FFmpeg execution success
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            error!("❌ FFmpeg failed after {:.1}s: {}", duration.as_secs_f64(), error_msg);
-            return Err(OptimizeError::FFmpeg(error_msg.to_string()).into());
+        info!("✅ Video compression completed in {:.1}s", duration.as_secs_f64());
+        
+        Ok(())
+    }
+    
+    /// Input, video mapping, video encoding and filter arguments shared by every encoding pass.
+    /// 
+    /// ffmpeg rotates the frames upright before the filters run, so the legacy
+    /// `rotate` tag must not be copied to the output (it would rotate them twice).
+    /// The filters only apply to the first video stream: extra angles kept by
+    /// `--streams all` may have a different size.
+    #[allow(clippy::too_many_arguments)]
+    fn encode_args(
+        config: &Config,
+        encoder: &VideoEncoder,
+        input_path: &Path,
+        video_info: Option<&VideoInfo>,
+        streams: Option<&StreamPlan>,
+        trim: Option<&TrimPoints>,
+        rate: &RateControl,
+        color: &ColorPlan,
+    ) -> Vec<String> {
+        let mut args = trim.map(TrimPoints::input_args).unwrap_or_default();
+        args.extend(to_string_vec(["-i", input_path.to_str().unwrap()]));
+        if let Some(trim) = trim {
+            args.extend(trim.output_args());
+        }
+        if let Some(plan) = streams {
+            args.extend(plan.video_args());
         }
+        args.extend(encoder.video_args_with_rate(config, rate, color));
+        if let Some(info) = video_info {
+            let filters = Self::video_filters(config, info);
+            if !filters.is_empty() {
+                args.extend(to_string_vec(["-filter:v:0", &filters.join(",")]));
+            }
+            if info.rotation != 0 {
+                args.extend(to_string_vec(["-metadata:s:v:0", "rotate=0"]));
+            }
+        }
+        args
+    }
+    
+    /// Looks for black/silent footage at the start and end (`config.video_trim`).
+    /// 
+    /// An analysis failure is not fatal: the video is encoded untrimmed.
+    /// 
+    /// # Returns
+    /// * `Result<Option<TrimPoints>>` - The part to keep, None when nothing is trimmed
+    async fn detect_trim(&mut self, input_path: &Path, info: &VideoInfo) -> Result<Option<TrimPoints>> {
+        let Some(mode) = self.config.video_trim else {
+            return Ok(None);
+        };
+        let Some(args) = video_trim::analysis_args(mode, input_path, info.audio_codec.is_some()) else {
+            return Ok(None);
+        };
         
-        info!("✅ Video compression completed in {:.1}s", duration.as_secs_f64());
+        info!(
+            "✂️ Looking for dead segments in {}",
+            input_path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let log = ffmpeg::analyze(&args, input_path, Some(info.duration), self.progress.as_ref()).await;
+        if self.should_stop() {
+            return Err(OptimizeError::Cancelled("Video trim analysis".to_string()).into());
+        }
+        let log = match log {
+            Ok(log) => log,
+            Err(e) => {
+                warn!("Dead segment analysis failed for {}, not trimming: {}", input_path.display(), e);
+                return Ok(None);
+            }
+        };
+        
+        let trim = TrimPoints::find(&video_trim::parse_dead_segments(&log, info.duration), info.duration);
+        if let Some(ref trim) = trim {
+            info!(
+                "✂️ Trimming {}: keeping {:.2}s-{:.2}s of {:.2}s ({:.1}s removed)",
+                input_path.file_name().unwrap_or_default().to_string_lossy(),
+                trim.start, trim.end, trim.source_duration, trim.removed()
+            );
+            if self.config.json_output {
+                JsonMessage::video_trim(input_path.to_path_buf(), trim).emit();
+            }
+        }
+        Ok(trim)
+    }
+    
+    /// Streams to map for `config.stream_policy`.
+    /// 
+    /// # Returns
+    /// * `Option<StreamPlan>` - None when the source could not be analysed:
+    ///   ffmpeg then keeps its default selection (one video and one audio stream)
+    pub fn stream_plan(config: &Config, video_info: Option<&VideoInfo>) -> Option<StreamPlan> {
+        let plan = StreamPlan::new(config, &video_info?.streams);
+        (!plan.video.is_empty()).then_some(plan)
+    }
+    
+    /// Logs the kept tracks and warns about subtitles the container cannot hold.
+    fn log_stream_plan(&self, plan: &StreamPlan, video_info: Option<&VideoInfo>) {
+        let chapters = video_info.map_or(0, |info| info.chapters);
+        debug!(
+            "Streams: {} video, {} audio, {} subtitles, {} chapters",
+            plan.video.len(), plan.audio.len(), plan.subtitles.len(), chapters
+        );
+        for stream in &plan.dropped {
+            warn!(
+                "Bitmap subtitle {} cannot be stored in {}: dropped",
+                stream.describe(), self.config.video_codec.container_extension()
+            );
+        }
+    }
+    
+    /// Video bitrate for the target bitrate/size modes (None = CRF encoding).
+    /// 
+    /// With `video_target_size` the size is spread over the duration, minus
+    /// [`CONTAINER_OVERHEAD`] and the audio track (estimated at `audio_bitrate`,
+    /// so copied audio tracks can make the file slightly larger or smaller).
+    /// 
+    /// # Errors
+    /// When the duration is unknown or the size leaves less than
+    /// [`MIN_TARGET_BITRATE`] for the video.
+    pub fn target_bitrate(config: &Config, video_info: Option<&VideoInfo>) -> Result<Option<u64>> {
+        if let Some(ref bitrate) = config.video_target_bitrate {
+            return parse_bitrate(bitrate)
+                .map(Some)
+                .ok_or_else(|| anyhow::anyhow!("Invalid video target bitrate '{}'", bitrate));
+        }
+        let Some(ref size) = config.video_target_size else {
+            return Ok(None);
+        };
+        let bytes = parse_file_size(size)
+            .ok_or_else(|| anyhow::anyhow!("Invalid video target size '{}'", size))?;
+        let info = video_info
+            .filter(|info| info.duration > 0.0)
+            .ok_or_else(|| anyhow::anyhow!("Target size {} needs the video duration, which could not be read", size))?;
+        
+        let audio_bitrate = match config.audio_codec {
+            AudioCodec::Disabled => 0,
+            _ if info.audio_codec.is_none() => 0,
+            _ => parse_bitrate(&config.audio_bitrate).unwrap_or(128_000),
+        };
+        let total_bitrate = bytes as f64 * 8.0 * (1.0 - CONTAINER_OVERHEAD) / info.duration;
+        let video_bitrate = total_bitrate - audio_bitrate as f64;
+        if video_bitrate < MIN_TARGET_BITRATE as f64 {
+            return Err(anyhow::anyhow!(
+                "Target size {} is too small for {} of video ({:.0} kbps left for the video stream)",
+                size, info.duration_string(), video_bitrate.max(0.0) / 1000.0
+            ));
+        }
+        Ok(Some(video_bitrate as u64))
+    }
+    
+    /// Changes the container without re-encoding the video stream.
+    /// 
+    /// Used in remux mode and when the pre-flight analysis finds the video
+    /// already efficient but in a container other than the target one.
+    /// Runs in seconds: only an incompatible audio track is transcoded.
+    async fn remux_video(&mut self, input_path: &Path, output_path: &Path, video_info: Option<&VideoInfo>) -> Result<()> {
+        let encoder = VideoEncoder::resolve(self.config.video_codec).await?;
+        info!(
+            "📦 Remuxing video without re-encoding: {}",
+            input_path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        
+        if self.should_stop() {
+            return Err(OptimizeError::Cancelled("Video remux".to_string()).into());
+        }
+        
+        if let Some(ref plan) = Self::stream_plan(&self.config, video_info) {
+            self.log_stream_plan(plan, video_info);
+        }
+        let mut args = to_string_vec(["-i", input_path.to_str().unwrap()]);
+        args.extend(Self::remux_args(&self.config, &encoder, video_info));
+        args.extend(to_string_vec(["-y", output_path.to_str().unwrap()]));
         
+        let start_time = std::time::Instant::now();
+        ffmpeg::run(&args, input_path, video_info.map(|info| info.duration), self.progress.as_ref()).await?;
+        
+        if self.should_stop() {
+            return Err(OptimizeError::Cancelled("Video remux".to_string()).into());
+        }
+        
+        info!("✅ Video remux completed in {:.1}s", start_time.elapsed().as_secs_f64());
         Ok(())
     }
     
+    /// Builds the stream-copy arguments of a remux (everything between input and output).
+    /// 
+    /// - Video is always copied (`-c:v copy`), HEVC tagged `hvc1` for Apple players
+    /// - Audio is copied when the target container accepts it and no audio
+    ///   processing is configured, otherwise transcoded (or dropped with `none`)
+    /// - Audio tracks, text subtitles and chapters follow `config.stream_policy`;
+    ///   subtitles are converted to the container's format, bitmap ones dropped;
+    ///   without a source analysis all subtitles are dropped (`-sn`)
+    /// - MP4 outputs get `+faststart`
+    pub fn remux_args(config: &Config, encoder: &VideoEncoder, video_info: Option<&VideoInfo>) -> Vec<String> {
+        let streams = Self::stream_plan(config, video_info);
+        let mut args = streams.as_ref().map(StreamPlan::video_args).unwrap_or_default();
+        args.extend(to_string_vec(["-c:v", "copy"]));
+        if video_info.is_some_and(|info| info.codec == "hevc") && !config.video_codec.is_webm() {
+            args.extend(to_string_vec(["-tag:v", "hvc1"]));
+        }
+        
+        args.extend(encoder.audio_args(config, video_info, true));
+        
+        match streams {
+            Some(plan) => args.extend(plan.other_args()),
+            None => args.push("-sn".to_string()),
+        }
+        args.extend(to_string_vec(["-map_metadata", "0"]));
+        args.extend(encoder.remux_container_args());
+        args
+    }
+    
+    /// Decides whether a video should be encoded, remuxed or skipped.
+    /// 
+    /// The expected output bitrate is derived from a bits-per-pixel budget for the
+    /// configured codec at the configured CRF. If even that bitrate would not get
+    /// the file below `size_threshold`, encoding is pointless: the video is only
+    /// remuxed when its container must change or the audio and stream settings
+    /// would change the file, and skipped otherwise.
+    /// 
+    /// # Arguments
+    /// * `config` - Codec, CRF, filters and size threshold
+    /// * `info` - ffprobe analysis of the source
+    /// * `input_path` - Source path (its extension is the current container)
+    /// * `source_size` - Source size in bytes
+    pub fn plan(config: &Config, info: &VideoInfo, input_path: &Path, source_size: u64) -> VideoDecision {
+        let target_container = config.video_codec.container_extension();
+        
+        if config.remux_videos {
+            return if config.video_codec.container_accepts(&info.codec) {
+                VideoDecision {
+                    action: VideoAction::Remux,
+                    reason: format!("remux mode: {} stream copied into {}", info.codec, target_container),
+                    target_bitrate: info.bitrate,
+                    estimated_size: source_size,
+                }
+            } else {
+                VideoDecision::encode(&format!("remux mode: {} cannot be stored in {}", info.codec, target_container), 0, 0)
+            };
+        }
+        
+        if !Self::video_filters(config, info).is_empty() {
+            return VideoDecision::encode("resolution or frame rate above the configured limits", 0, 0);
+        }
+        
+        let pixels_per_second = info.width as f64 * info.height as f64 * info.frame_rate;
+        if pixels_per_second <= 0.0 || info.duration <= 0.0 || info.bitrate == 0 {
+            return VideoDecision::encode("incomplete stream information", 0, 0);
+        }
+        
+        let audio_bitrate = match config.audio_codec {
+            AudioCodec::Disabled => 0,
+            _ => parse_bitrate(&config.audio_bitrate).unwrap_or(128_000),
+        };
+        // A target bitrate/size replaces the bits-per-pixel estimate
+        let video_bitrate = match Self::target_bitrate(config, Some(info)) {
+            Ok(Some(bitrate)) => bitrate,
+            _ => (pixels_per_second * target_bits_per_pixel(config)) as u64,
+        };
+        let target_bitrate = video_bitrate + audio_bitrate;
+        let estimated_size = info.estimate_compressed_size(target_bitrate);
+        let bpp = info.bits_per_pixel();
+        
+        // Only worth encoding if the estimate lands below the replacement threshold
+        let worth_threshold = (target_bitrate as f64 / config.size_threshold) as u64;
+        let worth_encoding = info.needs_optimization(worth_threshold)
+            && (estimated_size as f64) < source_size as f64 * config.size_threshold;
+        if worth_encoding {
+            return VideoDecision::encode(
+                &format!("{} at {:.3} bpp, expected ~{} after encoding", info.bitrate_string(), bpp, FileManager::format_size(estimated_size)),
+                target_bitrate,
+                estimated_size,
+            );
+        }
+        
+        let source_extension = input_path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_default();
+        let already_efficient = format!("already efficient ({} {} at {:.3} bpp)", info.codec, info.bitrate_string(), bpp);
+        
+        if !config.video_codec.container_accepts(&info.codec) {
+            // Efficient but not playable in the target container: encoding is the only option
+            VideoDecision::encode(&format!("{} cannot be stored in {}", info.codec, target_container), target_bitrate, estimated_size)
+        } else if source_extension == target_container {
+            match Self::copy_changes(config, info) {
+                // The audio or track settings still change the file: stream copy with those applied
+                Some(change) => VideoDecision {
+                    action: VideoAction::Remux,
+                    reason: format!("{}, {}", already_efficient, change),
+                    target_bitrate,
+                    estimated_size,
+                },
+                None => VideoDecision { action: VideoAction::Skip, reason: already_efficient, target_bitrate, estimated_size },
+            }
+        } else {
+            VideoDecision {
+                action: VideoAction::Remux,
+                reason: format!("{}, container {} -> {}", already_efficient, source_extension, target_container),
+                target_bitrate,
+                estimated_size,
+            }
+        }
+    }
+    
+    /// Changes that the audio and stream settings make even when the video
+    /// stream is kept: dropped, normalised, downmixed or converted audio and
+    /// tracks left out by `stream_policy`.
+    /// 
+    /// # Returns
+    /// * `Option<&'static str>` - The first change found (None: copying the source unchanged is correct)
+    fn copy_changes(config: &Config, info: &VideoInfo) -> Option<&'static str> {
+        let tracks = |kind: StreamKind| info.streams.iter().filter(move |stream| stream.kind == kind && !stream.attached_pic);
+        let has_audio = info.audio_codec.is_some() || tracks(StreamKind::Audio).next().is_some();
+        let target_audio = match config.audio_codec {
+            AudioCodec::Aac => Some("aac"),
+            AudioCodec::Opus => Some("opus"),
+            _ => None,
+        };
+        
+        if has_audio && config.audio_codec == AudioCodec::Disabled {
+            Some("audio dropped")
+        } else if has_audio && config.loudness_target.is_some() {
+            Some("loudness normalised")
+        } else if has_audio && config.audio_channels.is_some_and(|max| info.audio_channels.is_none_or(|channels| channels > max)) {
+            Some("audio downmixed")
+        } else if target_audio.is_some_and(|codec| info.audio_codec.as_deref().is_some_and(|source| source != codec)
+            || tracks(StreamKind::Audio).any(|stream| stream.codec != codec)) {
+            Some("audio converted")
+        } else if config.stream_policy != StreamPolicy::All && tracks(StreamKind::Video).count() > 1 {
+            Some("extra video streams dropped")
+        } else if config.stream_policy == StreamPolicy::NoSubtitles && tracks(StreamKind::Subtitle).next().is_some() {
+            Some("subtitles dropped")
+        } else {
+            None
+        }
+    }
+    
+    /// Logs the pre-flight decision and emits it as a `video_analysis` JSON event.
+    fn report_decision(&self, input_path: &Path, info: Option<&VideoInfo>, decision: &VideoDecision) {
+        if self.config.json_output {
+            JsonMessage::video_analysis(input_path.to_path_buf(), info, decision).emit();
+        }
+        info!(
+            "🔍 Pre-flight {}: {:?} ({})",
+            input_path.file_name().unwrap_or_default().to_string_lossy(),
+            decision.action,
+            decision.reason
+        );
+    }
+    
+    /// Builds the ffmpeg video filters for `max_video_fps`, `max_video_dimension`
+    /// and HDR tone mapping (`hdr_policy`).
+    /// 
+    /// The frame rate is capped first, so that the scaler processes fewer frames,
+    /// and tone mapping runs last, on the smallest frames.
+    /// Nothing is added when the source is already within the limits (no upscaling).
+    /// 
+    /// # Returns
+    /// * `Vec<String>` - Filters to join with `,` for `-vf` (empty when not needed)
+    pub fn video_filters(config: &Config, info: &VideoInfo) -> Vec<String> {
+        let mut filters = Vec::new();
+        
+        if let Some(max_fps) = config.max_video_fps {
+            if info.exceeds_frame_rate(max_fps) {
+                filters.push(format!("fps={}", max_fps));
+            }
+        }
+        
+        if let Some(max_dimension) = config.max_video_dimension {
+            if let Some((width, height)) = info.scaled_dimensions(max_dimension) {
+                filters.push(format!("scale={}:{}:flags=lanczos", width, height));
+            }
+        }
+        
+        if ColorPlan::new(config, Some(&info.color)).tone_map {
+            filters.push(video_color::TONE_MAP_FILTER.to_string());
+        }
+        
+        filters
+    }
+    
     /// Preserves original video metadata using exiftool.
     /// 
     /// This method copies all metadata from the original video file to the compressed
@@ -489,6 +1039,7 @@ impl VideoProcessor {
     /// # Arguments
     /// * `source` - Path to the original video file (metadata source)
     /// * `target` - Path to the compressed video file (metadata destination)
+    /// * `upright` - The frames were re-encoded upright: the source rotation is not copied
     /// 
     /// # Returns
     /// * `Result<()>` - Success or error information
@@ -503,22 +1054,31 @@ impl VideoProcessor {
     /// exiftool -tagsFromFile source.mp4 -extractEmbedded -all:all \
     ///          -FileModifyDate -overwrite_original target.mp4
     /// ```
-    async fn preserve_metadata(&self, source: &Path, target: &Path) -> Result<()> {
+    async fn preserve_metadata(&self, source: &Path, target: &Path, upright: bool) -> Result<()> {
         // debug!("📝 Preserving video metadata from {} to {}", source.display(), target.display());
         
         let platform = PlatformCommands::instance();
         let exiftool_cmd = platform.get_command("exiftool");
         
+        let mut args = vec![
+            "-tagsFromFile",                       // Copy tags from source file
+            source.to_str().unwrap(),              // Source file path
+            "-extractEmbedded",                    // Extract embedded metadata
+            "-all:all",                            // Copy all available tags
+        ];
+        if upright {
+            // The track matrix would rotate the already rotated frames again
+            args.extend(["--Rotation", "--MatrixStructure"]);
+        }
+        args.extend([
+            "-FileModifyDate",                     // Preserve file modification date
+            "-overwrite_original",                 // Overwrite target file safely
+            target.to_str().unwrap(),              // Target file path
+        ]);
+        
         let output = Command::new(exiftool_cmd)
-            .args([
-                "-tagsFromFile",                       // Copy tags from source file
-                source.to_str().unwrap(),              // Source file path
-                "-extractEmbedded",                    // Extract embedded metadata
-                "-all:all",                            // Copy all available tags
-                "-FileModifyDate",                     // Preserve file modification date
-                "-overwrite_original",                 // Overwrite target file safely
-                target.to_str().unwrap(),              // Target file path
-            ])
+            .args(&args)
+            .kill_on_drop(true)
             .output().await
             .map_err(|e| anyhow::anyhow!("Failed to execute exiftool: {}", e))?;
         
@@ -563,7 +1123,7 @@ impl VideoProcessor {
     /// * `Result<VideoInfo>` - Structured video information or error
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let processor = VideoProcessor::new(config);
     /// let info = processor.get_video_info(Path::new("video.mp4")).await?;
     /// // println!("Duration: {:.1}s, Resolution: {}x{}", 
@@ -572,31 +1132,12 @@ impl VideoProcessor {
     /// 
     /// # ffprobe Command
     /// ```bash
-    /// ffprobe -v quiet -// print_format json -show_format -show_streams video.mp4
+    /// ffprobe -v quiet -print_format json -show_format -show_streams video.mp4
     /// ```
     pub async fn get_video_info(&self, video_path: &Path) -> Result<VideoInfo> {
         // debug!("📊 Analyzing video properties: {}", video_path.display());
         
-        let platform = PlatformCommands::instance();
-        let ffprobe_cmd = platform.get_command("ffprobe");
-        
-        let output = Command::new(ffprobe_cmd)
-            .args([
-                "-v", "quiet",                         // Suppress informational output
-                "-// print_format", "json",               // Output in JSON format
-                "-show_format",                        // Show container format info
-                "-show_streams",                       // Show stream details
-                video_path.to_str().unwrap(),          // Input video file
-            ])
-            .output().await
-            .map_err(|e| anyhow::anyhow!("Failed to execute ffprobe: {}", e))?;
-        
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            return Err(OptimizeError::FFmpeg(error_msg.to_string()).into());
-        }
-        
-        let info_str = String::from_utf8_lossy(&output.stdout);
+        let info_str = ffmpeg::probe(video_path).await?;
         let video_info = VideoInfo::from_ffprobe_json(&info_str)?;
         
         debug!("📊 Video analysis complete: {}x{}, {:.1}s, {} codec", 
@@ -605,6 +1146,138 @@ impl VideoProcessor {
         Ok(video_info)
     }
     
+    /// Creates a storyboard sprite and its WebVTT index for every configured profile.
+    /// 
+    /// Outputs go to `output/storyboards/<profile>/<relative path>/<stem>.jpg`
+    /// and `<stem>.vtt`; the layout and the VTT format are described in the
+    /// `storyboard` module. A partial sprite is removed on failure or cancellation.
+    /// 
+    /// # Returns
+    /// * `Result<Vec<PathBuf>>` - Paths of the sprites and tracks created (empty
+    ///   when the duration is unknown)
+    pub async fn create_storyboards(&mut self, input_path: &Path, input_base_dir: &Path) -> Result<Vec<PathBuf>> {
+        if self.config.storyboards.is_empty() {
+            return Ok(Vec::new());
+        }
+        let output_base = self.config.output_path.clone()
+            .ok_or_else(|| anyhow::anyhow!("Storyboard creation requires an output directory to be configured"))?;
+        let video_info = self.get_video_info(input_path).await?;
+        let file_stem = input_path.file_stem()
+            .ok_or_else(|| anyhow::anyhow!("Invalid file name: {}", input_path.display()))?
+            .to_string_lossy()
+            .to_string();
+        let relative_dir = input_path.strip_prefix(input_base_dir)
+            .unwrap_or(input_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+        
+        // Sprite JPEG, sempre SDR
+        let tone_map = video_info.color.is_hdr() && video_color::tone_mapping_available().await;
+        
+        let mut created = Vec::new();
+        let mut guard = TempFileGuard::default();
+        let profiles = self.config.storyboards.clone();
+        for (name, profile) in &profiles {
+            if self.should_stop() {
+                return Err(OptimizeError::Cancelled("Storyboard creation".to_string()).into());
+            }
+            let Some(layout) = StoryboardLayout::new(profile, &video_info) else {
+                warn!("Unknown duration, no storyboard for {}", input_path.display());
+                return Ok(created);
+            };
+            
+            let output_dir = output_base.join("storyboards").join(name).join(relative_dir);
+            tokio::fs::create_dir_all(&output_dir).await?;
+            let sprite_name = format!("{}.jpg", file_stem);
+            let sprite_path = guard.track(output_dir.join(&sprite_name));
+            let track_path = output_dir.join(format!("{}.vtt", file_stem));
+            
+            ffmpeg::run(&layout.ffmpeg_args(input_path, tone_map, &sprite_path), input_path, Some(video_info.duration), self.progress.as_ref()).await?;
+            tokio::fs::write(&track_path, layout.webvtt(&sprite_name)).await?;
+            for path in [&sprite_path, &track_path] {
+                FileManager::copy_file_attributes(input_path, path, self.config.preserve_ownership).await?;
+            }
+            guard.keep(&sprite_path);
+            
+            debug!("Storyboard '{}' for {}: {} frames, {}x{} sprite", 
+                   name, input_path.display(), layout.frames, layout.sheet_size().0, layout.sheet_size().1);
+            created.push(sprite_path);
+            created.push(track_path);
+        }
+        
+        Ok(created)
+    }
+    
+    /// Packages a long video as an adaptive streaming bundle (HLS or DASH).
+    /// 
+    /// Every ladder rung that fits the source is encoded in a single ffmpeg run
+    /// into a staging directory next to the final bundle, which then replaces
+    /// any previous bundle (`<stem>.hls` / `<stem>.dash`, see the `streaming`
+    /// module). In dry-run mode the staging directory is discarded.
+    /// 
+    /// # Returns
+    /// * `Result<Option<StreamingBundle>>` - None when streaming is not configured
+    ///   or the video is shorter than `streaming_min_duration`
+    pub async fn package_streaming(&mut self, input_path: &Path, input_base_dir: &Path) -> Result<Option<StreamingBundle>> {
+        let Some(format) = self.config.streaming else {
+            return Ok(None);
+        };
+        let video_info = self.get_video_info(input_path).await?;
+        if video_info.duration < self.config.streaming_min_duration {
+            debug!("{} is shorter than {:.0}s, no streaming bundle", 
+                   input_path.display(), self.config.streaming_min_duration);
+            return Ok(None);
+        }
+        
+        let encoder = VideoEncoder::resolve(self.config.video_codec).await?;
+        if ColorPlan::new(&self.config, Some(&video_info.color)).tone_map && !video_color::tone_mapping_available().await {
+            return Err(OptimizeError::MissingDependency(
+                "HDR tone mapping needs an ffmpeg built with the zscale filter (libzimg)".to_string()
+            ).into());
+        }
+        let rungs = streaming::select_rungs(&self.config.streaming_ladder, &video_info);
+        let bundle_dir = PathResolver::get_streaming_bundle_dir(input_path, input_base_dir, &self.config)?
+            .ok_or_else(|| anyhow::anyhow!("Streaming bundle path unavailable for {}", input_path.display()))?;
+        let parent = bundle_dir.parent().unwrap_or(Path::new("."));
+        tokio::fs::create_dir_all(parent).await?;
+        
+        // Staging nella stessa directory: il rename finale è atomico
+        let staging = tempfile::Builder::new().prefix(".streaming-").tempdir_in(parent)?;
+        if format == StreamingFormat::Hls {
+            for index in 0..streaming::stream_count(&self.config, &video_info, &rungs) {
+                tokio::fs::create_dir_all(staging.path().join(format!("stream_{}", index))).await?;
+            }
+        }
+        
+        info!("Packaging {} as {} ({} renditions, {})", 
+              input_path.display(), format.as_str(), rungs.len(), encoder.name);
+        let args = streaming::package_args(&self.config, format, &encoder, &video_info, &rungs, input_path, staging.path());
+        ffmpeg::run(&args, input_path, Some(video_info.duration), self.progress.as_ref()).await?;
+        
+        if self.should_stop() {
+            return Err(OptimizeError::Cancelled("Streaming packaging".to_string()).into());
+        }
+        
+        let size = streaming::bundle_size(staging.path());
+        if !self.config.dry_run {
+            if tokio::fs::try_exists(&bundle_dir).await.unwrap_or(false) {
+                tokio::fs::remove_dir_all(&bundle_dir).await?;
+            }
+            tokio::fs::rename(staging.keep(), &bundle_dir).await?;
+        }
+        
+        let manifest = bundle_dir.join(format.manifest_name());
+        if !self.config.dry_run {
+            FileManager::copy_file_attributes(input_path, &manifest, self.config.preserve_ownership).await?;
+        }
+        Ok(Some(StreamingBundle {
+            dir: bundle_dir,
+            manifest,
+            size,
+            renditions: rungs.len(),
+        }))
+    }
+    
     /// Verifies that all required external tools are available for video processing.
     /// 
     /// This method checks for the availability of essential tools needed for video
@@ -625,7 +1298,7 @@ impl VideoProcessor {
     /// helping users understand what needs to be installed.
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// // Check dependencies before processing
     /// VideoProcessor::check_dependencies().await?;
     /// 
@@ -683,7 +1356,7 @@ impl VideoProcessor {
     /// this method doesn't fail but reports the status of each tool.
     /// 
     /// # Example Output
-    /// ```
+    /// ```text
     /// 🔧 Checking available video processing tools:
     ///   ✅ ffmpeg - Video compression and encoding
     ///   ✅ ffprobe - Video analysis and property extraction  
@@ -719,7 +1392,7 @@ impl VideoProcessor {
     /// * `(broadcast::Sender<()>, broadcast::Receiver<()>)` - Sender and receiver for cancellation signals
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let (stop_sender, stop_receiver) = VideoProcessor::create_cancellation_channel(1);
     /// let processor = VideoProcessor::new_with_cancellation(config, stop_receiver);
     /// 
@@ -767,6 +1440,20 @@ pub struct VideoInfo {
     pub height: u32,
     /// Video codec name (e.g., "h264", "hevc", "vp9")
     pub codec: String,
+    /// Codec of the first audio stream (None for silent videos)
+    pub audio_codec: Option<String>,
+    /// Channel count of the first audio stream
+    pub audio_channels: Option<u32>,
+    /// Average frame rate in frames per second (0 if unknown)
+    pub frame_rate: f64,
+    /// Display rotation in degrees, normalized to 0, 90, 180 or 270
+    pub rotation: u32,
+    /// Color space, HDR signaling and bit depth of the video stream
+    pub color: ColorInfo,
+    /// Every stream in the file (video, audio, subtitles, attachments)
+    pub streams: Vec<StreamInfo>,
+    /// Number of chapters
+    pub chapters: usize,
 }
 
 impl VideoInfo {
@@ -795,7 +1482,9 @@ impl VideoInfo {
     ///       "codec_type": "video",
     ///       "codec_name": "h264",
     ///       "width": 1920,
-    ///       "height": 1080
+    ///       "height": 1080,
+    ///       "avg_frame_rate": "30000/1001",
+    ///       "side_data_list": [{ "rotation": -90 }]
     ///     }
     ///   ]
     /// }
@@ -814,7 +1503,7 @@ impl VideoInfo {
     /// - Missing codec information defaults to "unknown"
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let json_output = r#"{"format":{"duration":"60.0","bit_rate":"2000000"},...}"#;
     /// let video_info = VideoInfo::from_ffprobe_json(json_output)?;
     /// ```
@@ -836,8 +1525,9 @@ impl VideoInfo {
         // Locate the primary video stream
         let empty_vec = vec![];
         let streams = info["streams"].as_array().unwrap_or(&empty_vec);
+        // Cover art (attached_pic) is a video stream too, but never the primary one
         let video_stream = streams.iter()
-            .find(|s| s["codec_type"] == "video")
+            .find(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"] != 1)
             .unwrap_or(&serde_json::Value::Null);
         
         // Extract video stream properties
@@ -848,15 +1538,91 @@ impl VideoInfo {
             .unwrap_or("unknown")
             .to_string();
         
+        let audio_stream = streams.iter().find(|s| s["codec_type"] == "audio");
+        let audio_codec = audio_stream
+            .and_then(|s| s["codec_name"].as_str())
+            .map(|name| name.to_string());
+        let audio_channels = audio_stream
+            .and_then(|s| s["channels"].as_u64())
+            .map(|channels| channels as u32);
+        
+        // Frame rate as a fraction ("60000/1001"); r_frame_rate as fallback
+        let frame_rate = ["avg_frame_rate", "r_frame_rate"]
+            .iter()
+            .filter_map(|key| video_stream[*key].as_str().and_then(parse_frame_rate))
+            .next()
+            .unwrap_or(0.0);
+        
+        // Rotation: display matrix side data (current ffmpeg) or legacy "rotate" tag
+        let rotation = video_stream["side_data_list"]
+            .as_array()
+            .and_then(|list| list.iter().find_map(|side_data| side_data["rotation"].as_f64()))
+            .or_else(|| video_stream["tags"]["rotate"].as_str().and_then(|r| r.parse::<f64>().ok()))
+            .map(|degrees| ((degrees.round() as i64).rem_euclid(360)) as u32)
+            .unwrap_or(0);
+        
         Ok(VideoInfo {
             duration,
             bitrate,
             width,
             height,
             codec,
+            audio_codec,
+            audio_channels,
+            frame_rate,
+            rotation,
+            color: ColorInfo::from_stream(video_stream),
+            streams: StreamInfo::from_ffprobe_streams(streams),
+            chapters: info["chapters"].as_array().map_or(0, |chapters| chapters.len()),
         })
     }
     
+    /// Returns the dimensions as displayed, with the rotation metadata applied.
+    pub fn display_dimensions(&self) -> (u32, u32) {
+        if self.rotation % 180 == 90 {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+    
+    /// Computes the displayed size whose long edge fits `max_long_edge`.
+    /// 
+    /// Keeps the aspect ratio and rounds both sides down to even values
+    /// (required by 4:2:0 chroma subsampling).
+    /// 
+    /// # Returns
+    /// * `Option<(u32, u32)>` - Target size, or None when no downscale is needed
+    pub fn scaled_dimensions(&self, max_long_edge: u32) -> Option<(u32, u32)> {
+        let (width, height) = self.display_dimensions();
+        let long_edge = width.max(height);
+        if long_edge == 0 || long_edge <= max_long_edge {
+            return None;
+        }
+        
+        let scale = max_long_edge as f64 / long_edge as f64;
+        let even = |side: u32| (((side as f64 * scale + 1e-6) / 2.0).floor() as u32 * 2).max(2);
+        Some((even(width), even(height)))
+    }
+    
+    /// Returns the overall bits per pixel per frame (0 when unknown).
+    /// 
+    /// Typical values: ~0.1 for H.264 phone footage, ~0.05 for HEVC, several
+    /// times higher for intermediate or screen-recording codecs.
+    pub fn bits_per_pixel(&self) -> f64 {
+        let pixels_per_second = self.width as f64 * self.height as f64 * self.frame_rate;
+        if pixels_per_second > 0.0 {
+            self.bitrate as f64 / pixels_per_second
+        } else {
+            0.0
+        }
+    }
+    
+    /// Checks whether the frame rate is above `max_fps` (29.97 does not exceed 30).
+    pub fn exceeds_frame_rate(&self, max_fps: u32) -> bool {
+        self.frame_rate > max_fps as f64 + 0.01
+    }
+    
     /// Determines if a video would benefit from optimization based on target bitrate.
     /// 
     /// This method provides a simple heuristic for deciding whether a video should
@@ -881,7 +1647,7 @@ impl VideoInfo {
     /// * `bool` - True if optimization is recommended, false otherwise
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let video_info = VideoInfo::from_ffprobe_json(json_output)?;
     /// let target_bitrate = 2_000_000; // 2 Mbps
     /// 
@@ -931,7 +1697,7 @@ impl VideoInfo {
     /// * `u64` - Estimated compressed file size in bytes
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let video_info = VideoInfo::from_ffprobe_json(json_output)?;
     /// let target_bitrate = 1_500_000; // 1.5 Mbps
     /// 
@@ -953,7 +1719,7 @@ impl VideoInfo {
     /// * `String` - Resolution in "WIDTHxHEIGHT" format (e.g., "1920x1080")
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let video_info = VideoInfo::from_ffprobe_json(json_output)?;
     /// // println!("Resolution: {}", video_info.resolution_string());
     /// // Output: "Resolution: 1920x1080"
@@ -968,7 +1734,7 @@ impl VideoInfo {
     /// * `String` - Duration in "MM:SS" or "HH:MM:SS" format
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let video_info = VideoInfo::from_ffprobe_json(json_output)?;
     /// // println!("Duration: {}", video_info.duration_string());
     /// // Output: "Duration: 1:23:45" for a 1 hour, 23 minute, 45 second video
@@ -992,7 +1758,7 @@ impl VideoInfo {
     /// * `String` - Bitrate in appropriate units (kbps, Mbps, etc.)
     /// 
     /// # Example
-    /// ```rust
+    /// ```rust,ignore
     /// let video_info = VideoInfo::from_ffprobe_json(json_output)?;
     /// // println!("Bitrate: {}", video_info.bitrate_string());
     /// // Output: "Bitrate: 2.5 Mbps"
@@ -1008,4 +1774,349 @@ impl VideoInfo {
             format!("{} bps", self.bitrate)
         }
     }
-}
\ No newline at end of file
+}
+/// Outcome of the pre-flight analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoAction {
+    /// Full re-encode with the configured codec
+    Encode,
+    /// Stream copy into the target container
+    Remux,
+    /// Keep the source as it is
+    Skip,
+}
+
+/// Pre-flight decision for a video, with the estimates that led to it
+#[derive(Debug, Clone)]
+pub struct VideoDecision {
+    pub action: VideoAction,
+    /// Human-readable explanation, also reported in JSON
+    pub reason: String,
+    /// Expected output bitrate in bits per second (0 if not estimated)
+    pub target_bitrate: u64,
+    /// Expected output size in bytes (0 if not estimated)
+    pub estimated_size: u64,
+}
+
+impl VideoDecision {
+    fn encode(reason: &str, target_bitrate: u64, estimated_size: u64) -> Self {
+        Self { action: VideoAction::Encode, reason: reason.to_string(), target_bitrate, estimated_size }
+    }
+    
+    /// Decision used when ffprobe fails: encode as before the pre-flight existed
+    fn unanalyzed() -> Self {
+        Self::encode("ffprobe analysis unavailable", 0, 0)
+    }
+}
+
+/// Share of a target size reserved for the container (index, headers, muxing)
+pub const CONTAINER_OVERHEAD: f64 = 0.02;
+
+/// Lowest video bitrate accepted for a target size, in bits per second
+pub const MIN_TARGET_BITRATE: u64 = 100_000;
+
+/// Bits per pixel per frame expected from the configured codec and CRF.
+/// 
+/// Reference values are for CRF 26 (x264 scale); every 6 CRF steps halve or
+/// double the bitrate.
+fn target_bits_per_pixel(config: &Config) -> f64 {
+    let reference = match config.video_codec {
+        VideoCodec::H264 => 0.07,
+        VideoCodec::Hevc | VideoCodec::Vp9 => 0.045,
+        VideoCodec::Av1 => 0.035,
+    };
+    reference * 2f64.powf((26.0 - config.video_crf as f64) / 6.0)
+}
+
+/// Parses an ffprobe frame rate ("30000/1001" or "25") into frames per second.
+fn parse_frame_rate(value: &str) -> Option<f64> {
+    let rate = match value.split_once('/') {
+        Some((num, den)) => {
+            let den = den.parse::<f64>().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num.parse::<f64>().ok()? / den
+        }
+        None => value.parse::<f64>().ok()?,
+    };
+    (rate > 0.0).then_some(rate)
+}
+
+/// Fixture condivise dai test dei moduli video
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::VideoInfo;
+
+    /// Video H.264 a 30 fps e 8 Mbit/s, senza audio né altre tracce.
+    /// 
+    /// I campi non impostati vengono dal parser di ffprobe: i test
+    /// sovrascrivono solo quelli che verificano (`..video(...)`).
+    pub(crate) fn video(width: u32, height: u32, duration: f64) -> VideoInfo {
+        VideoInfo {
+            width,
+            height,
+            duration,
+            bitrate: 8_000_000,
+            codec: "h264".to_string(),
+            frame_rate: 30.0,
+            ..VideoInfo::from_ffprobe_json("{}").unwrap()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(width: u32, height: u32, frame_rate: &str, rotation: i32) -> VideoInfo {
+        let json = serde_json::json!({
+            "format": { "duration": "12.5", "bit_rate": "40000000" },
+            "streams": [
+                { "codec_type": "audio", "codec_name": "aac", "channels": 2 },
+                {
+                    "codec_type": "video",
+                    "codec_name": "hevc",
+                    "width": width,
+                    "height": height,
+                    "avg_frame_rate": frame_rate,
+                    "side_data_list": [{ "side_data_type": "Display Matrix", "rotation": rotation }]
+                }
+            ]
+        });
+        VideoInfo::from_ffprobe_json(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_ffprobe_parsing() {
+        let info = probe(3840, 2160, "60000/1001", -90);
+        assert_eq!(info.codec, "hevc");
+        assert_eq!(info.rotation, 270);
+        assert!((info.frame_rate - 59.94).abs() < 0.01);
+        assert_eq!(info.display_dimensions(), (2160, 3840));
+
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate("25"), Some(25.0));
+    }
+
+    #[test]
+    fn test_scale_and_fps_filters() {
+        let config = Config {
+            max_video_dimension: Some(1920),
+            max_video_fps: Some(30),
+            ..Default::default()
+        };
+
+        // 4K60 landscape: fps cap first, then scale
+        let filters = VideoProcessor::video_filters(&config, &probe(3840, 2160, "60/1", 0));
+        assert_eq!(filters, ["fps=30", "scale=1920:1080:flags=lanczos"]);
+
+        // Portrait (90° rotation): the long edge is the displayed height
+        let filters = VideoProcessor::video_filters(&config, &probe(3840, 2160, "30/1", 90));
+        assert_eq!(filters, ["scale=1080:1920:flags=lanczos"]);
+
+        // Already within limits: no upscale, 29.97 does not exceed 30
+        assert!(VideoProcessor::video_filters(&config, &probe(1280, 720, "30000/1001", 0)).is_empty());
+
+        // HDR is tone-mapped only on request, after the scaler
+        let mut hdr = probe(3840, 2160, "30/1", 0);
+        hdr.color.transfer = Some("arib-std-b67".to_string());
+        assert_eq!(VideoProcessor::video_filters(&config, &hdr).len(), 1);
+        let tone_map = Config { hdr_policy: crate::config::HdrPolicy::ToneMap, ..config.clone() };
+        let filters = VideoProcessor::video_filters(&tone_map, &hdr);
+        assert_eq!(filters.last().unwrap(), video_color::TONE_MAP_FILTER);
+
+        // Odd sides are rounded down to even values
+        let info = probe(1000, 750, "25/1", 0);
+        assert_eq!(info.scaled_dimensions(333), Some((332, 248)));
+    }
+
+    #[test]
+    fn test_remux_args() {
+        let config = Config::default();
+        let encoder = VideoEncoder { codec: VideoCodec::H264, name: "libx264" };
+
+        // HEVC + AAC from a .mov: everything copied, hvc1 tag, faststart
+        let info = probe(1920, 1080, "30/1", 0);
+        let args = VideoProcessor::remux_args(&config, &encoder, Some(&info));
+        assert_eq!(&args[..4], ["-c:v", "copy", "-tag:v", "hvc1"]);
+        assert!(args.windows(2).any(|w| w == ["-c:a", "copy"]));
+        assert!(args.windows(2).any(|w| w == ["-movflags", "+faststart+use_metadata_tags"]));
+
+        // PCM audio (typical of MOV/AVI) is not valid in MP4: only audio is transcoded
+        let pcm = VideoInfo { audio_codec: Some("pcm_s16le".to_string()), ..info.clone() };
+        let args = VideoProcessor::remux_args(&config, &encoder, Some(&pcm));
+        assert!(args.windows(2).any(|w| w == ["-c:a", "aac"]));
+        assert!(args.windows(2).any(|w| w == ["-c:v", "copy"]));
+
+        // Audio processing forces a transcode even when the track could be copied
+        let loud = Config { loudness_target: Some(-16.0), audio_channels: Some(2), ..Default::default() };
+        let surround = VideoInfo { audio_channels: Some(6), ..info.clone() };
+        let args = VideoProcessor::remux_args(&loud, &encoder, Some(&surround));
+        assert!(args.windows(2).any(|w| w == ["-ac", "2"]));
+        assert!(args.windows(2).any(|w| w == ["-af", "loudnorm=I=-16:TP=-1.5:LRA=11"]));
+        // Stereo sources are not touched by the downmix
+        let stereo = VideoInfo { audio_channels: Some(2), ..info.clone() };
+        assert!(!VideoProcessor::remux_args(&loud, &encoder, Some(&stereo)).contains(&"-ac".to_string()));
+
+        let silent = Config { audio_codec: AudioCodec::Disabled, ..Default::default() };
+        assert!(VideoProcessor::remux_args(&silent, &encoder, Some(&info)).contains(&"-an".to_string()));
+        let opus = Config { audio_codec: AudioCodec::Opus, ..Default::default() };
+        assert!(VideoProcessor::remux_args(&opus, &encoder, Some(&info)).windows(2).any(|w| w == ["-c:a", "libopus"]));
+
+        // MKV with two audio tracks, SRT + PGS subtitles and chapters: every track
+        // is mapped, SRT becomes mov_text, the DTS track forces an audio transcode
+        let json = serde_json::json!({
+            "format": { "duration": "5400.0", "bit_rate": "12000000" },
+            "streams": [
+                { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 },
+                { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2, "tags": { "language": "ita" } },
+                { "index": 2, "codec_type": "audio", "codec_name": "dts", "channels": 6, "tags": { "language": "eng" } },
+                { "index": 3, "codec_type": "subtitle", "codec_name": "subrip", "tags": { "language": "ita" } },
+                { "index": 4, "codec_type": "subtitle", "codec_name": "hdmv_pgs_subtitle" }
+            ],
+            "chapters": [{ "id": 0 }, { "id": 1 }]
+        });
+        let mkv = VideoInfo::from_ffprobe_json(&json.to_string()).unwrap();
+        assert_eq!((mkv.streams.len(), mkv.chapters), (5, 2));
+        let args = VideoProcessor::remux_args(&config, &encoder, Some(&mkv));
+        assert_eq!(&args[..4], ["-map", "0:0", "-c:v", "copy"]);
+        assert!(args.windows(2).any(|w| w == ["-map", "0:2"]));
+        assert!(!args.windows(2).any(|w| w == ["-map", "0:4"]));
+        assert!(args.windows(2).any(|w| w == ["-c:s", "mov_text"]));
+        assert!(args.windows(2).any(|w| w == ["-map_chapters", "0"]));
+        assert!(args.windows(2).any(|w| w == ["-c:a", "aac"]));
+        let no_subtitles = Config { stream_policy: crate::config::StreamPolicy::NoSubtitles, ..Default::default() };
+        assert!(!VideoProcessor::remux_args(&no_subtitles, &encoder, Some(&mkv)).contains(&"-c:s".to_string()));
+    }
+
+    #[test]
+    fn test_encode_args_trim() {
+        let config = Config::default();
+        let encoder = VideoEncoder { codec: VideoCodec::H264, name: "libx264" };
+        let trim = TrimPoints { start: 3.2, end: 55.1, source_duration: 60.0 };
+        let color = ColorPlan::new(&config, None);
+        let args = VideoProcessor::encode_args(
+            &config, &encoder, Path::new("/v/clip.mp4"), None, None, Some(&trim), &RateControl::Crf, &color,
+        );
+        assert_eq!(&args[..6], ["-ss", "3.200", "-i", "/v/clip.mp4", "-t", "51.900"]);
+    }
+
+    #[test]
+    fn test_preflight_decision() {
+        let config = Config { video_codec: VideoCodec::Hevc, ..Default::default() };
+        let size_for = |info: &VideoInfo| (info.bitrate as f64 * info.duration / 8.0) as u64;
+
+        // Phone H.264 1080p30 at 20 Mbps: worth encoding
+        let mut info = probe(1920, 1080, "30/1", 0);
+        info.bitrate = 20_000_000;
+        let decision = VideoProcessor::plan(&config, &info, Path::new("/v/a.mp4"), size_for(&info));
+        assert_eq!(decision.action, VideoAction::Encode);
+        assert!(decision.estimated_size < size_for(&info));
+
+        // Low-bitrate HEVC already in MP4: skipped
+        info.bitrate = 2_000_000;
+        let decision = VideoProcessor::plan(&config, &info, Path::new("/v/a.mp4"), size_for(&info));
+        assert_eq!(decision.action, VideoAction::Skip);
+
+        // Same stream in a .mov: only the container changes
+        let decision = VideoProcessor::plan(&config, &info, Path::new("/v/a.MOV"), size_for(&info));
+        assert_eq!(decision.action, VideoAction::Remux);
+
+        // Audio and track settings are applied by a remux even when the video is kept
+        let remuxed = |config: Config, info: &VideoInfo| {
+            let config = Config { video_codec: VideoCodec::Hevc, ..config };
+            VideoProcessor::plan(&config, info, Path::new("/v/a.mp4"), size_for(info)).action
+        };
+        assert_eq!(remuxed(Config { audio_codec: AudioCodec::Disabled, ..Default::default() }, &info), VideoAction::Remux);
+        assert_eq!(remuxed(Config { loudness_target: Some(-16.0), ..Default::default() }, &info), VideoAction::Remux);
+        assert_eq!(remuxed(Config { audio_codec: AudioCodec::Opus, ..Default::default() }, &info), VideoAction::Remux);
+        assert_eq!(remuxed(Config { audio_codec: AudioCodec::Aac, ..Default::default() }, &info), VideoAction::Skip);
+        // Stereo sources are not downmixed, 5.1 sources are
+        let downmix = Config { audio_channels: Some(2), ..Default::default() };
+        assert_eq!(remuxed(downmix.clone(), &info), VideoAction::Skip);
+        let surround = VideoInfo { audio_channels: Some(6), ..info.clone() };
+        assert_eq!(remuxed(downmix, &surround), VideoAction::Remux);
+        // stream_policy drops a second video stream, or the subtitles
+        let with_streams = |streams: serde_json::Value| VideoInfo {
+            streams: StreamInfo::from_ffprobe_streams(streams.as_array().unwrap()),
+            ..info.clone()
+        };
+        let tracks = with_streams(serde_json::json!([
+            { "index": 0, "codec_type": "video", "codec_name": "hevc" },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac" },
+            { "index": 2, "codec_type": "video", "codec_name": "hevc" }
+        ]));
+        assert_eq!(remuxed(Config::default(), &tracks), VideoAction::Remux);
+        assert_eq!(remuxed(Config { stream_policy: StreamPolicy::All, ..Default::default() }, &tracks), VideoAction::Skip);
+        let subtitled = with_streams(serde_json::json!([
+            { "index": 0, "codec_type": "video", "codec_name": "hevc" },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac" },
+            { "index": 2, "codec_type": "subtitle", "codec_name": "mov_text" }
+        ]));
+        assert_eq!(remuxed(Config::default(), &subtitled), VideoAction::Skip);
+        assert_eq!(remuxed(Config { stream_policy: StreamPolicy::NoSubtitles, ..Default::default() }, &subtitled), VideoAction::Remux);
+        
+        // HEVC cannot go into WebM: must be encoded for VP9
+        let vp9 = Config { video_codec: VideoCodec::Vp9, ..Default::default() };
+        let decision = VideoProcessor::plan(&vp9, &info, Path::new("/v/a.mp4"), size_for(&info));
+        assert_eq!(decision.action, VideoAction::Encode);
+
+        // Remux mode ignores the bitrate estimate
+        let remux = Config { remux_videos: true, ..Default::default() };
+        info.bitrate = 20_000_000;
+        let decision = VideoProcessor::plan(&remux, &info, Path::new("/v/a.mkv"), size_for(&info));
+        assert_eq!(decision.action, VideoAction::Remux);
+    }
+
+    #[test]
+    fn test_target_bitrate() {
+        // 10 minutes into 100 MiB: 98% of 1398 kbps, minus 128k of audio
+        let mut info = probe(1920, 1080, "30/1", 0);
+        info.duration = 600.0;
+        let config = Config { video_target_size: Some("100M".to_string()), ..Default::default() };
+        let bitrate = VideoProcessor::target_bitrate(&config, Some(&info)).unwrap().unwrap();
+        assert_eq!(bitrate, 1_242_139);
+
+        // Without audio the whole budget goes to the video
+        let silent = Config { audio_codec: AudioCodec::Disabled, ..config.clone() };
+        assert_eq!(VideoProcessor::target_bitrate(&silent, Some(&info)).unwrap(), Some(1_370_139));
+
+        // Too small for the duration, or duration unknown
+        let tiny = Config { video_target_size: Some("5M".to_string()), ..Default::default() };
+        assert!(VideoProcessor::target_bitrate(&tiny, Some(&info)).is_err());
+        assert!(VideoProcessor::target_bitrate(&config, None).is_err());
+
+        let fixed = Config { video_target_bitrate: Some("2500k".to_string()), ..Default::default() };
+        assert_eq!(VideoProcessor::target_bitrate(&fixed, None).unwrap(), Some(2_500_000));
+        assert_eq!(VideoProcessor::target_bitrate(&Config::default(), Some(&info)).unwrap(), None);
+
+        // The pre-flight estimate follows the target: 20 Mbps source, ~1.4 Mbps output
+        info.bitrate = 20_000_000;
+        let source_size = (info.bitrate as f64 * info.duration / 8.0) as u64;
+        let decision = VideoProcessor::plan(&config, &info, Path::new("/v/a.mp4"), source_size);
+        assert_eq!(decision.action, VideoAction::Encode);
+        assert_eq!(decision.target_bitrate, 1_242_139 + 128_000);
+    }
+
+    #[tokio::test]
+    async fn test_skip_compression_in_place_keeps_original() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let video = temp_dir.path().join("clip.mov");
+        std::fs::write(&video, b"not really a video").unwrap();
+
+        let config = Config { skip_video_compression: true, output_path: None, ..Default::default() };
+        let output = VideoProcessor::new(config).optimize(&video, temp_dir.path()).await.unwrap();
+        assert_eq!(output, video);
+        assert_eq!(std::fs::read(&video).unwrap(), b"not really a video");
+
+        // With an output directory the original is still copied
+        let out_dir = temp_dir.path().join("out");
+        std::fs::create_dir(&out_dir).unwrap();
+        let config = Config { skip_video_compression: true, output_path: Some(out_dir.clone()), ..Default::default() };
+        let output = VideoProcessor::new(config).optimize(&video, temp_dir.path()).await.unwrap();
+        assert_eq!(output, out_dir.join("clip.mov"));
+        assert_eq!(std::fs::read(&output).unwrap(), b"not really a video");
+    }
+}
@@ -0,0 +1,220 @@
+//! # Video Streams Module
+//!
+//! Elenco degli stream di un video e scelta di quelli da portare nell'output.
+//!
+//! ## Responsabilità:
+//! - Legge da ffprobe tipo, codec, lingua e titolo di ogni stream ([`StreamInfo`])
+//! - Applica `config.stream_policy` e costruisce gli argomenti `-map` ([`StreamPlan`])
+//! - Converte i sottotitoli in un formato accettato dal container di output
+//!
+//! ## Politiche:
+//! | Politica       | Video        | Audio | Sottotitoli | Capitoli |
+//! |----------------|--------------|-------|-------------|----------|
+//! | `all`          | tutti        | tutti | sì          | sì       |
+//! | `first-video`  | il primo     | tutti | sì          | sì       |
+//! | `no-subtitles` | il primo     | tutti | no          | sì       |
+//!
+//! Le copertine (stream video `attached_pic`), gli stream dati (timecode,
+//! telemetria) e gli allegati (font MKV) non vengono mai copiati. Lingua,
+//! titolo e disposition (default/forced) delle tracce seguono lo stream.
+//!
+//! ## Sottotitoli:
+//! - MP4: i sottotitoli testuali (SRT, ASS, WebVTT...) diventano `mov_text`
+//! - WebM: diventano `webvtt`
+//! - I sottotitoli bitmap (PGS dei Blu-ray, VobSub dei DVD) non sono
+//!   rappresentabili in MP4/WebM e vengono scartati con un avviso
+
+use crate::config::{AudioCodec, Config, StreamPolicy};
+use crate::utils::to_string_vec;
+use serde::{Deserialize, Serialize};
+
+/// Tipo di uno stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+}
+
+/// Uno stream del file sorgente
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StreamInfo {
+    /// Indice dello stream nel file (quello di `-map 0:N`)
+    pub index: u32,
+    pub kind: StreamKind,
+    /// Nome del codec ffprobe (es. "subrip", "hdmv_pgs_subtitle")
+    pub codec: String,
+    /// Lingua ISO 639-2 (es. "ita")
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// Traccia predefinita
+    pub default: bool,
+    /// Copertina (immagine statica) invece di un video
+    pub attached_pic: bool,
+}
+
+impl StreamInfo {
+    /// Legge gli stream da `ffprobe -show_streams`
+    pub fn from_ffprobe_streams(streams: &[serde_json::Value]) -> Vec<Self> {
+        streams.iter()
+            .filter_map(|stream| {
+                let kind = match stream["codec_type"].as_str()? {
+                    "video" => StreamKind::Video,
+                    "audio" => StreamKind::Audio,
+                    "subtitle" => StreamKind::Subtitle,
+                    "attachment" => StreamKind::Attachment,
+                    _ => StreamKind::Data,
+                };
+                let tag = |key: &str| stream["tags"][key].as_str()
+                    .filter(|value| !value.is_empty() && *value != "und")
+                    .map(|value| value.to_string());
+                Some(Self {
+                    index: stream["index"].as_u64()? as u32,
+                    kind,
+                    codec: stream["codec_name"].as_str().unwrap_or("unknown").to_string(),
+                    language: tag("language"),
+                    title: tag("title"),
+                    default: stream["disposition"]["default"].as_u64() == Some(1),
+                    attached_pic: stream["disposition"]["attached_pic"].as_u64() == Some(1),
+                })
+            })
+            .collect()
+    }
+
+    /// Sottotitolo testuale (convertibile), non bitmap
+    pub fn is_text_subtitle(&self) -> bool {
+        matches!(self.codec.as_str(), "subrip" | "srt" | "ass" | "ssa" | "webvtt" | "mov_text" | "text" | "microdvd" | "subviewer")
+    }
+
+    /// Descrizione breve per i log (es. "#3 subrip ita")
+    pub fn describe(&self) -> String {
+        match self.language {
+            Some(ref language) => format!("#{} {} {}", self.index, self.codec, language),
+            None => format!("#{} {}", self.index, self.codec),
+        }
+    }
+}
+
+/// Stream scelti per l'output
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamPlan {
+    pub video: Vec<u32>,
+    pub audio: Vec<u32>,
+    pub subtitles: Vec<u32>,
+    /// Encoder dei sottotitoli nel container di output
+    pub subtitle_codec: Option<&'static str>,
+    /// Sottotitoli scartati perché non rappresentabili nel container
+    pub dropped: Vec<StreamInfo>,
+}
+
+impl StreamPlan {
+    /// Sceglie gli stream secondo `config.stream_policy`
+    pub fn new(config: &Config, streams: &[StreamInfo]) -> Self {
+        let of_kind = |kind: StreamKind| streams.iter().filter(move |stream| stream.kind == kind);
+        let mut plan = Self::default();
+
+        let mut videos = of_kind(StreamKind::Video).filter(|stream| !stream.attached_pic);
+        plan.video = match config.stream_policy {
+            StreamPolicy::All => videos.map(|stream| stream.index).collect(),
+            _ => videos.next().map(|stream| stream.index).into_iter().collect(),
+        };
+        if config.audio_codec != AudioCodec::Disabled {
+            plan.audio = of_kind(StreamKind::Audio).map(|stream| stream.index).collect();
+        }
+        if config.stream_policy != StreamPolicy::NoSubtitles {
+            for stream in of_kind(StreamKind::Subtitle) {
+                if stream.is_text_subtitle() {
+                    plan.subtitles.push(stream.index);
+                } else {
+                    plan.dropped.push(stream.clone());
+                }
+            }
+        }
+        if !plan.subtitles.is_empty() {
+            plan.subtitle_codec = Some(config.video_codec.subtitle_encoder());
+        }
+        plan
+    }
+
+    /// Mappa solo gli stream video (primo passaggio dei due)
+    pub fn video_args(&self) -> Vec<String> {
+        self.video.iter()
+            .flat_map(|index| to_string_vec(["-map", &format!("0:{}", index)]))
+            .collect()
+    }
+
+    /// Mappa audio e sottotitoli, con il codec dei sottotitoli e i capitoli
+    pub fn other_args(&self) -> Vec<String> {
+        let mut args: Vec<String> = self.audio.iter()
+            .chain(&self.subtitles)
+            .flat_map(|index| to_string_vec(["-map", &format!("0:{}", index)]))
+            .collect();
+        if let Some(codec) = self.subtitle_codec {
+            args.extend(to_string_vec(["-c:s", codec]));
+        }
+        args.extend(to_string_vec(["-map_chapters", "0"]));
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VideoCodec;
+
+    fn mkv_streams() -> Vec<StreamInfo> {
+        let json = serde_json::json!([
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "disposition": { "default": 1 } },
+            { "index": 1, "codec_type": "audio", "codec_name": "eac3", "tags": { "language": "ita" }, "disposition": { "default": 1 } },
+            { "index": 2, "codec_type": "audio", "codec_name": "aac", "tags": { "language": "eng", "title": "Commentary" } },
+            { "index": 3, "codec_type": "subtitle", "codec_name": "subrip", "tags": { "language": "ita" } },
+            { "index": 4, "codec_type": "subtitle", "codec_name": "hdmv_pgs_subtitle", "tags": { "language": "eng" } },
+            { "index": 5, "codec_type": "video", "codec_name": "mjpeg", "disposition": { "attached_pic": 1 } },
+            { "index": 6, "codec_type": "attachment", "codec_name": "ttf" },
+            { "index": 7, "codec_type": "video", "codec_name": "h264", "tags": { "language": "und" } }
+        ]);
+        StreamInfo::from_ffprobe_streams(json.as_array().unwrap())
+    }
+
+    #[test]
+    fn test_stream_parsing() {
+        let streams = mkv_streams();
+        assert_eq!(streams.len(), 8);
+        assert_eq!(streams[2].title.as_deref(), Some("Commentary"));
+        assert!(streams[1].default);
+        assert!(streams[5].attached_pic);
+        assert_eq!(streams[6].kind, StreamKind::Attachment);
+        assert_eq!(streams[7].language, None);
+        assert!(streams[3].is_text_subtitle());
+        assert!(!streams[4].is_text_subtitle());
+        assert_eq!(streams[3].describe(), "#3 subrip ita");
+    }
+
+    #[test]
+    fn test_stream_plan() {
+        let streams = mkv_streams();
+
+        // Default: first real video, every audio track, text subtitles as mov_text
+        let plan = StreamPlan::new(&Config::default(), &streams);
+        assert_eq!((plan.video.clone(), plan.audio.clone(), plan.subtitles.clone()), (vec![0], vec![1, 2], vec![3]));
+        assert_eq!(plan.dropped.len(), 1);
+        assert_eq!(plan.video_args(), ["-map", "0:0"]);
+        assert_eq!(plan.other_args(), ["-map", "0:1", "-map", "0:2", "-map", "0:3", "-c:s", "mov_text", "-map_chapters", "0"]);
+
+        let all = Config { stream_policy: StreamPolicy::All, video_codec: VideoCodec::Vp9, ..Default::default() };
+        let plan = StreamPlan::new(&all, &streams);
+        assert_eq!(plan.video, vec![0, 7]);
+        assert_eq!(plan.subtitle_codec, Some("webvtt"));
+
+        let silent = Config { stream_policy: StreamPolicy::NoSubtitles, audio_codec: AudioCodec::Disabled, ..Default::default() };
+        let plan = StreamPlan::new(&silent, &streams);
+        assert!(plan.audio.is_empty() && plan.subtitles.is_empty() && plan.dropped.is_empty());
+        assert_eq!(plan.other_args(), ["-map_chapters", "0"]);
+
+        assert_eq!("drop-subtitles".parse::<StreamPolicy>().unwrap(), StreamPolicy::NoSubtitles);
+        assert!("some".parse::<StreamPolicy>().is_err());
+    }
+}
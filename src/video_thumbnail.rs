@@ -0,0 +1,332 @@
+//! # Video Thumbnail Module
+//!
+//! Poster e clip di anteprima dei video, nello stesso albero dei thumbnails
+//! delle immagini.
+//!
+//! ## Responsabilità:
+//! - Sceglie il frame del poster scartando i frame neri (fade-in, intro)
+//! - Estrae un poster JPEG (o WebP con `convert_to_webp`) per ogni `ThumbnailSize`
+//! - Con `video_preview_seconds` crea anche una breve clip muta in loop (H.264)
+//!
+//! ## Struttura Output
+//! ```text
+//! /output/thumbnails/gallery/
+//! ├── IMG_0001.jpg              (thumbnail della foto)
+//! ├── IMG_0001.poster.jpg       (poster del video IMG_0001.MOV)
+//! └── IMG_0001.preview.mp4      (anteprima, solo con video_preview_seconds)
+//! ```
+//! Il suffisso `.poster`/`.preview` evita collisioni con le foto che hanno lo
+//! stesso nome del video (es. Live Photo: `IMG_0001.JPG` + `IMG_0001.MOV`).
+//!
+//! ## Scelta del frame:
+//! Si campionano alcuni istanti (10%, 25%, 50%, 75% della durata) con un frame
+//! 32x18 in scala di grigi; il primo non nero diventa il poster. Un frame è
+//! nero con gli stessi criteri del filtro `blackframe` di ffmpeg: almeno il
+//! 98% dei pixel con luminanza ≤ 32. Se sono tutti neri si usa il primo.
+//!
+//! ## HDR:
+//! Poster e clip sono SDR: le sorgenti HDR passano dal tone mapping di
+//! `video_color` quando ffmpeg ha il filtro `zscale`.
+//!
+//! ## Dimensioni:
+//! Come per le immagini (`ResizeMode::Fit`): aspect ratio preservato, mai
+//! upscale. La clip ha dimensioni pari, richieste da yuv420p.
+
+use crate::config::{Config, ThumbnailSize};
+use crate::error::OptimizeError;
+use crate::ffmpeg;
+use crate::file_manager::{FileManager, TempFileGuard};
+use crate::utils::to_string_vec;
+use crate::video_color::{self, TONE_MAP_FILTER};
+use crate::video_processor::VideoInfo;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast;
+use tracing::{debug, info};
+
+/// Istanti campionati per il poster, in frazioni della durata
+const POSTER_CANDIDATES: &[f64] = &[0.10, 0.25, 0.50, 0.75];
+
+/// Dimensioni del frame campionato per riconoscere i frame neri
+const SAMPLE_WIDTH: u32 = 32;
+const SAMPLE_HEIGHT: u32 = 18;
+
+/// Soglie del filtro `blackframe` di ffmpeg
+const BLACK_LUMA_THRESHOLD: u8 = 32;
+const BLACK_PIXEL_RATIO: f64 = 0.98;
+
+/// Crea poster e anteprime dei video
+pub struct VideoThumbnailer {
+    config: Config,
+    stop_receiver: Option<broadcast::Receiver<()>>,
+}
+
+impl VideoThumbnailer {
+    pub fn new(config: Config) -> Self {
+        Self { config, stop_receiver: None }
+    }
+
+    /// Crea un thumbnailer che si interrompe alla ricezione dello stop
+    pub fn new_with_cancellation(config: Config, stop_receiver: broadcast::Receiver<()>) -> Self {
+        Self { config, stop_receiver: Some(stop_receiver) }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if let Some(ref mut receiver) = self.stop_receiver {
+            matches!(receiver.try_recv(), Ok(()) | Err(broadcast::error::TryRecvError::Lagged(_)))
+        } else {
+            false
+        }
+    }
+
+    /// Indica se il file è un video per cui creare poster e anteprime
+    pub fn is_supported(path: &Path) -> bool {
+        FileManager::is_video(path)
+    }
+
+    /// Crea poster (e anteprime) per tutti i thumbnails configurati.
+    ///
+    /// # Returns
+    /// I path dei file creati. Un output parziale viene rimosso se il task
+    /// viene cancellato o ffmpeg fallisce.
+    pub async fn create_previews(&mut self, input_path: &Path, input_base_dir: &Path) -> Result<Vec<PathBuf>> {
+        if self.config.thumbnails.is_empty() {
+            return Ok(Vec::new());
+        }
+        let output_base = self.config.output_path.clone()
+            .ok_or_else(|| anyhow::anyhow!("Thumbnail creation requires an output directory to be configured"))?;
+
+        let info = VideoInfo::from_ffprobe_json(&ffmpeg::probe(input_path).await?)?;
+        let timestamp = self.poster_timestamp(input_path, info.duration).await?;
+        debug!("Poster frame for {} at {:.2}s", input_path.display(), timestamp);
+        // Poster e clip sono sempre SDR: le sorgenti HDR vengono convertite se possibile
+        let tone_map = info.color.is_hdr() && video_color::tone_mapping_available().await;
+        if info.color.is_hdr() && !tone_map {
+            debug!("No zscale filter: HDR previews of {} are not tone-mapped", input_path.display());
+        }
+
+        let mut guard = TempFileGuard::default();
+        let mut created = Vec::new();
+        let thumbnails = self.config.thumbnails.clone();
+        for (name, size) in &thumbnails {
+            if self.should_stop() {
+                return Err(OptimizeError::Cancelled("Video thumbnail creation".to_string()).into());
+            }
+
+            let poster = Self::preview_path(input_path, input_base_dir, &output_base, name, self.poster_extension())?;
+            self.render(&mut guard, input_path, &poster, Self::poster_args(&self.config, input_path, timestamp, size, tone_map, &poster)).await?;
+            created.push(poster);
+
+            if let Some(seconds) = self.config.video_preview_seconds {
+                let clip = Self::preview_path(input_path, input_base_dir, &output_base, name, "preview.mp4")?;
+                let start = Self::preview_start(timestamp, seconds, info.duration);
+                self.render(&mut guard, input_path, &clip, Self::preview_args(input_path, start, seconds, size, tone_map, &clip)).await?;
+                created.push(clip);
+            }
+        }
+
+        info!(
+            "Created {} video previews for {}",
+            created.len(),
+            input_path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        Ok(created)
+    }
+
+    /// Esegue ffmpeg per un output, con le date del video originale
+    async fn render(&self, guard: &mut TempFileGuard, input_path: &Path, output_path: &Path, args: Vec<String>) -> Result<()> {
+        if let Some(parent) = output_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        guard.track(output_path.to_path_buf());
+        ffmpeg::capture(&args).await?;
+        FileManager::copy_file_attributes(input_path, output_path, self.config.preserve_ownership).await?;
+        guard.keep(output_path);
+        Ok(())
+    }
+
+    /// Primo istante candidato con un frame non nero
+    async fn poster_timestamp(&mut self, input_path: &Path, duration: f64) -> Result<f64> {
+        let candidates = Self::candidate_timestamps(duration);
+        for &timestamp in &candidates {
+            if self.should_stop() {
+                return Err(OptimizeError::Cancelled("Video thumbnail creation".to_string()).into());
+            }
+            let luma = ffmpeg::capture(&Self::sample_args(input_path, timestamp)).await?;
+            if !Self::is_black_frame(&luma) {
+                return Ok(timestamp);
+            }
+            debug!("Black frame at {:.2}s in {}, trying the next one", timestamp, input_path.display());
+        }
+        Ok(candidates[0])
+    }
+
+    /// Istanti da campionare per il poster (0 se la durata è sconosciuta)
+    pub fn candidate_timestamps(duration: f64) -> Vec<f64> {
+        if duration <= 0.0 {
+            return vec![0.0];
+        }
+        POSTER_CANDIDATES.iter()
+            .map(|fraction| (duration * fraction * 1000.0).round() / 1000.0)
+            .collect()
+    }
+
+    /// Un frame in scala di grigi è nero se quasi tutti i pixel sono scuri.
+    /// Un frame vuoto (istante oltre la fine) non è utilizzabile.
+    pub fn is_black_frame(luma: &[u8]) -> bool {
+        if luma.is_empty() {
+            return true;
+        }
+        let dark = luma.iter().filter(|&&value| value <= BLACK_LUMA_THRESHOLD).count();
+        dark as f64 >= luma.len() as f64 * BLACK_PIXEL_RATIO
+    }
+
+    /// Inizio della clip: dal poster, anticipato se la clip supererebbe la fine
+    pub fn preview_start(poster_timestamp: f64, seconds: f64, duration: f64) -> f64 {
+        if duration <= 0.0 {
+            return poster_timestamp;
+        }
+        poster_timestamp.min(duration - seconds).max(0.0)
+    }
+
+    /// Argomenti per un frame ridotto in scala di grigi su stdout
+    fn sample_args(input_path: &Path, timestamp: f64) -> Vec<String> {
+        to_string_vec([
+            "-ss", &format!("{:.3}", timestamp),
+            "-i", &input_path.to_string_lossy(),
+            "-frames:v", "1",
+            "-vf", &format!("scale={}:{},format=gray", SAMPLE_WIDTH, SAMPLE_HEIGHT),
+            "-f", "rawvideo",
+            "pipe:1",
+        ])
+    }
+
+    /// Filtro di scala stile `ResizeMode::Fit`, senza upscale
+    fn scale_filter(size: &ThumbnailSize, even: bool, tone_map: bool) -> String {
+        let mut filter = if tone_map { format!("{},", TONE_MAP_FILTER) } else { String::new() };
+        filter.push_str(&format!(
+            "scale='min({},iw)':'min({},ih)':force_original_aspect_ratio=decrease",
+            size.width, size.height
+        ));
+        if even {
+            filter.push_str(":force_divisible_by=2");
+        }
+        filter
+    }
+
+    fn poster_extension(&self) -> &'static str {
+        if self.config.convert_to_webp { "poster.webp" } else { "poster.jpg" }
+    }
+
+    /// Argomenti ffmpeg per il poster (`tone_map`: sorgente HDR da convertire in SDR)
+    pub fn poster_args(config: &Config, input_path: &Path, timestamp: f64, size: &ThumbnailSize, tone_map: bool, output_path: &Path) -> Vec<String> {
+        let mut args = to_string_vec([
+            "-ss", &format!("{:.3}", timestamp),
+            "-i", &input_path.to_string_lossy(),
+            "-frames:v", "1",
+            "-update", "1",
+            "-vf", &Self::scale_filter(size, false, tone_map),
+        ]);
+        if config.convert_to_webp {
+            args.extend(to_string_vec(["-c:v", "libwebp", "-quality", &config.webp_quality.to_string()]));
+        } else {
+            args.extend(to_string_vec(["-q:v", "2"]));
+        }
+        args.extend(to_string_vec(["-map_metadata", "-1", "-y", &output_path.to_string_lossy()]));
+        args
+    }
+
+    /// Argomenti ffmpeg per la clip muta (H.264, riproducibile ovunque in `<video loop muted>`)
+    pub fn preview_args(input_path: &Path, start: f64, seconds: f64, size: &ThumbnailSize, tone_map: bool, output_path: &Path) -> Vec<String> {
+        to_string_vec([
+            "-ss", &format!("{:.3}", start),
+            "-t", &format!("{:.3}", seconds),
+            "-i", &input_path.to_string_lossy(),
+            "-map", "0:v:0",
+            "-an", "-sn", "-dn",
+            "-vf", &Self::scale_filter(size, true, tone_map),
+            "-c:v", "libx264",
+            "-preset", "veryfast",
+            "-crf", "28",
+            "-pix_fmt", "yuv420p",
+            "-movflags", "+faststart",
+            "-map_metadata", "-1",
+            "-y", &output_path.to_string_lossy(),
+        ])
+    }
+
+    /// `output_base/thumbnails/<name>/<relative_path>/<stem>.<suffix>`
+    pub fn preview_path(
+        input_path: &Path,
+        input_base_dir: &Path,
+        output_base: &Path,
+        thumbnail_name: &str,
+        suffix: &str,
+    ) -> Result<PathBuf> {
+        let file_stem = input_path.file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("Invalid file name: {:?}", input_path))?;
+        let relative_path = input_path
+            .strip_prefix(input_base_dir)
+            .unwrap_or(input_path)
+            .parent()
+            .unwrap_or(Path::new(""));
+
+        Ok(output_base
+            .join("thumbnails")
+            .join(thumbnail_name)
+            .join(relative_path)
+            .join(format!("{}.{}", file_stem, suffix)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poster_frame_selection() {
+        assert_eq!(VideoThumbnailer::candidate_timestamps(0.0), vec![0.0]);
+        assert_eq!(VideoThumbnailer::candidate_timestamps(60.0), vec![6.0, 15.0, 30.0, 45.0]);
+
+        assert!(VideoThumbnailer::is_black_frame(&[]));
+        assert!(VideoThumbnailer::is_black_frame(&[16u8; 576]));
+        // Un titolo bianco su nero (oltre il 2% dei pixel) non è un frame nero
+        let mut title = vec![16u8; 576];
+        title[..20].fill(235);
+        assert!(!VideoThumbnailer::is_black_frame(&title));
+        assert!(!VideoThumbnailer::is_black_frame(&[120u8; 576]));
+
+        assert_eq!(VideoThumbnailer::preview_start(6.0, 3.0, 60.0), 6.0);
+        assert_eq!(VideoThumbnailer::preview_start(4.5, 3.0, 6.0), 3.0);
+        assert_eq!(VideoThumbnailer::preview_start(0.2, 3.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn test_preview_args_and_paths() {
+        let input = Path::new("/media/trip/IMG_0001.MOV");
+        let size = ThumbnailSize::new(800, 600);
+        let poster = VideoThumbnailer::preview_path(input, Path::new("/media"), Path::new("/out"), "gallery", "poster.jpg").unwrap();
+        assert_eq!(poster, PathBuf::from("/out/thumbnails/gallery/trip/IMG_0001.poster.jpg"));
+
+        let args = VideoThumbnailer::poster_args(&Config::default(), input, 6.0, &size, false, &poster);
+        assert_eq!(&args[..2], ["-ss", "6.000"]);
+        assert!(args.windows(2).any(|w| w == ["-vf", "scale='min(800,iw)':'min(600,ih)':force_original_aspect_ratio=decrease"]));
+        assert!(args.windows(2).any(|w| w == ["-q:v", "2"]));
+
+        let webp = Config { convert_to_webp: true, ..Default::default() };
+        let args = VideoThumbnailer::poster_args(&webp, input, 6.0, &size, false, &poster);
+        assert!(args.windows(2).any(|w| w == ["-c:v", "libwebp"]));
+
+        // HDR source: tone mapping before the scaler
+        let args = VideoThumbnailer::poster_args(&Config::default(), input, 6.0, &size, true, &poster);
+        assert!(args.iter().any(|a| a.starts_with(TONE_MAP_FILTER) && a.ends_with("force_original_aspect_ratio=decrease")));
+
+        let clip = PathBuf::from("/out/thumbnails/gallery/trip/IMG_0001.preview.mp4");
+        let args = VideoThumbnailer::preview_args(input, 6.0, 3.0, &size, false, &clip);
+        assert!(args.contains(&"-an".to_string()));
+        assert!(args.windows(2).any(|w| w == ["-t", "3.000"]));
+        assert!(args.iter().any(|a| a.ends_with("force_divisible_by=2")));
+        assert_eq!(args.last().unwrap(), "/out/thumbnails/gallery/trip/IMG_0001.preview.mp4");
+    }
+}
@@ -0,0 +1,232 @@
+//! # Video Trim Module
+//!
+//! Taglio dei tratti "morti" all'inizio e alla fine dei video, tipici delle
+//! registrazioni col telefono (secondi di tasca prima e dopo la scena).
+//!
+//! ## Responsabilità:
+//! - Argomenti del passaggio di analisi con `blackdetect` e `silencedetect` ([`analysis_args`])
+//! - Lettura degli intervalli rilevati dal log di ffmpeg ([`parse_dead_segments`])
+//! - Calcolo dei punti di taglio e argomenti `-ss`/`-t` della codifica ([`TrimPoints`])
+//!
+//! ## Rilevamento:
+//! - Nero: il 98% dei pixel sotto il 10% di luminanza per almeno 0.5 s
+//! - Silenzio: audio sotto -50 dB per almeno 0.5 s (prima traccia audio)
+//! - Con `any` gli intervalli dei due filtri vengono uniti
+//!
+//! Vengono tagliati solo i tratti che toccano l'inizio o la fine del video
+//! e lunghi almeno [`MIN_TRIM_SECONDS`]: quelli a metà video restano. Un video
+//! interamente nero o silenzioso non viene tagliato.
+//!
+//! L'analisi decodifica il video una volta in più ed è quindi opt-in
+//! (`config.video_trim`). Il taglio si applica alla codifica del video: il
+//! packaging HLS/DASH, i poster e gli storyboard usano la sorgente intera.
+
+use crate::config::TrimMode;
+use crate::utils::to_string_vec;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::debug;
+
+/// Filtro di rilevamento del nero
+const BLACK_FILTER: &str = "blackdetect=d=0.5:pic_th=0.98:pix_th=0.10";
+
+/// Filtro di rilevamento del silenzio
+const SILENCE_FILTER: &str = "silencedetect=noise=-50dB:d=0.5";
+
+/// Durata minima di un taglio, in secondi (tratti più brevi restano)
+pub const MIN_TRIM_SECONDS: f64 = 1.0;
+
+/// Durata minima della parte mantenuta, in secondi
+const MIN_KEPT_SECONDS: f64 = 1.0;
+
+/// Distanza massima dall'inizio/fine perché un tratto li "tocchi": l'ultimo
+/// frame nero finisce una durata di frame prima della fine del file
+const EDGE_TOLERANCE: f64 = 0.5;
+
+/// Pausa massima tra due tratti morti consecutivi perché vengano uniti
+const MERGE_GAP: f64 = 0.1;
+
+/// Parte del video mantenuta dopo il taglio
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrimPoints {
+    /// Inizio della parte mantenuta, in secondi dalla sorgente
+    pub start: f64,
+    /// Fine della parte mantenuta, in secondi dalla sorgente
+    pub end: f64,
+    /// Durata della sorgente
+    pub source_duration: f64,
+}
+
+impl TrimPoints {
+    /// Calcola i punti di taglio dai tratti morti rilevati.
+    ///
+    /// # Returns
+    /// * `Option<Self>` - None quando non c'è nulla da tagliare, o quando
+    ///   resterebbe meno di un secondo di video
+    pub fn find(segments: &[(f64, f64)], duration: f64) -> Option<Self> {
+        if duration <= 0.0 {
+            return None;
+        }
+
+        let mut sorted = segments.to_vec();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64)> = Vec::new();
+        for (start, end) in sorted {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + MERGE_GAP => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let start = merged.first()
+            .filter(|(start, _)| *start <= EDGE_TOLERANCE)
+            .map(|(_, end)| end.min(duration))
+            .filter(|lead| *lead >= MIN_TRIM_SECONDS)
+            .unwrap_or(0.0);
+        let end = merged.last()
+            .filter(|(_, end)| *end >= duration - EDGE_TOLERANCE)
+            .map(|(start, _)| start.max(0.0))
+            .filter(|tail| duration - tail >= MIN_TRIM_SECONDS)
+            .unwrap_or(duration);
+
+        if start == 0.0 && end == duration {
+            return None;
+        }
+        if end - start < MIN_KEPT_SECONDS {
+            debug!("Dead segments cover the whole video ({:.1}s): not trimming", duration);
+            return None;
+        }
+        Some(Self { start, end, source_duration: duration })
+    }
+
+    /// Durata della parte mantenuta
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+
+    /// Secondi tagliati in totale
+    pub fn removed(&self) -> f64 {
+        self.source_duration - self.duration()
+    }
+
+    /// Opzioni di input, da anteporre a `-i` (seek preciso in ricodifica)
+    pub fn input_args(&self) -> Vec<String> {
+        if self.start > 0.0 {
+            to_string_vec(["-ss", &format!("{:.3}", self.start)])
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Opzioni di output: durata della parte mantenuta
+    pub fn output_args(&self) -> Vec<String> {
+        if self.end < self.source_duration {
+            to_string_vec(["-t", &format!("{:.3}", self.duration())])
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Argomenti del passaggio di analisi (output scartato con `-f null`).
+///
+/// # Returns
+/// * `Option<Vec<String>>` - None quando non c'è nulla da analizzare
+///   (modalità `silence` su un video senza audio)
+pub fn analysis_args(mode: TrimMode, input_path: &Path, has_audio: bool) -> Option<Vec<String>> {
+    let black = mode != TrimMode::Silence;
+    let silence = mode != TrimMode::Black && has_audio;
+    if !black && !silence {
+        return None;
+    }
+
+    let mut args = to_string_vec(["-i", input_path.to_str().unwrap()]);
+    if black {
+        args.extend(to_string_vec(["-map", "0:v:0", "-vf", BLACK_FILTER]));
+    }
+    if silence {
+        args.extend(to_string_vec(["-map", "0:a:0", "-af", SILENCE_FILTER]));
+    }
+    args.extend(to_string_vec(["-f", "null", "-"]));
+    Some(args)
+}
+
+/// Legge i tratti neri e silenziosi dal log di `blackdetect`/`silencedetect`.
+///
+/// Un silenzio ancora aperto a fine file (`silence_start` senza
+/// `silence_end`) arriva fino a `duration`.
+pub fn parse_dead_segments(log: &str, duration: f64) -> Vec<(f64, f64)> {
+    let mut segments = Vec::new();
+    let mut silence_start = None;
+    for line in log.lines() {
+        if let (Some(start), Some(end)) = (field(line, "black_start:"), field(line, "black_end:")) {
+            segments.push((start, end));
+        } else if let Some(start) = field(line, "silence_start:") {
+            silence_start = Some(start);
+        } else if let Some(end) = field(line, "silence_end:") {
+            segments.push((silence_start.take().unwrap_or(0.0), end));
+        }
+    }
+    if let Some(start) = silence_start {
+        segments.push((start, duration));
+    }
+    segments
+}
+
+/// Valore numerico che segue `key` in una riga del log (es. "black_end:2.5")
+fn field(line: &str, key: &str) -> Option<f64> {
+    let rest = line[line.find(key)? + key.len()..].trim_start();
+    let value = rest.split(|c: char| c.is_whitespace() || c == '|').next()?;
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dead_segments() {
+        let log = "\
+[blackdetect @ 0x5581] black_start:0 black_end:3.2 black_duration:3.2
+[silencedetect @ 0x5582] silence_start: 0
+[silencedetect @ 0x5582] silence_end: 2.75 | silence_duration: 2.75
+frame= 1200 fps=240 q=-0.0 size=N/A time=00:00:40.00
+[blackdetect @ 0x5581] black_start:55.1 black_end:60 black_duration:4.9
+[silencedetect @ 0x5582] silence_start: 57.5";
+        assert_eq!(parse_dead_segments(log, 60.0), vec![(0.0, 3.2), (0.0, 2.75), (55.1, 60.0), (57.5, 60.0)]);
+    }
+
+    #[test]
+    fn test_trim_points() {
+        // Pocket footage at both ends: keep 3.2s-55.1s
+        let trim = TrimPoints::find(&[(0.0, 3.2), (0.0, 2.75), (55.1, 59.8), (57.5, 60.0)], 60.0).unwrap();
+        assert_eq!((trim.start, trim.end), (3.2, 55.1));
+        assert!((trim.removed() - 8.1).abs() < 1e-9);
+        assert_eq!(trim.input_args(), ["-ss", "3.200"]);
+        assert_eq!(trim.output_args(), ["-t", "51.900"]);
+
+        // Adjacent black and silence are chained; a dead middle is kept
+        let trim = TrimPoints::find(&[(0.0, 1.5), (1.55, 4.0), (20.0, 30.0)], 60.0).unwrap();
+        assert_eq!((trim.start, trim.end), (4.0, 60.0));
+        assert!(trim.output_args().is_empty());
+
+        // Too short to matter, not at the edges, or the whole video
+        assert_eq!(TrimPoints::find(&[(0.0, 0.6), (59.5, 60.0)], 60.0), None);
+        assert_eq!(TrimPoints::find(&[(10.0, 20.0)], 60.0), None);
+        assert_eq!(TrimPoints::find(&[(0.0, 60.0)], 60.0), None);
+        assert_eq!(TrimPoints::find(&[(0.0, 5.0)], 0.0), None);
+    }
+
+    #[test]
+    fn test_analysis_args() {
+        let input = Path::new("/v/clip.mp4");
+        let args = analysis_args(TrimMode::Any, input, true).unwrap();
+        assert_eq!(&args[2..6], ["-map", "0:v:0", "-vf", BLACK_FILTER]);
+        assert!(args.windows(2).any(|w| w == ["-af", SILENCE_FILTER]));
+        assert_eq!(&args[args.len() - 3..], ["-f", "null", "-"]);
+
+        // Silent video: only the black detector runs
+        assert!(!analysis_args(TrimMode::Any, input, false).unwrap().contains(&"-af".to_string()));
+        assert_eq!(analysis_args(TrimMode::Silence, input, false), None);
+    }
+}